@@ -0,0 +1,53 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use subtext::{
+    block::Block,
+    primitive::Entity,
+    render::{render_terminal, LinkTarget, TerminalOptions},
+};
+
+pub fn main() {
+    let args = env::args();
+
+    if args.len() < 2 {
+        println!("Please provide the path to a .subtext file!");
+        process::exit(1);
+    }
+
+    let path = env::args().next_back().unwrap();
+    let directory = fs::canonicalize(&path)
+        .ok()
+        .and_then(|path| path.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+
+    // Link slashlinks to the notes next to this one
+    let resolve = |target: &LinkTarget| match target {
+        LinkTarget::SlashLink(slashlink) => Some(
+            directory
+                .join(format!("{}.subtext", &slashlink[1..]))
+                .display()
+                .to_string(),
+        ),
+        LinkTarget::WikiLink(_) => None,
+    };
+
+    match fs::read(&path)
+        .map_err(|error| error.into())
+        .and_then(|buffer| subtext::parse(&buffer))
+    {
+        Ok(blocks) => {
+            let blocks: Vec<Block<Entity>> = blocks.collect();
+            println!(
+                "{}",
+                render_terminal(&blocks, &resolve, &TerminalOptions::default())
+            );
+        }
+        Err(error) => {
+            println!("Error: {}", error);
+            process::exit(1);
+        }
+    }
+}
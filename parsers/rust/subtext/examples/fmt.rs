@@ -0,0 +1,27 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use subtext::format::{format, FormatOptions};
+
+pub fn main() {
+    let args = env::args();
+
+    if args.len() < 2 {
+        println!("Please provide the path to a .subtext file!");
+        process::exit(1);
+    }
+
+    let path = env::args().next_back().unwrap();
+
+    match fs::read(&path)
+        .map_err(|error| error.into())
+        .and_then(|buffer| format(&buffer, &FormatOptions::default()))
+    {
+        Ok(formatted) => print!("{}", formatted),
+        Err(error) => {
+            println!("Error: {}", error);
+            process::exit(1);
+        }
+    }
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::double_ended_iterator_last)]
+
 use std::env;
 use std::fs;
 use std::path::Path;
@@ -6,6 +8,7 @@ use std::process;
 use subtext::block::Block;
 use subtext::parse;
 use subtext::primitive::Entity;
+use subtext::sexpr::blocks_to_sexpr;
 use tendril::fmt::Slice;
 
 pub fn main() {
@@ -26,7 +29,7 @@ pub fn main() {
             // let result = parse(buffer.as_bytes());
             let result: Vec<Block<Entity>> = parse(buffer.as_bytes()).unwrap().collect();
 
-            println!("Parsed blocks: {:#?}", result);
+            println!("Parsed blocks:\n{}", blocks_to_sexpr(&result));
         }
         Err(error) => {
             println!("Error: {}", error);
@@ -0,0 +1,103 @@
+//! Stable identifiers for blocks, and resolution of slashlink fragments
+//! (`/evolution#b3f9c1`, `/evolution#questions`) to the blocks they target.
+use std::{collections::HashMap, fmt::Display, ops::Range};
+
+use sha2::{Digest, Sha256};
+
+use crate::{block::Block, primitive::Entity, util::to_slug};
+
+/// The number of hex digits of the content hash used in a block ID
+const BLOCK_ID_LENGTH: usize = 6;
+
+/// A short identifier for a block, derived from a hash of its text content.
+/// When several blocks in a document hash to the same value, the second and
+/// subsequent ones are disambiguated by their order of appearance, e.g.
+/// `b3f9c1`, `b3f9c1-2`, `b3f9c1-3`. This keeps IDs stable across edits to
+/// unrelated blocks, and across changes to the block's sigil.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockId(String);
+
+impl BlockId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for BlockId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The part of a document that a fragment refers to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Anchor {
+    /// A single block, by index
+    Block(usize),
+    /// A header and the blocks that follow it, up to the next header
+    Section(Range<usize>),
+}
+
+fn content_hash(text: &str) -> String {
+    Sha256::digest(text.trim().as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>()[..BLOCK_ID_LENGTH]
+        .to_owned()
+}
+
+/// Compute the IDs of every block in a document. Blank blocks cannot be
+/// linked to, so they have no ID.
+pub fn block_ids<E>(blocks: &[Block<E>]) -> Vec<Option<BlockId>>
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let mut occurrences = HashMap::<String, usize>::new();
+
+    blocks
+        .iter()
+        .map(|block| match block {
+            Block::Blank(_) => None,
+            _ => {
+                let hash = content_hash(&block.to_text_content());
+                let count = occurrences.entry(hash.clone()).or_insert(0);
+                *count += 1;
+
+                Some(BlockId(match count {
+                    1 => hash,
+                    count => format!("{}-{}", hash, count),
+                }))
+            }
+        })
+        .collect()
+}
+
+/// Find the blocks a fragment refers to. The fragment is first matched
+/// against block IDs, then against the slugified text of each header.
+pub fn resolve<E>(blocks: &[Block<E>], fragment: &str) -> Option<Anchor>
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let fragment = fragment.trim_start_matches('#');
+
+    if let Some(index) = block_ids(blocks)
+        .iter()
+        .position(|id| matches!(id, Some(id) if id.as_str() == fragment))
+    {
+        return Some(Anchor::Block(index));
+    }
+
+    let section = to_slug(fragment).ok()?;
+    let start = blocks.iter().position(|block| match block {
+        Block::Header(_) => to_slug(&block.to_text_content()).ok().as_ref() == Some(&section),
+        _ => false,
+    })?;
+    let end = blocks
+        .iter()
+        .skip(start + 1)
+        .position(|block| matches!(block, Block::Header(_)))
+        .map(|offset| start + 1 + offset)
+        .unwrap_or(blocks.len());
+
+    Some(Anchor::Section(start..end))
+}
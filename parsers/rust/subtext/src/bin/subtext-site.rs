@@ -0,0 +1,42 @@
+use std::env;
+use std::path::PathBuf;
+use std::process;
+
+use subtext::site::{build, SiteOptions};
+
+pub fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+
+    if args.len() < 2 {
+        println!("Usage: subtext-site <notes directory> <output directory> [site title]");
+        process::exit(1);
+    }
+
+    let options = SiteOptions {
+        source: PathBuf::from(&args[0]),
+        output: PathBuf::from(&args[1]),
+        title: args.get(2).cloned().unwrap_or_else(|| "Notes".into()),
+    };
+
+    match build(&options) {
+        Ok(build) => {
+            println!(
+                "Wrote {} pages ({} unchanged, {} removed)",
+                build.written.len(),
+                build.unchanged.len(),
+                build.removed.len()
+            );
+
+            if !build.broken_links.is_empty() {
+                println!("\nBroken links:");
+                for broken in &build.broken_links {
+                    println!("  {}: {}", broken.page, broken.link);
+                }
+            }
+        }
+        Err(error) => {
+            println!("Error: {:#}", error);
+            process::exit(1);
+        }
+    }
+}
@@ -156,3 +156,15 @@ pub fn parse<E: From<Entity> + AsRef<Entity>>(
         length + 1,
     ))
 }
+
+/// Group contiguous blocks of the same type. This may be useful if you want
+/// to move or manipulate contiguous ranges of blocks together, such as a
+/// series of list items.
+pub fn group_blocks<E>(blocks: &[Block<E>]) -> Vec<&[Block<E>]>
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    blocks
+        .chunk_by(|a, b| std::mem::discriminant(a) == std::mem::discriminant(b))
+        .collect()
+}
@@ -0,0 +1,336 @@
+//! A builder for generating Subtext from code without hand-writing sigils.
+//!
+//! ```
+//! use subtext::builder::Document;
+//!
+//! let document = Document::builder()
+//!     .header("Evolution")
+//!     .paragraph(|p| p.text("See ").slashlink("punctuated-equilibrium"))
+//!     .build()
+//!     .unwrap();
+//!
+//! assert_eq!(
+//!     document.to_string(),
+//!     "# Evolution\nSee /punctuated-equilibrium"
+//! );
+//! ```
+//!
+//! Every block is checked by parsing it back; content that the parser would
+//! read differently than intended (a paragraph starting with `#`, a
+//! slashlink containing a space, text containing a newline...) causes
+//! [DocumentBuilder::build] to return an error.
+use std::fmt::Display;
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    block::Block,
+    primitive::{peer_link_length, Entity},
+    str::SharedString,
+};
+
+/// A sequence of blocks that is guaranteed to reparse to the same blocks
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    blocks: Vec<Block<Entity>>,
+}
+
+impl Document {
+    pub fn builder() -> DocumentBuilder {
+        DocumentBuilder::default()
+    }
+
+    pub fn blocks(&self) -> &[Block<Entity>] {
+        &self.blocks
+    }
+
+    pub fn into_blocks(self) -> Vec<Block<Entity>> {
+        self.blocks
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_string().into_bytes()
+    }
+
+    /// Print the document in the s-expression notation of the
+    /// specification; see [crate::sexpr]
+    pub fn to_sexpr(&self) -> String {
+        crate::sexpr::blocks_to_sexpr(&self.blocks)
+    }
+
+    /// Read a document from the s-expression notation, checking that every
+    /// block would be parsed as written
+    pub fn from_sexpr(input: &str) -> Result<Document> {
+        Ok(Document {
+            blocks: crate::sexpr::blocks_from_sexpr(input)?,
+        })
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines = self
+            .blocks
+            .iter()
+            .map(|block| block.to_string())
+            .collect::<Vec<String>>();
+
+        write!(f, "{}", lines.join("\n"))?;
+
+        // A terminating newline is dissolved by the parser, so an empty
+        // trailing blank needs one more to survive a round trip
+        match self.blocks.last() {
+            Some(block @ Block::Blank(_)) if block.to_string().is_empty() => writeln!(f),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The parts of a line of inline content, as the caller intended them
+#[derive(Debug, Clone, PartialEq)]
+enum Inline {
+    Text(String),
+    SlashLink(String),
+    HyperLink(String),
+    WikiLink(String),
+}
+
+/// Builds the inline content of a single block
+#[derive(Debug, Default)]
+pub struct InlineBuilder {
+    parts: Vec<Inline>,
+    error: Option<anyhow::Error>,
+}
+
+impl InlineBuilder {
+    fn fail(mut self, error: anyhow::Error) -> Self {
+        self.error.get_or_insert(error);
+        self
+    }
+
+    /// Append plain text
+    pub fn text(mut self, text: &str) -> Self {
+        if text.contains(['\n', '\r']) {
+            return self.fail(anyhow!("Text may not contain a line break: {:?}", text));
+        }
+
+        match self.parts.last_mut() {
+            Some(Inline::Text(previous)) => previous.push_str(text),
+            _ => self.parts.push(Inline::Text(text.to_owned())),
+        }
+
+        self
+    }
+
+    /// Append a slashlink to the given slug; the leading `/` is optional
+    pub fn slashlink(mut self, slug: &str) -> Self {
+        let slug = slug.trim_start_matches('/');
+
+        if slug.is_empty() {
+            return self.fail(anyhow!("A slashlink must have a slug"));
+        }
+
+        if let Some(invalid) = slug
+            .chars()
+            .find(|c| !matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '/'))
+        {
+            return self.fail(anyhow!(
+                "Slashlink /{} contains the invalid character {:?}",
+                slug,
+                invalid
+            ));
+        }
+
+        self.parts.push(Inline::SlashLink(format!("/{}", slug)));
+        self
+    }
+
+    /// Append a peer-qualified slashlink (`@cdata/foo`) or a bare peer
+    /// mention (`@cdata`); the leading `@` is optional
+    pub fn peer_link(mut self, link: &str) -> Self {
+        let link = format!("@{}", link.trim_start_matches('@'));
+
+        if peer_link_length(&link) != Some(link.len()) {
+            return self.fail(anyhow!("Invalid peer link: {:?}", link));
+        }
+
+        self.parts.push(Inline::SlashLink(link));
+        self
+    }
+
+    /// Append a bare `http` or `https` URL
+    pub fn hyperlink(mut self, url: &str) -> Self {
+        if !(url.starts_with("http://") || url.starts_with("https://")) {
+            return self.fail(anyhow!("Only http and https URLs are autolinked: {}", url));
+        }
+
+        if url.contains(char::is_whitespace) {
+            return self.fail(anyhow!("A URL may not contain whitespace: {:?}", url));
+        }
+
+        self.parts.push(Inline::HyperLink(url.to_owned()));
+        self
+    }
+
+    /// Append a `[[wikilink]]` with the given label
+    pub fn wikilink(mut self, label: &str) -> Self {
+        if label.contains(['\n', '\r']) || label.contains("]]") {
+            return self.fail(anyhow!("Invalid wikilink label: {:?}", label));
+        }
+
+        self.parts.push(Inline::WikiLink(format!("[[{}]]", label)));
+        self
+    }
+
+    fn to_line(&self) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Inline::Text(text)
+                | Inline::SlashLink(text)
+                | Inline::HyperLink(text)
+                | Inline::WikiLink(text) => text.as_str(),
+            })
+            .collect()
+    }
+}
+
+/// Builds a [Document] one block at a time
+#[derive(Debug, Default)]
+pub struct DocumentBuilder {
+    blocks: Vec<Block<Entity>>,
+    escape_sigils: bool,
+    error: Option<anyhow::Error>,
+}
+
+impl DocumentBuilder {
+    /// When enabled, a paragraph that would begin with a sigil character is
+    /// indented by a single space so that it is still read as a paragraph.
+    /// Otherwise, such a paragraph is an error.
+    pub fn escape_sigils(mut self, escape_sigils: bool) -> Self {
+        self.escape_sigils = escape_sigils;
+        self
+    }
+
+    /// Append a header with plain text content
+    pub fn header(self, text: &str) -> Self {
+        self.push(Some('#'), |h| h.text(text))
+    }
+
+    /// Append a header whose content may include links
+    pub fn header_with<F>(self, content: F) -> Self
+    where
+        F: FnOnce(InlineBuilder) -> InlineBuilder,
+    {
+        self.push(Some('#'), content)
+    }
+
+    pub fn paragraph<F>(self, content: F) -> Self
+    where
+        F: FnOnce(InlineBuilder) -> InlineBuilder,
+    {
+        self.push(None, content)
+    }
+
+    pub fn list<F>(self, content: F) -> Self
+    where
+        F: FnOnce(InlineBuilder) -> InlineBuilder,
+    {
+        self.push(Some('-'), content)
+    }
+
+    pub fn quote<F>(self, content: F) -> Self
+    where
+        F: FnOnce(InlineBuilder) -> InlineBuilder,
+    {
+        self.push(Some('>'), content)
+    }
+
+    pub fn blank(mut self) -> Self {
+        self.blocks
+            .push(Block::Blank(Entity::EmptySpace(SharedString::from(""))));
+        self
+    }
+
+    pub fn build(self) -> Result<Document> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(Document {
+                blocks: self.blocks,
+            }),
+        }
+    }
+
+    fn push<F>(mut self, sigil: Option<char>, content: F) -> Self
+    where
+        F: FnOnce(InlineBuilder) -> InlineBuilder,
+    {
+        if self.error.is_some() {
+            return self;
+        }
+
+        match self.build_block(sigil, content) {
+            Ok(block) => self.blocks.push(block),
+            Err(error) => self.error = Some(error),
+        }
+
+        self
+    }
+
+    fn build_block<F>(&self, sigil: Option<char>, content: F) -> Result<Block<Entity>>
+    where
+        F: FnOnce(InlineBuilder) -> InlineBuilder,
+    {
+        let inline = content(InlineBuilder::default());
+
+        if let Some(error) = inline.error {
+            return Err(error);
+        }
+
+        let text = inline.to_line();
+        let line = match sigil {
+            Some(sigil) if text.is_empty() => sigil.to_string(),
+            Some(sigil) => format!("{} {}", sigil, text),
+            None if text.trim().is_empty() => {
+                return Err(anyhow!("A paragraph must have some content"))
+            }
+            None if text.starts_with(['#', '>', '-']) => match self.escape_sigils {
+                true => format!(" {}", text),
+                false => {
+                    return Err(anyhow!(
+                        "Paragraph would be read as a sigil block: {:?}",
+                        text
+                    ))
+                }
+            },
+            None => text,
+        };
+
+        let (block, _) = crate::block::parse::<Entity>(SharedString::from(line.as_str()))
+            .map_err(|_| anyhow!("Could not parse {:?}", line))?;
+
+        if as_inline(&block) != inline.parts {
+            return Err(anyhow!("Content would be misread by a parser: {:?}", line));
+        }
+
+        Ok(block)
+    }
+}
+
+/// Recover the intended inline parts from a parsed block, for comparison
+fn as_inline(block: &Block<Entity>) -> Vec<Inline> {
+    let mut parts = Vec::new();
+
+    for entity in block.to_content_entities() {
+        let text = entity.to_string();
+        match (entity, parts.last_mut()) {
+            (Entity::SlashLink(_), _) => parts.push(Inline::SlashLink(text)),
+            (Entity::HyperLink(_), _) => parts.push(Inline::HyperLink(text)),
+            (Entity::WikiLink(_), _) => parts.push(Inline::WikiLink(text)),
+            (_, Some(Inline::Text(previous))) => previous.push_str(&text),
+            _ => parts.push(Inline::Text(text)),
+        }
+    }
+
+    parts
+}
@@ -0,0 +1,357 @@
+//! Content identifiers (CIDs) as used by IPFS, for slashlinks that refer to
+//! an immutable version of a document, e.g. `/bafkrei...`.
+//!
+//! Only the subset of multibase, multicodec and multihash needed to parse,
+//! validate and print CIDs is implemented here.
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+
+const BASE32_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Multicodec code for raw binary content
+pub const RAW_CODEC: u64 = 0x55;
+/// Multicodec code for a DAG-PB node (the only codec CIDv0 can express)
+pub const DAG_PB_CODEC: u64 = 0x70;
+/// Multihash code for SHA2-256
+pub const SHA2_256_CODE: u64 = 0x12;
+
+/// The length of a base58-encoded CIDv0, which always begins with `Qm`
+const CID_V0_LENGTH: usize = 46;
+
+/// A self-describing content identifier
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cid {
+    pub version: u64,
+    pub codec: u64,
+    pub hash_code: u64,
+    pub digest: Vec<u8>,
+}
+
+impl Cid {
+    /// Compute the CIDv1 of a serialized Subtext document, using the raw
+    /// codec and a SHA2-256 multihash
+    pub fn of_document(bytes: &[u8]) -> Cid {
+        Cid {
+            version: 1,
+            codec: RAW_CODEC,
+            hash_code: SHA2_256_CODE,
+            digest: Sha256::digest(bytes).to_vec(),
+        }
+    }
+
+    /// A human-readable name for the content codec, if it is a common one
+    pub fn codec_name(&self) -> Option<&'static str> {
+        match self.codec {
+            RAW_CODEC => Some("raw"),
+            DAG_PB_CODEC => Some("dag-pb"),
+            0x71 => Some("dag-cbor"),
+            0x0129 => Some("dag-json"),
+            0x0200 => Some("json"),
+            0x72 => Some("libp2p-key"),
+            _ => None,
+        }
+    }
+
+    /// Whether the given string has the shape of a CID. This is only a cheap
+    /// filter: ordinary slugs can have the same shape, so a slug is treated
+    /// as a CID only if it also decodes as one.
+    pub fn is_candidate(input: &str) -> bool {
+        if !input.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return false;
+        }
+
+        (input.starts_with("Qm") && input.len() == CID_V0_LENGTH)
+            || ((input.starts_with("ba") || input.starts_with('z') || input.starts_with("f01"))
+                && input.len() >= CID_V0_LENGTH)
+    }
+
+    /// The binary representation of the CID. For CIDv0 this is just the
+    /// multihash.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        if self.version > 0 {
+            write_varint(self.version, &mut bytes);
+            write_varint(self.codec, &mut bytes);
+        }
+
+        write_varint(self.hash_code, &mut bytes);
+        write_varint(self.digest.len() as u64, &mut bytes);
+        bytes.extend_from_slice(&self.digest);
+        bytes
+    }
+
+    fn from_v1_bytes(bytes: &[u8]) -> Result<Cid> {
+        let mut cursor = bytes;
+
+        let version = read_varint(&mut cursor, "CID version")?;
+        if version != 1 {
+            return Err(anyhow!("Unsupported CID version {}", version));
+        }
+
+        let codec = read_varint(&mut cursor, "multicodec")?;
+        let (hash_code, digest) = read_multihash(cursor)?;
+
+        Ok(Cid {
+            version,
+            codec,
+            hash_code,
+            digest,
+        })
+    }
+}
+
+impl FromStr for Cid {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("Qm") {
+            if s.len() != CID_V0_LENGTH {
+                return Err(anyhow!(
+                    "A CIDv0 must be {} characters long, but {} is {}",
+                    CID_V0_LENGTH,
+                    s,
+                    s.len()
+                ));
+            }
+
+            let bytes = decode_base58(s)?;
+            let (hash_code, digest) = read_multihash(&bytes)?;
+
+            if hash_code != SHA2_256_CODE {
+                return Err(anyhow!("A CIDv0 must use a SHA2-256 multihash"));
+            }
+
+            return Ok(Cid {
+                version: 0,
+                codec: DAG_PB_CODEC,
+                hash_code,
+                digest,
+            });
+        }
+
+        if s.is_empty() {
+            return Err(anyhow!("A CID cannot be empty"));
+        }
+
+        Cid::from_v1_bytes(&decode_multibase(s)?)
+    }
+}
+
+/// Decode a multibase string in one of the bases this module supports
+pub(crate) fn decode_multibase(input: &str) -> Result<Vec<u8>> {
+    let mut chars = input.chars();
+
+    match chars.next() {
+        Some('b') => decode_base32(chars.as_str()),
+        Some('B') => decode_base32(&chars.as_str().to_lowercase()),
+        Some('z') => decode_base58(chars.as_str()),
+        Some('f') | Some('F') => decode_base16(chars.as_str()),
+        Some(prefix) => Err(anyhow!("Unsupported multibase prefix '{}'", prefix)),
+        None => Err(anyhow!("Multibase input cannot be empty")),
+    }
+}
+
+impl Display for Cid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.version {
+            0 => write!(f, "{}", encode_base58(&self.to_bytes())),
+            _ => write!(f, "b{}", encode_base32(&self.to_bytes())),
+        }
+    }
+}
+
+fn read_multihash(bytes: &[u8]) -> Result<(u64, Vec<u8>)> {
+    let mut cursor = bytes;
+    let hash_code = read_varint(&mut cursor, "multihash code")?;
+    let length = read_varint(&mut cursor, "multihash length")? as usize;
+
+    if cursor.len() != length {
+        return Err(anyhow!(
+            "Multihash declares a {} byte digest, but {} bytes follow",
+            length,
+            cursor.len()
+        ));
+    }
+
+    if hash_code == SHA2_256_CODE && length != 32 {
+        return Err(anyhow!(
+            "A SHA2-256 digest must be 32 bytes, but this one is {}",
+            length
+        ));
+    }
+
+    Ok((hash_code, cursor.to_vec()))
+}
+
+/// Read an unsigned LEB128 varint from the front of the cursor, advancing it
+pub(crate) fn read_varint(cursor: &mut &[u8], field: &str) -> Result<u64> {
+    let mut value = 0u64;
+
+    for (index, byte) in cursor.iter().enumerate() {
+        if index >= 9 {
+            return Err(anyhow!("Varint for {} is too long", field));
+        }
+
+        value |= ((byte & 0x7f) as u64) << (7 * index);
+
+        if byte & 0x80 == 0 {
+            *cursor = &cursor[index + 1..];
+            return Ok(value);
+        }
+    }
+
+    Err(anyhow!("Unexpected end of input while reading {}", field))
+}
+
+pub(crate) fn write_varint(mut value: u64, bytes: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn decode_base32(input: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0u32;
+
+    for (index, character) in input.char_indices() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|c| *c as char == character)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Invalid base32 character '{}' at position {}",
+                    character,
+                    index + 1
+                )
+            })? as u32;
+
+        buffer = (buffer << 5) | value;
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Ok(bytes)
+}
+
+fn encode_base32(bytes: &[u8]) -> String {
+    let mut output = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0u32;
+
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+
+        while bits >= 5 {
+            bits -= 5;
+            output.push(BASE32_ALPHABET[(buffer >> bits) as usize & 31] as char);
+        }
+    }
+
+    if bits > 0 {
+        output.push(BASE32_ALPHABET[(buffer << (5 - bits)) as usize & 31] as char);
+    }
+
+    output
+}
+
+fn decode_base58(input: &str) -> Result<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::new();
+
+    for (index, character) in input.char_indices() {
+        let mut carry = BASE58_ALPHABET
+            .iter()
+            .position(|c| *c as char == character)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Invalid base58 character '{}' at position {}",
+                    character,
+                    index + 1
+                )
+            })? as u32;
+
+        for byte in bytes.iter_mut().rev() {
+            carry += *byte as u32 * 58;
+            *byte = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+
+        while carry > 0 {
+            bytes.insert(0, (carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+
+    let leading_zeros = input.chars().take_while(|c| *c == '1').count();
+    let mut output = vec![0u8; leading_zeros];
+    output.append(&mut bytes);
+
+    Ok(output)
+}
+
+pub(crate) fn encode_base58(bytes: &[u8]) -> String {
+    let mut digits: Vec<u8> = Vec::new();
+
+    for byte in bytes {
+        let mut carry = *byte as u32;
+
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    let leading_zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+
+    std::iter::repeat_n('1', leading_zeros)
+        .chain(
+            digits
+                .iter()
+                .rev()
+                .map(|digit| BASE58_ALPHABET[*digit as usize] as char),
+        )
+        .collect()
+}
+
+fn decode_base16(input: &str) -> Result<Vec<u8>> {
+    if !input.is_ascii() || !input.len().is_multiple_of(2) {
+        return Err(anyhow!("Base16 input must have an even number of digits"));
+    }
+
+    (0..input.len())
+        .step_by(2)
+        .map(|index| {
+            u8::from_str_radix(&input[index..index + 2], 16).map_err(|_| {
+                anyhow!(
+                    "Invalid base16 digits '{}' at position {}",
+                    &input[index..index + 2],
+                    index + 1
+                )
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,247 @@
+//! Convert between Subtext and Gemtext, the line-oriented markup of the
+//! Gemini protocol. Headers, list items, quotes and blank lines map one to
+//! one.
+//!
+//! Gemtext has no inline links, so on export the links in a block are
+//! lifted into `=>` link lines that follow it (or that follow the whole run,
+//! for list items and quotes). On import, a link line becomes a paragraph
+//! holding its label and a hyperlink, or a slashlink when the URL is
+//! relative. Subtext only links `http`, `https` and `ipfs` URLs, so links to
+//! Gemini capsules are kept as text and reported as a loss.
+//!
+//! Subtext has no preformatted text. Preformatted lines are either kept as
+//! paragraphs, with their whitespace collapsed, or moved verbatim to a text
+//! file named after the block's alt text that the document links to; see
+//! [GemtextOptions::preformatted].
+use anyhow::Result;
+
+use crate::{
+    block::{group_blocks, Block},
+    format::normalize_newlines,
+    primitive::Entity,
+    render::{wiki_link_label, LinkResolver, LinkTarget},
+};
+
+use super::{
+    hyperlink, join_label, relative_slashlink, Embed, Import, ImportedFile, LinkStyle, Loss,
+    LossKind, Slugs, Writer,
+};
+
+/// Prefixes that give a Gemtext line a meaning other than plain text
+const LINE_TYPES: &[&str] = &["=>", "```", "* ", "#", ">"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GemtextOptions {
+    /// Where the lines of a preformatted block go
+    pub preformatted: Embed,
+}
+
+impl Default for GemtextOptions {
+    fn default() -> Self {
+        GemtextOptions {
+            preformatted: Embed::Inline,
+        }
+    }
+}
+
+/// A preformatted block being read: its alt text and lines
+type Preformatted = (String, Vec<String>);
+
+/// Convert a Gemtext document to Subtext
+pub fn from_gemtext(input: &str, options: &GemtextOptions) -> Result<Import> {
+    let mut writer = Writer::default();
+    let mut import = Import::default();
+    let mut slugs = Slugs::default();
+    let mut preformatted: Option<Preformatted> = None;
+
+    for line in normalize_newlines(input).lines() {
+        if let Some((_, lines)) = preformatted.as_mut() {
+            match line.starts_with("```") {
+                true => {
+                    let block = preformatted.take().unwrap_or_default();
+                    write_preformatted(&mut writer, &mut import, &mut slugs, block, options);
+                }
+                false => lines.push(line.to_owned()),
+            }
+        } else if let Some(alt) = line.strip_prefix("```") {
+            preformatted = Some((alt.trim().to_owned(), Vec::new()));
+        } else if let Some(link) = line.strip_prefix("=>") {
+            let link = link.trim();
+            let (url, label) = link.split_once([' ', '\t']).unwrap_or((link, ""));
+            if url.is_empty() {
+                continue;
+            }
+
+            let label = label.trim();
+            match url.contains(':') {
+                // Subtext has no `gemini://` links, so those are reported
+                true => {
+                    writer.paragraph(&hyperlink(label, url, LinkStyle::Bare, &mut import.losses))
+                }
+                false => match relative_slashlink(url, &[".gmi", ".gemini"]) {
+                    Some(slashlink) => writer.paragraph(&join_label(label, &slashlink)),
+                    None => {
+                        import.losses.push(Loss {
+                            kind: LossKind::Link,
+                            detail: format!("The link from {:?} to {} was dropped", label, url),
+                        });
+                        writer.paragraph(label);
+                    }
+                },
+            }
+        } else if line.starts_with('#') {
+            let level = line.chars().take_while(|c| *c == '#').count();
+            let text = line.trim_start_matches('#');
+
+            if level > 1 {
+                import.losses.push(Loss {
+                    kind: LossKind::HeadingLevel,
+                    detail: format!(
+                        "The level {} heading {:?} became a header",
+                        level,
+                        text.trim()
+                    ),
+                });
+            }
+            writer.header(text);
+        } else if let Some(text) = line.strip_prefix("* ") {
+            writer.list(text);
+        } else if let Some(text) = line.strip_prefix('>') {
+            writer.quote(text);
+        } else if line.trim().is_empty() {
+            writer.blank();
+        } else {
+            writer.paragraph(line);
+        }
+    }
+
+    // A preformatted block that is never closed runs to the end of the
+    // document
+    if let Some(block) = preformatted {
+        write_preformatted(&mut writer, &mut import, &mut slugs, block, options);
+    }
+
+    import.blocks = writer.into_blocks()?;
+    Ok(import)
+}
+
+fn write_preformatted(
+    writer: &mut Writer,
+    import: &mut Import,
+    slugs: &mut Slugs,
+    (alt, lines): Preformatted,
+    options: &GemtextOptions,
+) {
+    match options.preformatted {
+        Embed::Inline => {
+            import.losses.push(Loss {
+                kind: LossKind::CodeBlock,
+                detail: format!(
+                    "A {} line preformatted block was kept as paragraphs",
+                    lines.len()
+                ),
+            });
+            for line in lines {
+                match line.trim().is_empty() {
+                    true => writer.blank(),
+                    false => writer.paragraph(&line),
+                }
+            }
+        }
+        Embed::Extract => {
+            let slug = slugs.unique(&alt, "preformatted");
+            writer.paragraph(&join_label(alt.trim(), &format!("/{}", slug)));
+            import.files.push(ImportedFile {
+                slug,
+                extension: "txt".into(),
+                content: lines.iter().map(|line| format!("{}\n", line)).collect(),
+            });
+        }
+    }
+}
+
+/// The URL and label of every link in a block that can be resolved
+fn links<E>(block: &Block<E>, resolver: &impl LinkResolver) -> Vec<(String, Option<String>)>
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    block
+        .to_content_entities()
+        .iter()
+        .filter_map(|entity| match entity.as_ref() {
+            Entity::HyperLink(url) => Some((url.to_string(), None)),
+            Entity::SlashLink(text) => resolver
+                .resolve(&LinkTarget::SlashLink(text.as_ref()))
+                .map(|url| (url, Some(text.to_string()))),
+            Entity::WikiLink(text) => {
+                let label = wiki_link_label(text.as_ref());
+                resolver
+                    .resolve(&LinkTarget::WikiLink(label))
+                    .map(|url| (url, Some(label.to_owned())))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Write link lines for the links in a run of blocks, once per URL
+fn write_links<E>(lines: &mut Vec<String>, blocks: &[Block<E>], resolver: &impl LinkResolver)
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let mut seen = Vec::<String>::new();
+
+    for (url, label) in blocks.iter().flat_map(|block| links(block, resolver)) {
+        if seen.contains(&url) {
+            continue;
+        }
+
+        lines.push(match label {
+            Some(label) => format!("=> {} {}", url, label),
+            None => format!("=> {}", url),
+        });
+        seen.push(url);
+    }
+}
+
+/// Convert blocks to Gemtext. Slashlinks and wikilinks get a link line only
+/// if the resolver gives them a URL.
+pub fn to_gemtext<E>(blocks: &[Block<E>], resolver: &impl LinkResolver) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let mut lines = Vec::<String>::new();
+
+    for group in group_blocks(blocks) {
+        match group.first() {
+            Some(Block::Header(_)) | Some(Block::Paragraph(_)) => {
+                for block in group {
+                    let text = block.to_text_content();
+                    lines.push(match block {
+                        Block::Header(_) => format!("# {}", text).trim_end().to_owned(),
+                        _ if LINE_TYPES.iter().any(|prefix| text.starts_with(prefix)) => {
+                            format!(" {}", text)
+                        }
+                        _ => text,
+                    });
+                    write_links(&mut lines, std::slice::from_ref(block), resolver);
+                }
+            }
+            Some(Block::List(_)) | Some(Block::Quote(_)) => {
+                let sigil = match group.first() {
+                    Some(Block::List(_)) => "*",
+                    _ => ">",
+                };
+                for block in group {
+                    let line = format!("{} {}", sigil, block.to_text_content());
+                    lines.push(line.trim_end().to_owned());
+                }
+                write_links(&mut lines, group, resolver);
+            }
+            Some(Block::Blank(_)) => lines.extend(group.iter().map(|_| String::new())),
+            None => (),
+        }
+    }
+
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
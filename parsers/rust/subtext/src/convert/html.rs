@@ -0,0 +1,474 @@
+//! Convert HTML, such as a clipped web page, to Subtext. Headings become
+//! headers, list items become list items and blockquotes become quotes.
+//! Paragraphs and other text become paragraphs, with a line break starting
+//! a new block. Whitespace is collapsed as a browser would.
+//!
+//! Links become their label followed by the URL, relative URLs are resolved
+//! against [HtmlImportOptions::base_url] when it is set and become
+//! slashlinks otherwise. The head of the document, scripts, styles and
+//! other content that isn't meant to be read are dropped.
+//!
+//! HTML is read leniently: unclosed paragraphs and list items are fine, and
+//! closing tags that don't match are ignored.
+use anyhow::Result;
+
+use super::{
+    clean_text, hyperlink, relative_link, relative_slashlink,
+    xml::{tokenize_html, Attributes, Token},
+    Import, LinkStyle, Loss, LossKind, Writer,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmlImportOptions {
+    pub link_style: LinkStyle,
+    /// The URL of the page, which relative links are resolved against. When
+    /// there is none, relative links become slashlinks.
+    pub base_url: Option<String>,
+}
+
+impl Default for HtmlImportOptions {
+    fn default() -> Self {
+        HtmlImportOptions {
+            link_style: LinkStyle::Bare,
+            base_url: None,
+        }
+    }
+}
+
+/// Elements whose content is dropped
+const SKIPPED: &[&str] = &[
+    "head", "script", "style", "template", "noscript", "svg", "canvas", "select", "button",
+];
+
+/// Elements that end any text before them, and start a section of their own
+/// when they aren't inside a list, quote or table
+const SECTIONS: &[&str] = &[
+    "p",
+    "div",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "dl",
+    "blockquote",
+    "pre",
+    "table",
+    "section",
+    "article",
+    "main",
+    "header",
+    "footer",
+    "aside",
+    "nav",
+    "figure",
+    "hr",
+    "address",
+    "details",
+    "form",
+    "fieldset",
+];
+
+/// Elements that end any text before them without starting a section
+const BLOCKS: &[&str] = &[
+    "li",
+    "dt",
+    "dd",
+    "tr",
+    "figcaption",
+    "summary",
+    "caption",
+    "body",
+];
+
+/// The elements that decide what kind of block text becomes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    Heading(usize),
+    Item,
+    Row,
+    Quote,
+    Preformatted,
+    /// A list or table, which holds items or rows but no text of its own
+    List,
+    Table,
+}
+
+impl Context {
+    fn from_name(name: &str) -> Option<Context> {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                Some(Context::Heading(name[1..].parse().unwrap_or(1)))
+            }
+            "li" => Some(Context::Item),
+            "tr" => Some(Context::Row),
+            "blockquote" => Some(Context::Quote),
+            "pre" => Some(Context::Preformatted),
+            "ul" | "ol" => Some(Context::List),
+            "table" => Some(Context::Table),
+            _ => None,
+        }
+    }
+}
+
+/// Converts a stream of HTML tokens into Subtext lines
+#[derive(Debug)]
+struct Converter<'a> {
+    options: &'a HtmlImportOptions,
+    writer: Writer,
+    losses: Vec<Loss>,
+    /// Inline text of the block being read; `\n` marks a line break
+    text: String,
+    /// Open elements that decide the kind of block, with their names
+    contexts: Vec<(String, Context)>,
+    /// Where each open link starts in `text`, and its URL
+    links: Vec<(usize, Option<String>)>,
+    /// The element whose content is being dropped, and how deeply it is
+    /// nested in itself
+    skip: Option<(String, usize)>,
+    flattened: usize,
+    /// Set when the next block starts a new section
+    separate: bool,
+}
+
+impl Converter<'_> {
+    fn lose(&mut self, kind: LossKind, detail: String) {
+        self.losses.push(Loss { kind, detail });
+    }
+
+    /// Report a kind of formatting the first time it is seen
+    fn lose_formatting(&mut self, detail: &str) {
+        if !self
+            .losses
+            .iter()
+            .any(|loss| loss.kind == LossKind::Formatting && loss.detail == detail)
+        {
+            self.lose(LossKind::Formatting, detail.into());
+        }
+    }
+
+    /// The innermost context that decides the kind of block
+    fn context(&self) -> Option<Context> {
+        self.contexts
+            .iter()
+            .rev()
+            .map(|(_, context)| *context)
+            .find(|context| !matches!(context, Context::List | Context::Table))
+    }
+
+    fn count(&self, kind: Context) -> usize {
+        self.contexts
+            .iter()
+            .filter(|(_, context)| *context == kind)
+            .count()
+    }
+
+    /// Whether a section element would start a new section here, rather
+    /// than continue a list, quote or table
+    fn at_top_level(&self, name: &str) -> bool {
+        SECTIONS.contains(&name)
+            && !self.contexts.iter().any(|(_, context)| {
+                matches!(context, Context::List | Context::Table | Context::Quote)
+            })
+    }
+
+    /// Write the text read so far as blocks of the current kind
+    fn flush(&mut self) {
+        let text = std::mem::take(&mut self.text);
+        // A link around blocks keeps only the label in the last of them
+        for (start, _) in self.links.iter_mut() {
+            *start = 0;
+        }
+        if text.trim().is_empty() {
+            return;
+        }
+
+        if self.separate {
+            self.writer.separate();
+            self.separate = false;
+        }
+
+        let lines = text.split('\n').filter(|line| !line.trim().is_empty());
+        match self.context() {
+            Some(Context::Heading(level)) => {
+                let text = lines.collect::<Vec<&str>>().join(" ");
+                if level > 1 {
+                    self.lose(
+                        LossKind::HeadingLevel,
+                        format!(
+                            "The level {} heading {:?} became a header",
+                            level,
+                            clean_text(&text)
+                        ),
+                    );
+                }
+                self.writer.header(&text);
+            }
+            Some(Context::Item) | Some(Context::Row) => {
+                lines.for_each(|line| self.writer.list(line))
+            }
+            Some(Context::Quote) => lines.for_each(|line| self.writer.quote(line)),
+            Some(Context::Preformatted) => {
+                let lines = text.trim_matches('\n').lines().collect::<Vec<&str>>();
+                self.lose(
+                    LossKind::CodeBlock,
+                    format!(
+                        "A {} line preformatted block was kept as paragraphs",
+                        lines.len()
+                    ),
+                );
+                for line in lines {
+                    match line.trim().is_empty() {
+                        true => self.writer.blank(),
+                        false => self.writer.paragraph(line),
+                    }
+                }
+            }
+            Some(Context::List) | Some(Context::Table) | None => {
+                lines.for_each(|line| self.writer.paragraph(line))
+            }
+        }
+    }
+
+    /// Resolve a URL against the base URL, if there is one
+    fn resolve(&self, url: &str) -> String {
+        match &self.options.base_url {
+            Some(base) => resolve_url(base, url),
+            None => url.to_owned(),
+        }
+    }
+
+    /// Write a link as its label followed by a slashlink for relative URLs
+    /// or a hyperlink for anything else
+    fn link(&mut self, label: &str, url: &str) -> String {
+        let url = self.resolve(url);
+
+        if url.contains(':') {
+            return hyperlink(label, &url, self.options.link_style, &mut self.losses);
+        }
+
+        match relative_slashlink(&url, &[".html", ".htm"]) {
+            Some(slashlink) => relative_link(label, &slashlink),
+            None => {
+                self.lose(
+                    LossKind::Link,
+                    format!("The link from {:?} to {} was dropped", label, url),
+                );
+                label.to_owned()
+            }
+        }
+    }
+
+    fn start(&mut self, name: &str, attributes: &Attributes, empty: bool) {
+        let attribute = |key: &str| {
+            attributes
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(key))
+                .map(|(_, value)| value.trim())
+        };
+
+        if SECTIONS.contains(&name) || BLOCKS.contains(&name) {
+            self.flush();
+            if self.at_top_level(name) {
+                self.separate = true;
+            }
+        }
+
+        match name {
+            _ if SKIPPED.contains(&name) && !empty => self.skip = Some((name.to_owned(), 1)),
+            "br" => self.text.push('\n'),
+            "hr" => self.lose(
+                LossKind::ThematicBreak,
+                "A horizontal rule was dropped".into(),
+            ),
+            "table" => self.lose(LossKind::Table, "A table was kept as a list".into()),
+            "td" | "th" if !self.text.trim().is_empty() => self.text.push_str(" | "),
+            "blockquote" if self.count(Context::Quote) > 0 => {
+                self.lose(LossKind::NestedQuote, "A nested quote was flattened".into())
+            }
+            "li" if self.count(Context::List) > 1 => self.flattened += 1,
+            "em" | "i" => self.lose_formatting("emphasis"),
+            "strong" | "b" => self.lose_formatting("strong emphasis"),
+            "s" | "del" | "strike" => self.lose_formatting("strikethrough"),
+            "code" | "kbd" | "samp" if self.context() != Some(Context::Preformatted) => {
+                self.lose_formatting("inline code")
+            }
+            "a" if !empty => {
+                let href = attribute("href").filter(|href| !href.is_empty());
+                self.links.push((self.text.len(), href.map(str::to_owned)));
+            }
+            "img" => {
+                let alt = clean_text(attribute("alt").unwrap_or_default());
+                match attribute("src").filter(|src| !src.is_empty() && !src.starts_with("data:")) {
+                    Some(src) => {
+                        self.lose(
+                            LossKind::Image,
+                            format!("The image {} is linked to instead of shown", src),
+                        );
+                        let src = self.resolve(src);
+                        let link = hyperlink(&alt, &src, self.options.link_style, &mut self.losses);
+                        self.text.push_str(&link);
+                    }
+                    None => self.text.push_str(&alt),
+                }
+            }
+            _ => (),
+        }
+
+        if let (Some(context), false) = (Context::from_name(name), empty) {
+            self.contexts.push((name.to_owned(), context));
+        }
+    }
+
+    fn end(&mut self, name: &str) {
+        if SECTIONS.contains(&name) || BLOCKS.contains(&name) {
+            self.flush();
+        }
+
+        let closed = match name {
+            "a" => self.links.pop(),
+            _ => None,
+        };
+        if let Some((start, url)) = closed {
+            let label = clean_text(&self.text.split_off(start));
+            let link = match url {
+                Some(url) => self.link(&label, &url),
+                None => label,
+            };
+            self.text.push_str(&link);
+        }
+
+        // Close the innermost matching context, along with anything left
+        // open inside it
+        if let Some(index) = self
+            .contexts
+            .iter()
+            .rposition(|(context, _)| context == name)
+        {
+            self.contexts.truncate(index);
+        }
+
+        if self.at_top_level(name) {
+            self.separate = true;
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        match self.context() {
+            Some(Context::Preformatted) => self.text.push_str(text),
+            _ => self.text.push_str(&text.replace(['\n', '\r'], " ")),
+        }
+    }
+
+    fn read(&mut self, token: Token) {
+        if let Some((skipped, depth)) = self.skip.as_mut() {
+            match &token {
+                Token::Start { name, empty, .. }
+                    if !empty && name.eq_ignore_ascii_case(skipped) =>
+                {
+                    *depth += 1
+                }
+                Token::End(name) if name.eq_ignore_ascii_case(skipped) => *depth -= 1,
+                // A head that is never closed ends where the body starts
+                Token::Start { name, .. }
+                    if skipped == "head" && name.eq_ignore_ascii_case("body") =>
+                {
+                    *depth = 0
+                }
+                _ => (),
+            }
+
+            if *depth > 0 {
+                return;
+            }
+            self.skip = None;
+            if let Token::End(_) = token {
+                return;
+            }
+        }
+
+        match token {
+            Token::Start {
+                name,
+                attributes,
+                empty,
+            } => self.start(&name.to_ascii_lowercase(), &attributes, empty),
+            Token::End(name) => self.end(&name.to_ascii_lowercase()),
+            Token::Text(text) => self.text(&text),
+        }
+    }
+}
+
+/// Resolve a possibly relative URL against the URL of the page it is on
+fn resolve_url(base: &str, url: &str) -> String {
+    let has_scheme = |url: &str| {
+        url.split(['/', '?', '#'])
+            .next()
+            .is_some_and(|scheme| scheme.contains(':'))
+    };
+    let Some(scheme_end) = base.find("://").map(|index| index + 3) else {
+        return url.to_owned();
+    };
+    if has_scheme(url) {
+        return url.to_owned();
+    }
+    if url.starts_with("//") {
+        return format!("{}{}", &base[..scheme_end - 2], url);
+    }
+
+    let authority_end = base[scheme_end..]
+        .find(['/', '?', '#'])
+        .map_or(base.len(), |index| index + scheme_end);
+    let base = base.split('#').next().unwrap_or(base);
+
+    if url.starts_with('/') {
+        format!("{}{}", &base[..authority_end], url)
+    } else if url.starts_with('#') {
+        format!("{}{}", base, url)
+    } else if url.starts_with('?') {
+        format!("{}{}", base.split('?').next().unwrap_or(base), url)
+    } else {
+        let base = base.split('?').next().unwrap_or(base);
+        let directory = match base[authority_end..].rfind('/') {
+            Some(index) => base[..authority_end + index + 1].to_owned(),
+            None => format!("{}/", base),
+        };
+        format!("{}{}", directory, url.trim_start_matches("./"))
+    }
+}
+
+/// Convert an HTML document or fragment to Subtext
+pub fn from_html(input: &str, options: &HtmlImportOptions) -> Result<Import> {
+    let mut converter = Converter {
+        options,
+        writer: Writer::default(),
+        losses: Vec::new(),
+        text: String::new(),
+        contexts: Vec::new(),
+        links: Vec::new(),
+        skip: None,
+        flattened: 0,
+        separate: false,
+    };
+
+    for token in tokenize_html(input)? {
+        converter.read(token);
+    }
+    converter.flush();
+
+    if converter.flattened > 0 {
+        converter.lose(
+            LossKind::NestedList,
+            format!("{} nested list items were flattened", converter.flattened),
+        );
+    }
+
+    Ok(Import {
+        blocks: converter.writer.into_blocks()?,
+        files: Vec::new(),
+        losses: converter.losses,
+    })
+}
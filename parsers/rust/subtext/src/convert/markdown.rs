@@ -0,0 +1,470 @@
+//! Convert CommonMark (with tables, strikethrough and task lists) to
+//! Subtext. Requires the `markdown` feature.
+use anyhow::Result;
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag};
+
+pub use super::LinkStyle;
+
+use super::{
+    clean_text, code_extension, hyperlink, join_label, relative_link, relative_slashlink, to_csv,
+    Embed, Import, ImportedFile, Loss, LossKind, Slugs, Writer,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkdownOptions {
+    pub link_style: LinkStyle,
+    /// The deepest level of list nesting to keep in the document. Items
+    /// that have children at this depth link to a new note holding those
+    /// children instead. `None` flattens every list into the document.
+    pub max_list_depth: Option<usize>,
+    /// Code blocks are extracted to a file named after their language
+    pub code_blocks: Embed,
+    /// Tables are extracted to a CSV file
+    pub tables: Embed,
+}
+
+impl Default for MarkdownOptions {
+    fn default() -> Self {
+        MarkdownOptions {
+            link_style: LinkStyle::Bare,
+            max_list_depth: None,
+            code_blocks: Embed::Inline,
+            tables: Embed::Inline,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Item {
+    text: String,
+    children: Vec<Item>,
+    /// Headings, code blocks and tables inside the item, each with the
+    /// number of children that come before it
+    sections: Vec<(usize, Section)>,
+}
+
+/// The Markdown constructs that survive into Subtext, in document order
+#[derive(Debug)]
+enum Section {
+    Heading(usize, String),
+    Paragraph(String),
+    /// The paragraphs and other sections inside a quote
+    Quote(Vec<Section>),
+    List(Vec<Item>),
+    Code(Option<String>, String),
+    Table(Vec<Vec<String>>),
+}
+
+impl Section {
+    fn name(&self) -> &'static str {
+        match self {
+            Section::Heading(_, _) => "heading",
+            Section::Paragraph(_) => "paragraph",
+            Section::Quote(_) => "quote",
+            Section::List(_) => "list",
+            Section::Code(_, _) => "code block",
+            Section::Table(_) => "table",
+        }
+    }
+}
+
+/// Gathers the sections of a Markdown document from parser events
+#[derive(Debug, Default)]
+struct Reader {
+    sections: Vec<Section>,
+    losses: Vec<Loss>,
+    /// Inline text of the heading, paragraph, item or cell being read
+    text: String,
+    /// Where each open link or image starts in `text`, and its URL
+    links: Vec<(usize, String)>,
+    quote_depth: usize,
+    /// The sections of the outermost open quote, in document order
+    quote: Vec<Section>,
+    lists: Vec<Vec<Item>>,
+    items: Vec<Item>,
+    table: Vec<Vec<String>>,
+    row: Vec<String>,
+}
+
+impl Reader {
+    fn lose(&mut self, kind: LossKind, detail: String) {
+        self.losses.push(Loss { kind, detail });
+    }
+
+    /// Report a kind of formatting the first time it is seen
+    fn lose_formatting(&mut self, detail: &str) {
+        if !self
+            .losses
+            .iter()
+            .any(|loss| loss.kind == LossKind::Formatting && loss.detail == detail)
+        {
+            self.lose(LossKind::Formatting, detail.into());
+        }
+    }
+
+    /// Finish the paragraph being read, adding it to the enclosing item or
+    /// quote if there is one
+    fn end_paragraph(&mut self) {
+        let text = std::mem::take(&mut self.text);
+
+        if let Some(item) = self.items.last_mut() {
+            if !item.text.is_empty() && !text.trim().is_empty() {
+                item.text.push(' ');
+            }
+            item.text.push_str(&text);
+        } else {
+            self.push_section(Section::Paragraph(text));
+        }
+    }
+
+    /// Add a section to the enclosing item or quote if there is one, or else
+    /// to the document
+    fn push_section(&mut self, section: Section) {
+        match self.items.last_mut() {
+            Some(item) => item.sections.push((item.children.len(), section)),
+            None if self.quote_depth > 0 => self.quote.push(section),
+            None => self.sections.push(section),
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::BlockQuote => {
+                if self.quote_depth > 0 {
+                    self.lose(LossKind::NestedQuote, "A nested quote was flattened".into());
+                }
+                self.quote_depth += 1;
+            }
+            // Text before a nested block belongs to the parent item
+            Tag::List(_) | Tag::CodeBlock(_) | Tag::Table(_) | Tag::Heading(_, _, _)
+                if !self.items.is_empty() =>
+            {
+                self.end_paragraph();
+                if let Tag::List(_) = tag {
+                    self.lists.push(Vec::new());
+                }
+            }
+            Tag::List(_) => self.lists.push(Vec::new()),
+            Tag::Item => self.items.push(Item::default()),
+            Tag::Emphasis => self.lose_formatting("emphasis"),
+            Tag::Strong => self.lose_formatting("strong emphasis"),
+            Tag::Strikethrough => self.lose_formatting("strikethrough"),
+            Tag::Link(_, url, _) | Tag::Image(_, url, _) => {
+                self.links.push((self.text.len(), url.to_string()))
+            }
+            _ => (),
+        }
+    }
+
+    fn end(&mut self, tag: Tag, options: &MarkdownOptions) {
+        match tag {
+            Tag::Paragraph => self.end_paragraph(),
+            Tag::Heading(level, _, _) => {
+                let text = std::mem::take(&mut self.text);
+                self.push_section(Section::Heading(level as usize, text));
+            }
+            Tag::BlockQuote => {
+                self.quote_depth -= 1;
+                if self.quote_depth == 0 && !self.quote.is_empty() {
+                    let quote = std::mem::take(&mut self.quote);
+                    self.sections.push(Section::Quote(quote));
+                }
+            }
+            Tag::CodeBlock(kind) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => info
+                        .split_whitespace()
+                        .next()
+                        .map(|language| language.to_owned()),
+                    CodeBlockKind::Indented => None,
+                };
+                let code = std::mem::take(&mut self.text);
+                self.push_section(Section::Code(language, code));
+            }
+            Tag::Item => {
+                self.end_paragraph();
+                if let (Some(item), Some(list)) = (self.items.pop(), self.lists.last_mut()) {
+                    list.push(item);
+                }
+            }
+            Tag::List(_) => {
+                let list = self.lists.pop().unwrap_or_default();
+                match self.items.last_mut() {
+                    Some(parent) => parent.children.extend(list),
+                    None => self.push_section(Section::List(list)),
+                }
+            }
+            Tag::TableCell => {
+                let cell = clean_text(&std::mem::take(&mut self.text));
+                self.row.push(cell);
+            }
+            Tag::TableHead | Tag::TableRow => {
+                let row = std::mem::take(&mut self.row);
+                self.table.push(row);
+            }
+            Tag::Table(_) => {
+                let table = std::mem::take(&mut self.table);
+                self.push_section(Section::Table(table));
+            }
+            Tag::Link(_, _, _) => {
+                if let Some((start, url)) = self.links.pop() {
+                    let label = self.text.split_off(start);
+                    let link = self.link(label.trim(), &url, options);
+                    self.text.push_str(&link);
+                }
+            }
+            Tag::Image(_, _, _) => {
+                if let Some((start, url)) = self.links.pop() {
+                    let description = self.text.split_off(start);
+                    self.lose(
+                        LossKind::Image,
+                        format!("The image {} is linked to instead of shown", url),
+                    );
+                    let link = hyperlink(
+                        description.trim(),
+                        &url,
+                        options.link_style,
+                        &mut self.losses,
+                    );
+                    self.text.push_str(&link);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Write a link as its label followed by a slashlink for relative URLs
+    /// or a hyperlink for anything else
+    fn link(&mut self, label: &str, url: &str, options: &MarkdownOptions) -> String {
+        if url.contains(':') {
+            return hyperlink(label, url, options.link_style, &mut self.losses);
+        }
+
+        match relative_slashlink(url, &[".md", ".markdown"]) {
+            Some(slashlink) => relative_link(label, &slashlink),
+            None => {
+                self.lose(
+                    LossKind::Link,
+                    format!("The link from {:?} to {} was dropped", label, url),
+                );
+                label.to_owned()
+            }
+        }
+    }
+
+    fn read(mut self, input: &str, options: &MarkdownOptions) -> Self {
+        let parser = Parser::new_ext(
+            input,
+            Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS,
+        );
+
+        for event in parser {
+            match event {
+                Event::Start(tag) => self.start(tag),
+                Event::End(tag) => self.end(tag, options),
+                Event::Text(text) => self.text.push_str(&text),
+                Event::Code(code) => {
+                    self.lose_formatting("inline code");
+                    self.text.push_str(&code);
+                }
+                Event::Html(html) => self.lose(
+                    LossKind::Html,
+                    format!("The HTML {:?} was dropped", html.trim()),
+                ),
+                Event::FootnoteReference(label) => self.text.push_str(&format!("[^{}]", label)),
+                Event::SoftBreak => self.text.push(' '),
+                Event::HardBreak => self.text.push('\n'),
+                Event::Rule => self.lose(
+                    LossKind::ThematicBreak,
+                    "A horizontal rule was dropped".into(),
+                ),
+                Event::TaskListMarker(true) => self.text.push_str("[x] "),
+                Event::TaskListMarker(false) => self.text.push_str("[ ] "),
+            }
+        }
+
+        self
+    }
+}
+
+/// Turns sections into Subtext, splitting files out as configured
+struct Converter<'a> {
+    options: &'a MarkdownOptions,
+    slugs: Slugs,
+    files: Vec<ImportedFile>,
+    losses: Vec<Loss>,
+}
+
+impl Converter<'_> {
+    fn write_section(&mut self, writer: &mut Writer, section: Section) {
+        match section {
+            Section::Heading(level, text) => {
+                if level > 1 {
+                    self.losses.push(Loss {
+                        kind: LossKind::HeadingLevel,
+                        detail: format!("The level {} heading {:?} became a header", level, text),
+                    });
+                }
+                writer.header(&text);
+            }
+            Section::Paragraph(text) => text.split('\n').for_each(|line| writer.paragraph(line)),
+            Section::Quote(sections) => {
+                // Everything inside the quote is written as it would be
+                // outside, then quoted line by line
+                let mut quoted = Writer::default();
+                for section in sections {
+                    if !matches!(section, Section::Paragraph(_)) {
+                        self.losses.push(Loss {
+                            kind: LossKind::QuotedBlock,
+                            detail: format!(
+                                "A {} inside a quote became quoted text",
+                                section.name()
+                            ),
+                        });
+                    }
+                    self.write_section(&mut quoted, section);
+                }
+                quoted
+                    .to_subtext()
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .for_each(|line| writer.quote(line));
+            }
+            Section::List(items) => {
+                let flattened = self.write_items(writer, items, 1);
+                if flattened > 0 {
+                    self.losses.push(Loss {
+                        kind: LossKind::NestedList,
+                        detail: format!("{} nested list items were flattened", flattened),
+                    });
+                }
+            }
+            Section::Code(language, code) => match self.options.code_blocks {
+                Embed::Inline => {
+                    self.losses.push(Loss {
+                        kind: LossKind::CodeBlock,
+                        detail: format!(
+                            "A {} line code block was kept as paragraphs",
+                            code.lines().count()
+                        ),
+                    });
+                    for line in code.lines() {
+                        match line.trim().is_empty() {
+                            true => writer.blank(),
+                            false => writer.paragraph(line),
+                        }
+                    }
+                }
+                Embed::Extract => {
+                    let extension = language.as_deref().map_or("txt".into(), code_extension);
+                    self.extract(writer, "code", &extension, code);
+                }
+            },
+            Section::Table(rows) => match self.options.tables {
+                Embed::Inline => {
+                    self.losses.push(Loss {
+                        kind: LossKind::Table,
+                        detail: format!("A {} row table was kept as a list", rows.len()),
+                    });
+                    for row in rows {
+                        writer.list(&row.join(" | "));
+                    }
+                }
+                Embed::Extract => {
+                    self.extract(writer, "table", "csv", to_csv(&rows));
+                }
+            },
+        }
+    }
+
+    /// Move content to a file of its own and link to it from the document
+    fn extract(&mut self, writer: &mut Writer, name: &str, extension: &str, content: String) {
+        let slug = self.slugs.unique(name, name);
+        writer.paragraph(&format!("/{}", slug));
+        self.files.push(ImportedFile {
+            slug,
+            extension: extension.into(),
+            content,
+        });
+    }
+
+    /// Write list items at the given depth, returning how many nested items
+    /// were flattened into the list
+    fn write_items(&mut self, writer: &mut Writer, items: Vec<Item>, depth: usize) -> usize {
+        let mut flattened = 0;
+
+        for item in items {
+            let text = clean_text(&item.text);
+
+            if !item.children.is_empty()
+                && matches!(self.options.max_list_depth, Some(max) if depth >= max)
+            {
+                let slug = self.slugs.unique(&text, "note");
+                let mut note = Writer::default();
+                flattened += self.write_content(&mut note, item.children, item.sections, 1);
+
+                writer.list(&join_label(&text, &format!("/{}", slug)));
+                self.files.push(ImportedFile {
+                    slug,
+                    extension: "subtext".into(),
+                    content: note.to_subtext(),
+                });
+            } else {
+                writer.list(&text);
+                flattened += item.children.len();
+                flattened += self.write_content(writer, item.children, item.sections, depth + 1);
+            }
+        }
+
+        flattened
+    }
+
+    /// Write the children of an item, with the sections inside the item in
+    /// between them where they appeared
+    fn write_content(
+        &mut self,
+        writer: &mut Writer,
+        children: Vec<Item>,
+        sections: Vec<(usize, Section)>,
+        depth: usize,
+    ) -> usize {
+        let mut sections = sections.into_iter().peekable();
+        let mut flattened = 0;
+
+        for (index, child) in children.into_iter().enumerate() {
+            while let Some((_, section)) = sections.next_if(|(position, _)| *position <= index) {
+                self.write_section(writer, section);
+            }
+            flattened += self.write_items(writer, vec![child], depth);
+        }
+
+        for (_, section) in sections {
+            self.write_section(writer, section);
+        }
+
+        flattened
+    }
+}
+
+/// Convert a CommonMark document to Subtext
+pub fn from_markdown(input: &str, options: &MarkdownOptions) -> Result<Import> {
+    let reader = Reader::default().read(input, options);
+    let mut converter = Converter {
+        options,
+        slugs: Slugs::default(),
+        files: Vec::new(),
+        losses: reader.losses,
+    };
+    let mut writer = Writer::default();
+
+    for section in reader.sections {
+        writer.separate();
+        converter.write_section(&mut writer, section);
+    }
+
+    Ok(Import {
+        blocks: writer.into_blocks()?,
+        files: converter.files,
+        losses: converter.losses,
+    })
+}
@@ -0,0 +1,359 @@
+//! Converters between Subtext and other document formats. Subtext has far
+//! fewer constructs than most formats, so importing is lossy: every importer
+//! reports what it could not carry over as a list of [Loss]es alongside the
+//! converted blocks.
+pub mod gemtext;
+pub mod html;
+#[cfg(feature = "markdown")]
+pub mod markdown;
+pub mod opml;
+pub mod org;
+mod xml;
+
+pub use gemtext::{from_gemtext, to_gemtext, GemtextOptions};
+pub use html::{from_html, HtmlImportOptions};
+#[cfg(feature = "markdown")]
+pub use markdown::{from_markdown, MarkdownOptions};
+pub use opml::{from_opml, to_opml, OpmlOptions};
+pub use org::{from_org, org_file_link, to_org, OrgOptions};
+
+use std::{collections::HashMap, fmt::Display};
+
+use anyhow::Result;
+
+use crate::{block::Block, primitive::Entity, util::to_slug};
+
+/// The kinds of content a converter may have to drop or flatten
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LossKind {
+    /// A heading below the top level became a plain header
+    HeadingLevel,
+    /// Nested list items were flattened into a single list
+    NestedList,
+    /// A quote nested inside another quote was flattened
+    NestedQuote,
+    /// A heading, list or other block inside a quote became quoted text
+    QuotedBlock,
+    /// Emphasis, inline code or similar markup was reduced to its text
+    Formatting,
+    /// A link could not be expressed and only its label was kept
+    Link,
+    /// An image was reduced to its description and URL
+    Image,
+    /// A code block was kept as plain text
+    CodeBlock,
+    /// A table was kept as plain text
+    Table,
+    /// Raw HTML was dropped
+    Html,
+    /// A horizontal rule was dropped
+    ThematicBreak,
+    /// Keywords, comments, properties or similar metadata were dropped
+    Metadata,
+}
+
+impl LossKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LossKind::HeadingLevel => "heading-level",
+            LossKind::NestedList => "nested-list",
+            LossKind::NestedQuote => "nested-quote",
+            LossKind::QuotedBlock => "quoted-block",
+            LossKind::Formatting => "formatting",
+            LossKind::Link => "link",
+            LossKind::Image => "image",
+            LossKind::CodeBlock => "code-block",
+            LossKind::Table => "table",
+            LossKind::Html => "html",
+            LossKind::ThematicBreak => "thematic-break",
+            LossKind::Metadata => "metadata",
+        }
+    }
+}
+
+impl Display for LossKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Something in the source document that did not survive conversion intact
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loss {
+    pub kind: LossKind,
+    pub detail: String,
+}
+
+impl Display for Loss {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}]: {}", self.kind, self.detail)
+    }
+}
+
+/// A note or file split out of the converted document. The document links
+/// to it with the slashlink `/{slug}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedFile {
+    pub slug: String,
+    pub extension: String,
+    pub content: String,
+}
+
+impl ImportedFile {
+    pub fn file_name(&self) -> String {
+        format!("{}.{}", self.slug, self.extension)
+    }
+}
+
+/// The result of converting a document into Subtext
+#[derive(Debug, Clone, Default)]
+pub struct Import {
+    pub blocks: Vec<Block<Entity>>,
+    /// Notes and files split out of the document, which it links to
+    pub files: Vec<ImportedFile>,
+    pub losses: Vec<Loss>,
+}
+
+impl Import {
+    /// Serialize the converted blocks as a Subtext document
+    pub fn to_subtext(&self) -> String {
+        match self.blocks.is_empty() {
+            true => String::new(),
+            false => format!(
+                "{}\n",
+                self.blocks
+                    .iter()
+                    .map(|block| block.to_string())
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            ),
+        }
+    }
+}
+
+/// How links to other websites are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkStyle {
+    /// `label https://example.com`
+    Bare,
+    /// `label <https://example.com>`
+    Bracket,
+}
+
+impl LinkStyle {
+    pub(crate) fn write(&self, url: &str) -> String {
+        match self {
+            LinkStyle::Bare => url.to_owned(),
+            LinkStyle::Bracket => format!("<{}>", url),
+        }
+    }
+}
+
+/// What to do with content that Subtext has no block for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Embed {
+    /// Keep the content in the document as plain text
+    Inline,
+    /// Move the content to a file of its own and link to it
+    Extract,
+}
+
+/// Collapse runs of whitespace, including line breaks, into single spaces
+pub(crate) fn clean_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Write a link after its label, if it has one
+pub(crate) fn join_label(label: &str, link: &str) -> String {
+    match label.is_empty() {
+        true => link.to_owned(),
+        false => format!("{} {}", label, link),
+    }
+}
+
+/// URL schemes that Subtext recognizes as links
+const LINK_SCHEMES: &[&str] = &["http://", "https://", "ipfs://"];
+
+/// Write a link to another website after its label. A space follows the
+/// URL, so that punctuation or text after the link is not read as part of
+/// it; [clean_text] drops the space where it isn't needed. Subtext only
+/// recognizes `http`, `https` and `ipfs` URLs, so a link with any other
+/// scheme is kept as text and reported as a loss.
+pub(crate) fn hyperlink(
+    label: &str,
+    url: &str,
+    style: LinkStyle,
+    losses: &mut Vec<Loss>,
+) -> String {
+    if !LINK_SCHEMES.iter().any(|scheme| url.starts_with(scheme)) {
+        losses.push(Loss {
+            kind: LossKind::Link,
+            detail: format!("The link from {:?} to {} was kept as text", label, url),
+        });
+    }
+
+    match label == url {
+        true => format!("{} ", style.write(url)),
+        false => format!("{} ", join_label(label, &style.write(url))),
+    }
+}
+
+/// Write the slashlink that stands in for a relative link after its label.
+/// As with [hyperlink], a space follows the slashlink so that punctuation
+/// after the link is not read as part of its slug.
+pub(crate) fn relative_link(label: &str, slashlink: &str) -> String {
+    match label == slashlink {
+        true => format!("{} ", slashlink),
+        false => format!("{} ", join_label(label, slashlink)),
+    }
+}
+
+/// The slashlink for a relative URL to another document, ignoring its
+/// query, fragment and any of the given file extensions. Returns `None` for
+/// absolute URLs and URLs that have no usable path.
+pub(crate) fn relative_slashlink(url: &str, extensions: &[&str]) -> Option<String> {
+    if url.contains(':') {
+        return None;
+    }
+
+    let path = url.split(['#', '?']).next().unwrap_or_default();
+    let path = extensions
+        .iter()
+        .find_map(|extension| path.strip_suffix(extension))
+        .unwrap_or(path);
+
+    match to_slug(path) {
+        Ok(slug) if !slug.is_empty() => Some(format!("/{}", slug)),
+        _ => None,
+    }
+}
+
+/// The file extension for code in the named language
+pub(crate) fn code_extension(language: &str) -> String {
+    let language = language.to_lowercase();
+
+    match language.as_str() {
+        "rust" => "rs".into(),
+        "python" => "py".into(),
+        "javascript" => "js".into(),
+        "typescript" => "ts".into(),
+        "shell" | "bash" | "zsh" => "sh".into(),
+        "markdown" => "md".into(),
+        "yaml" => "yml".into(),
+        "emacs-lisp" | "elisp" => "el".into(),
+        _ if language.chars().all(|c| c.is_ascii_alphanumeric()) => language,
+        _ => "txt".into(),
+    }
+}
+
+/// Write rows of cells as CSV, quoting fields that contain a separator,
+/// quote or line break
+pub(crate) fn to_csv(rows: &[Vec<String>]) -> String {
+    rows.iter()
+        .map(|row| {
+            let fields = row
+                .iter()
+                .map(|field| match field.contains([',', '"', '\n']) {
+                    true => format!("\"{}\"", field.replace('"', "\"\"")),
+                    false => field.to_owned(),
+                });
+            format!("{}\n", fields.collect::<Vec<String>>().join(","))
+        })
+        .collect()
+}
+
+/// Accumulates Subtext lines, making sure that converted text always reads
+/// back as the intended kind of block
+#[derive(Debug, Default)]
+pub(crate) struct Writer {
+    lines: Vec<String>,
+}
+
+impl Writer {
+    pub fn header(&mut self, text: &str) {
+        self.sigil_line('#', text);
+    }
+
+    pub fn list(&mut self, text: &str) {
+        self.sigil_line('-', text);
+    }
+
+    pub fn quote(&mut self, text: &str) {
+        self.sigil_line('>', text);
+    }
+
+    /// Write a paragraph, skipping it if it is empty. Text that starts with
+    /// a sigil is indented by a space so that it stays a paragraph.
+    pub fn paragraph(&mut self, text: &str) {
+        let text = clean_text(text);
+
+        match text.starts_with(['#', '>', '-']) {
+            _ if text.is_empty() => (),
+            true => self.lines.push(format!(" {}", text)),
+            false => self.lines.push(text),
+        }
+    }
+
+    pub fn blank(&mut self) {
+        self.lines.push(String::new());
+    }
+
+    /// Start a new section, separated from any previous one by a single
+    /// blank line
+    pub fn separate(&mut self) {
+        if matches!(self.lines.last(), Some(line) if !line.is_empty()) {
+            self.blank();
+        }
+    }
+
+    fn sigil_line(&mut self, sigil: char, text: &str) {
+        let text = clean_text(text);
+
+        self.lines.push(match text.is_empty() {
+            true => sigil.to_string(),
+            false => format!("{} {}", sigil, text),
+        });
+    }
+
+    /// The lines written so far as a document, without trailing blank lines
+    pub fn to_subtext(&self) -> String {
+        let end = self
+            .lines
+            .iter()
+            .rposition(|line| !line.is_empty())
+            .map_or(0, |index| index + 1);
+
+        self.lines[..end]
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+
+    pub fn into_blocks(self) -> Result<Vec<Block<Entity>>> {
+        Ok(crate::parse(self.to_subtext().as_bytes())?.collect())
+    }
+}
+
+/// Hands out slugs for split-out files, so that no two share a name
+#[derive(Debug, Default)]
+pub(crate) struct Slugs {
+    counts: HashMap<String, usize>,
+}
+
+impl Slugs {
+    /// A slug derived from `text`, or from `fallback` if the text has no
+    /// usable characters, with a numeric suffix if it has been taken
+    pub fn unique(&mut self, text: &str, fallback: &str) -> String {
+        let slug = match to_slug(text) {
+            Ok(slug) if !slug.is_empty() => slug.replace('/', "-"),
+            _ => fallback.to_owned(),
+        };
+        let count = self.counts.entry(slug.clone()).or_insert(0);
+        *count += 1;
+
+        match count {
+            1 => slug,
+            count => format!("{}-{}", slug, count),
+        }
+    }
+}
@@ -0,0 +1,383 @@
+//! Convert between Subtext and OPML, the outline format understood by most
+//! outliners and feed readers.
+//!
+//! On export, each header becomes an outline holding the blocks of its
+//! section, and a list that directly follows a paragraph is nested under
+//! it. Blank lines are dropped. A block with a single link is written as an
+//! outline of type `link`.
+//!
+//! On import, a top-level outline with children becomes a header followed
+//! by its children as list items, and deeper outlines are flattened into
+//! the list. With [OpmlOptions::max_depth], outlines at the given depth
+//! that have children link to a new note holding those children instead, so
+//! that deep outlines become a tree of notes. An outline's `_note` becomes
+//! a quote, and its `url` is kept as a link.
+use anyhow::{anyhow, Result};
+
+use crate::{
+    block::Block,
+    primitive::Entity,
+    render::{wiki_link_label, LinkResolver, LinkTarget},
+};
+
+use super::{
+    clean_text, hyperlink, join_label, relative_link, relative_slashlink,
+    xml::{escape_xml, tokenize, Attributes, Token},
+    Import, ImportedFile, LinkStyle, Loss, LossKind, Slugs, Writer,
+};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpmlOptions {
+    /// The deepest level of outline nesting to keep in a document, counting
+    /// top-level outlines as level 1. Outlines at this level that have
+    /// children link to a new note holding those children instead. `None`
+    /// keeps the whole outline in one document.
+    pub max_depth: Option<usize>,
+}
+
+/// An outline being written
+#[derive(Debug, Default)]
+struct Node {
+    text: String,
+    url: Option<String>,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn write(&self, lines: &mut Vec<String>, depth: usize) {
+        let indent = "  ".repeat(depth);
+        let mut attributes = format!("text=\"{}\"", escape_xml(&self.text));
+        if let Some(url) = &self.url {
+            attributes.push_str(&format!(" type=\"link\" url=\"{}\"", escape_xml(url)));
+        }
+
+        match self.children.is_empty() {
+            true => lines.push(format!("{}<outline {}/>", indent, attributes)),
+            false => {
+                lines.push(format!("{}<outline {}>", indent, attributes));
+                for child in &self.children {
+                    child.write(lines, depth + 1);
+                }
+                lines.push(format!("{}</outline>", indent));
+            }
+        }
+    }
+}
+
+/// The URL of a block's only link, if it has exactly one that resolves
+fn only_link<E>(block: &Block<E>, resolver: &impl LinkResolver) -> Option<String>
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let mut urls = block
+        .to_content_entities()
+        .into_iter()
+        .filter_map(|entity| match entity.as_ref() {
+            Entity::HyperLink(url) => Some(url.to_string()),
+            Entity::SlashLink(text) => resolver.resolve(&LinkTarget::SlashLink(text.as_ref())),
+            Entity::WikiLink(text) => {
+                resolver.resolve(&LinkTarget::WikiLink(wiki_link_label(text.as_ref())))
+            }
+            _ => None,
+        });
+
+    match (urls.next(), urls.next()) {
+        (Some(url), None) => Some(url),
+        _ => None,
+    }
+}
+
+/// Convert blocks to an OPML document with the given title. Slashlinks and
+/// wikilinks only count as links if the resolver gives them a URL.
+pub fn to_opml<E>(blocks: &[Block<E>], title: &str, resolver: &impl LinkResolver) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let mut nodes = Vec::<Node>::new();
+    let mut in_section = false;
+    let mut after_paragraph = false;
+
+    for block in blocks {
+        let node = Node {
+            text: block.to_text_content().trim().to_owned(),
+            url: only_link(block, resolver),
+            children: Vec::new(),
+        };
+
+        let siblings = match nodes.last_mut() {
+            Some(section) if in_section => &mut section.children,
+            _ => &mut nodes,
+        };
+
+        match block {
+            Block::Header(_) => {
+                nodes.push(node);
+                in_section = true;
+            }
+            Block::List(_) if after_paragraph => match siblings.last_mut() {
+                Some(paragraph) => paragraph.children.push(node),
+                None => siblings.push(node),
+            },
+            Block::Blank(_) => (),
+            _ => siblings.push(node),
+        }
+
+        after_paragraph = match block {
+            Block::Paragraph(_) => true,
+            Block::List(_) => after_paragraph,
+            _ => false,
+        };
+    }
+
+    let mut lines = vec![
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>".to_owned(),
+        "<opml version=\"2.0\">".to_owned(),
+        "  <head>".to_owned(),
+    ];
+    if !title.is_empty() {
+        lines.push(format!("    <title>{}</title>", escape_xml(title)));
+    }
+    lines.push("  </head>".into());
+    lines.push("  <body>".into());
+    for node in &nodes {
+        node.write(&mut lines, 2);
+    }
+    lines.push("  </body>".into());
+    lines.push("</opml>".into());
+
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+/// An element of the OPML document
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Attributes,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn outlines(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter(|child| child.name == "outline")
+    }
+}
+
+/// Build the tree of elements, requiring tags to be balanced
+fn read_tree(input: &str) -> Result<Element> {
+    let mut stack = vec![Element::default()];
+
+    for token in tokenize(input)? {
+        match token {
+            Token::Start {
+                name,
+                attributes,
+                empty,
+            } => {
+                let element = Element {
+                    name,
+                    attributes,
+                    ..Default::default()
+                };
+                match empty {
+                    true => stack.last_mut().unwrap().children.push(element),
+                    false => stack.push(element),
+                }
+            }
+            Token::End(name) => {
+                let element = stack
+                    .pop()
+                    .filter(|element| element.name == name && !element.name.is_empty())
+                    .ok_or_else(|| anyhow!("Unexpected closing tag </{}>", name))?;
+                stack.last_mut().unwrap().children.push(element);
+            }
+            Token::Text(text) => stack.last_mut().unwrap().text.push_str(&text),
+        }
+    }
+
+    match stack.len() {
+        1 => Ok(stack.pop().unwrap()),
+        _ => Err(anyhow!(
+            "The <{}> element is never closed",
+            stack.last().unwrap().name
+        )),
+    }
+}
+
+struct Converter<'a> {
+    options: &'a OpmlOptions,
+    slugs: Slugs,
+    files: Vec<ImportedFile>,
+    losses: Vec<Loss>,
+    flattened: usize,
+}
+
+impl Converter<'_> {
+    /// The text of an outline, with its URL as a link
+    fn text(&mut self, outline: &Element) -> String {
+        let text = clean_text(outline.attribute("text").unwrap_or_default());
+        let url = ["url", "htmlUrl", "xmlUrl"]
+            .iter()
+            .find_map(|name| outline.attribute(name))
+            .map(str::trim)
+            .filter(|url| !url.is_empty() && !text.contains(url));
+
+        match url {
+            None => text,
+            Some(url) if url.contains(':') => {
+                hyperlink(&text, url, LinkStyle::Bare, &mut self.losses)
+            }
+            Some(url) => match relative_slashlink(url, &[".opml", ".subtext"]) {
+                Some(slashlink) => relative_link(&text, &slashlink),
+                None => {
+                    self.losses.push(Loss {
+                        kind: LossKind::Link,
+                        detail: format!("The link from {:?} to {} was dropped", text, url),
+                    });
+                    text
+                }
+            },
+        }
+    }
+
+    /// Write an outline's `_note` as a quote
+    fn note(&mut self, writer: &mut Writer, outline: &Element) {
+        if let Some(note) = outline.attribute("_note") {
+            if !note.trim().is_empty() {
+                writer.quote(note);
+            }
+        }
+    }
+
+    fn at_cutoff(&self, depth: usize) -> bool {
+        matches!(self.options.max_depth, Some(max) if depth >= max)
+    }
+
+    /// Move the children of an outline to a note of their own, returning
+    /// the slashlink to it
+    fn extract(&mut self, text: &str, outline: &Element) -> String {
+        let slug = self.slugs.unique(text, "note");
+        let mut note = Writer::default();
+        self.write_outlines(&mut note, outline);
+
+        self.files.push(ImportedFile {
+            slug: slug.clone(),
+            extension: "subtext".into(),
+            content: note.to_subtext(),
+        });
+        format!("/{}", slug)
+    }
+
+    /// Write the outlines of a document or note: those with children become
+    /// headers, and the rest list items
+    fn write_outlines(&mut self, writer: &mut Writer, parent: &Element) {
+        let mut after_section = false;
+
+        for outline in parent.outlines() {
+            let text = self.text(outline);
+            let has_children = outline.outlines().next().is_some();
+
+            if has_children && !self.at_cutoff(1) {
+                writer.separate();
+                writer.header(&text);
+                self.note(writer, outline);
+                self.write_items(writer, outline, 2);
+                after_section = true;
+                continue;
+            }
+
+            if after_section {
+                writer.separate();
+                after_section = false;
+            }
+
+            match has_children {
+                true => {
+                    let slashlink = self.extract(&text, outline);
+                    writer.list(&join_label(&text, &slashlink));
+                }
+                false if text.is_empty() => (),
+                false => writer.list(&text),
+            }
+            self.note(writer, outline);
+        }
+    }
+
+    /// Write the children of an outline as list items, flattening them up to
+    /// the cutoff
+    fn write_items(&mut self, writer: &mut Writer, parent: &Element, depth: usize) {
+        for outline in parent.outlines() {
+            let text = self.text(outline);
+            let has_children = outline.outlines().next().is_some();
+
+            if depth > 2 {
+                self.flattened += 1;
+            }
+
+            if has_children && self.at_cutoff(depth) {
+                let slashlink = self.extract(&text, outline);
+                writer.list(&join_label(&text, &slashlink));
+                self.note(writer, outline);
+            } else {
+                if !text.is_empty() {
+                    writer.list(&text);
+                }
+                self.note(writer, outline);
+                self.write_items(writer, outline, depth + 1);
+            }
+        }
+    }
+}
+
+/// Convert an OPML document to Subtext. The title of the document becomes
+/// its first header.
+pub fn from_opml(input: &str, options: &OpmlOptions) -> Result<Import> {
+    let tree = read_tree(input)?;
+    let opml = tree
+        .child("opml")
+        .ok_or_else(|| anyhow!("Not an OPML document"))?;
+    let body = opml
+        .child("body")
+        .ok_or_else(|| anyhow!("The OPML document has no body"))?;
+
+    let mut converter = Converter {
+        options,
+        slugs: Slugs::default(),
+        files: Vec::new(),
+        losses: Vec::new(),
+        flattened: 0,
+    };
+    let mut writer = Writer::default();
+
+    if let Some(title) = opml.child("head").and_then(|head| head.child("title")) {
+        if !title.text.trim().is_empty() {
+            writer.header(&title.text);
+            writer.blank();
+        }
+    }
+    converter.write_outlines(&mut writer, body);
+
+    if converter.flattened > 0 {
+        converter.losses.push(Loss {
+            kind: LossKind::NestedList,
+            detail: format!("{} nested outlines were flattened", converter.flattened),
+        });
+    }
+
+    Ok(Import {
+        blocks: writer.into_blocks()?,
+        files: converter.files,
+        losses: converter.losses,
+    })
+}
@@ -0,0 +1,457 @@
+//! Convert between Subtext and Emacs Org mode. Headlines of any depth become
+//! headers, plain list items become list items (flattened when nested) and
+//! `#+BEGIN_QUOTE` blocks become quotes. Lines map one to one in both
+//! directions, so a document survives a round trip through Org.
+//!
+//! On import, `[[target][description]]` links become a slashlink for
+//! `file:` targets, a hyperlink for web URLs and a wikilink for links to
+//! headlines. Keywords, comments and property drawers are dropped, and
+//! source blocks and tables are kept as text or split out into files.
+//!
+//! On export, slashlinks and wikilinks are written as Org links to whatever
+//! URL the resolver returns; [org_file_link] links each slashlink to the
+//! `.org` file of the same name, leaving links to other peers as text.
+use anyhow::Result;
+
+use crate::{
+    block::{group_blocks, Block},
+    format::normalize_newlines,
+    primitive::Entity,
+    render::{wiki_link_label, LinkResolver, LinkTarget},
+};
+
+use super::{
+    code_extension, hyperlink, relative_link, relative_slashlink, to_csv, Embed, Import,
+    ImportedFile, LinkStyle, Loss, LossKind, Slugs, Writer,
+};
+
+/// Org treats a zero width space at the start of a line as plain text, which
+/// makes it the conventional way to escape markup
+const ZERO_WIDTH_SPACE: char = '\u{200B}';
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrgOptions {
+    /// Source and example blocks are extracted to a file named after their
+    /// language
+    pub code_blocks: Embed,
+    /// Tables are extracted to a CSV file
+    pub tables: Embed,
+}
+
+impl Default for OrgOptions {
+    fn default() -> Self {
+        OrgOptions {
+            code_blocks: Embed::Inline,
+            tables: Embed::Inline,
+        }
+    }
+}
+
+/// A `#+BEGIN_` block other than a quote: its language, if any, and lines
+type CodeBlock = (Option<String>, Vec<String>);
+
+struct Importer<'a> {
+    options: &'a OrgOptions,
+    writer: Writer,
+    import: Import,
+    slugs: Slugs,
+    table: Vec<Vec<String>>,
+    nested_items: usize,
+}
+
+impl Importer<'_> {
+    fn lose(&mut self, kind: LossKind, detail: String) {
+        self.import.losses.push(Loss { kind, detail });
+    }
+
+    /// Move content to a file of its own and link to it from the document
+    fn extract(&mut self, name: &str, extension: &str, content: String) {
+        let slug = self.slugs.unique(name, name);
+        self.writer.paragraph(&format!("/{}", slug));
+        self.import.files.push(ImportedFile {
+            slug,
+            extension: extension.into(),
+            content,
+        });
+    }
+
+    fn end_table(&mut self) {
+        if self.table.is_empty() {
+            return;
+        }
+
+        let rows = std::mem::take(&mut self.table);
+        match self.options.tables {
+            Embed::Inline => {
+                self.lose(
+                    LossKind::Table,
+                    format!("A {} row table was kept as a list", rows.len()),
+                );
+                for row in rows {
+                    let text = import_links(&row.join(" | "), &mut self.import.losses);
+                    self.writer.list(&text);
+                }
+            }
+            Embed::Extract => self.extract("table", "csv", to_csv(&rows)),
+        }
+    }
+
+    fn end_code_block(&mut self, (language, lines): CodeBlock) {
+        match self.options.code_blocks {
+            Embed::Inline => {
+                self.lose(
+                    LossKind::CodeBlock,
+                    format!("A {} line block was kept as paragraphs", lines.len()),
+                );
+                for line in lines {
+                    match line.trim().is_empty() {
+                        true => self.writer.blank(),
+                        false => self.writer.paragraph(&line),
+                    }
+                }
+            }
+            Embed::Extract => {
+                let extension = language.as_deref().map_or("txt".into(), code_extension);
+                let content = lines.iter().map(|line| format!("{}\n", line)).collect();
+                self.extract("code", &extension, content);
+            }
+        }
+    }
+
+    fn read_line(&mut self, line: &str) {
+        // An escaped line is always plain text
+        if let Some(text) = line.strip_prefix(ZERO_WIDTH_SPACE) {
+            self.end_table();
+            let text = import_links(text, &mut self.import.losses);
+            self.writer.paragraph(&text);
+            return;
+        }
+
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+
+        if let Some(row) = trimmed.strip_prefix('|') {
+            // Rules between rows only separate them visually
+            if !row.starts_with('-') {
+                let row = row.strip_suffix('|').unwrap_or(row);
+                self.table
+                    .push(row.split('|').map(|cell| cell.trim().to_owned()).collect());
+            }
+            return;
+        }
+        self.end_table();
+
+        if let Some(title) = keyword(trimmed, "TITLE") {
+            let text = import_links(title, &mut self.import.losses);
+            self.writer.header(&text);
+        } else if trimmed.starts_with("#+") || trimmed == "#" || trimmed.starts_with("# ") {
+            self.lose(
+                LossKind::Metadata,
+                format!("The line {:?} was dropped", trimmed),
+            );
+        } else if let Some(text) = headline(line) {
+            let level = line.chars().take_while(|c| *c == '*').count();
+            let text = import_links(text, &mut self.import.losses);
+
+            if level > 1 {
+                self.lose(
+                    LossKind::HeadingLevel,
+                    format!("The level {} headline {:?} became a header", level, text),
+                );
+            }
+            self.writer.header(&text);
+        } else if let Some(text) = list_item(trimmed, indent) {
+            if indent > 0 {
+                self.nested_items += 1;
+            }
+            let text = import_links(text, &mut self.import.losses);
+            self.writer.list(&text);
+        } else if trimmed.is_empty() {
+            self.writer.blank();
+        } else {
+            let text = import_links(trimmed, &mut self.import.losses);
+            self.writer.paragraph(&text);
+        }
+    }
+}
+
+/// The value of a `#+KEYWORD:` line, matched case-insensitively
+fn keyword<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let (key, value) = line.strip_prefix("#+")?.split_once(':')?;
+    key.eq_ignore_ascii_case(name).then_some(value)
+}
+
+/// The name and arguments of a `#+BEGIN_NAME arguments` line
+fn begin_block(line: &str) -> Option<(String, &str)> {
+    let rest = line.get(..8)?;
+    if !rest.eq_ignore_ascii_case("#+begin_") {
+        return None;
+    }
+
+    let rest = &line[8..];
+    let (name, arguments) = rest.split_once(' ').unwrap_or((rest, ""));
+    Some((name.to_uppercase(), arguments.trim()))
+}
+
+fn is_end_block(line: &str, name: &str) -> bool {
+    line.get(..6)
+        .is_some_and(|end| end.eq_ignore_ascii_case("#+end_"))
+        && line[6..].trim().eq_ignore_ascii_case(name)
+}
+
+/// The text of a headline: one or more stars at the start of a line
+/// followed by a space
+fn headline(line: &str) -> Option<&str> {
+    let text = line.trim_start_matches('*');
+    match text.len() < line.len() && (text.is_empty() || text.starts_with(' ')) {
+        true => Some(text),
+        false => None,
+    }
+}
+
+/// The text of a plain list item. A `*` bullet is only a list item when it
+/// is indented, since it would otherwise start a headline.
+fn list_item(line: &str, indent: usize) -> Option<&str> {
+    if let Some(text) = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("+ "))
+        .or_else(|| line.strip_prefix("* ").filter(|_| indent > 0))
+    {
+        return Some(text);
+    }
+
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    match digits {
+        0 => None,
+        _ => line[digits..]
+            .strip_prefix(". ")
+            .or_else(|| line[digits..].strip_prefix(") ")),
+    }
+}
+
+/// Replace the Org links in a line of text with Subtext links
+fn import_links(text: &str, losses: &mut Vec<Loss>) -> String {
+    let mut output = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("[[") {
+        let end = match rest[start..].find("]]") {
+            Some(end) => start + end + 2,
+            None => break,
+        };
+        let link = &rest[start..end];
+        let (target, description) = match link[2..link.len() - 2].split_once("][") {
+            Some((target, description)) => (target, Some(description.trim())),
+            None => (&link[2..link.len() - 2], None),
+        };
+
+        output.push_str(&rest[..start]);
+        output.push_str(&import_link(link, target, description, losses));
+        rest = &rest[end..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+fn import_link(
+    link: &str,
+    target: &str,
+    description: Option<&str>,
+    losses: &mut Vec<Loss>,
+) -> String {
+    let label = description.unwrap_or_default();
+
+    if let Some(path) = target.strip_prefix("file:") {
+        let path = path.split("::").next().unwrap_or_default();
+        if let Some(slashlink) = relative_slashlink(path, &[".org", ".subtext"]) {
+            return relative_link(label, &slashlink);
+        }
+    } else if target.starts_with("http://") || target.starts_with("https://") {
+        return hyperlink(label, target, LinkStyle::Bare, losses);
+    } else if description.is_none() && !target.contains(':') {
+        // A link to a headline reads the same as a wikilink
+        return format!("[[{}]]", target.trim_start_matches(['*', '#']));
+    }
+
+    losses.push(Loss {
+        kind: LossKind::Link,
+        detail: format!("The link {} was dropped", link),
+    });
+    match label.is_empty() {
+        true => target.to_owned(),
+        false => label.to_owned(),
+    }
+}
+
+/// Convert an Org document to Subtext
+pub fn from_org(input: &str, options: &OrgOptions) -> Result<Import> {
+    let mut importer = Importer {
+        options,
+        writer: Writer::default(),
+        import: Import::default(),
+        slugs: Slugs::default(),
+        table: Vec::new(),
+        nested_items: 0,
+    };
+    let mut quote = false;
+    let mut drawer = false;
+    let mut code_block: Option<(String, CodeBlock)> = None;
+
+    for line in normalize_newlines(input).lines() {
+        let trimmed = line.trim();
+
+        if let Some((name, (_, lines))) = code_block.as_mut() {
+            match is_end_block(trimmed, name) {
+                true => {
+                    let (_, block) = code_block.take().unwrap_or_default();
+                    importer.end_code_block(block);
+                }
+                false => lines.push(unescape(line).to_owned()),
+            }
+        } else if drawer {
+            drawer = !trimmed.eq_ignore_ascii_case(":END:");
+        } else if quote {
+            if is_end_block(trimmed, "QUOTE") {
+                quote = false;
+            } else if !trimmed.is_empty() {
+                let text = import_links(unescape(trimmed), &mut importer.import.losses);
+                importer.writer.quote(&text);
+            }
+        } else if trimmed.eq_ignore_ascii_case(":PROPERTIES:") {
+            importer.end_table();
+            importer.lose(LossKind::Metadata, "A property drawer was dropped".into());
+            drawer = true;
+        } else if let Some((name, arguments)) = begin_block(trimmed) {
+            importer.end_table();
+            match name.as_str() {
+                "QUOTE" => quote = true,
+                _ => {
+                    let language = match name.as_str() {
+                        "SRC" => arguments.split_whitespace().next().map(str::to_owned),
+                        _ => None,
+                    };
+                    code_block = Some((name, (language, Vec::new())));
+                }
+            }
+        } else {
+            importer.read_line(line);
+        }
+    }
+
+    importer.end_table();
+    if let Some((_, block)) = code_block {
+        importer.end_code_block(block);
+    }
+    if importer.nested_items > 0 {
+        let detail = format!("{} nested list items were flattened", importer.nested_items);
+        importer.lose(LossKind::NestedList, detail);
+    }
+
+    let mut import = importer.import;
+    import.blocks = importer.writer.into_blocks()?;
+    Ok(import)
+}
+
+/// Remove the comma that protects a line inside a block from being read as
+/// a headline or keyword
+fn unescape(line: &str) -> &str {
+    match line.strip_prefix(',') {
+        Some(rest) if rest.starts_with('*') || rest.starts_with("#+") => rest,
+        _ => line,
+    }
+}
+
+/// Link each slashlink to the Org file with the same name, relative to the
+/// document. Links to other peers' notes have no local file, so they are
+/// left as text.
+pub fn org_file_link(target: &LinkTarget) -> Option<String> {
+    match target {
+        LinkTarget::SlashLink(slashlink) if slashlink.starts_with('@') => None,
+        LinkTarget::SlashLink(slashlink) => Some(format!(
+            "file:{}.org",
+            slashlink
+                .trim_start_matches('/')
+                .split('#')
+                .next()
+                .unwrap_or_default()
+        )),
+        LinkTarget::WikiLink(_) => None,
+    }
+}
+
+/// Render the content entities of a block as Org text
+fn render_inline<E>(block: &Block<E>, resolver: &impl LinkResolver) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    block
+        .to_content_entities()
+        .iter()
+        .map(|entity| match entity.as_ref() {
+            Entity::HyperLink(url) => format!("[[{}]]", url.as_ref()),
+            Entity::SlashLink(text) => {
+                match resolver.resolve(&LinkTarget::SlashLink(text.as_ref())) {
+                    Some(url) if url.starts_with("file:") => format!("[[{}]]", url),
+                    Some(url) => format!("[[{}][{}]]", url, text.as_ref()),
+                    None => text.to_string(),
+                }
+            }
+            Entity::WikiLink(text) => {
+                let label = wiki_link_label(text.as_ref());
+                match resolver.resolve(&LinkTarget::WikiLink(label)) {
+                    Some(url) => format!("[[{}][{}]]", url, label),
+                    None => text.to_string(),
+                }
+            }
+            entity => entity.to_string(),
+        })
+        .collect()
+}
+
+/// Whether a line of text would be read as a headline, list item, table,
+/// keyword, comment or fixed-width line
+fn is_org_syntax(line: &str) -> bool {
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+
+    line.starts_with(['*', '#', '-', '+', '|', ':'])
+        || (digits > 0 && line[digits..].starts_with(['.', ')']))
+}
+
+/// Convert blocks to Org. Slashlinks and wikilinks become Org links only if
+/// the resolver gives them a URL.
+pub fn to_org<E>(blocks: &[Block<E>], resolver: &impl LinkResolver) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let mut lines = Vec::<String>::new();
+
+    for group in group_blocks(blocks) {
+        let text = group.iter().map(|block| render_inline(block, resolver));
+
+        match group.first() {
+            Some(Block::Header(_)) => lines.extend(text.map(|text| format!("* {}", text))),
+            Some(Block::List(_)) => lines.extend(text.map(|text| format!("- {}", text))),
+            Some(Block::Paragraph(_)) => {
+                lines.extend(text.map(|text| match is_org_syntax(&text) {
+                    true => format!("{}{}", ZERO_WIDTH_SPACE, text),
+                    false => text,
+                }))
+            }
+            Some(Block::Quote(_)) => {
+                lines.push("#+BEGIN_QUOTE".into());
+                lines.extend(text.map(|text| {
+                    match text.starts_with('*') || text.starts_with("#+") {
+                        true => format!(",{}", text),
+                        false => text,
+                    }
+                }));
+                lines.push("#+END_QUOTE".into());
+            }
+            Some(Block::Blank(_)) => lines.extend(group.iter().map(|_| String::new())),
+            None => (),
+        }
+    }
+
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
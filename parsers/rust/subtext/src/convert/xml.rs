@@ -0,0 +1,270 @@
+//! A small tokenizer for the XML-like formats that converters read. It
+//! understands elements, attributes, text, character references and CDATA,
+//! and skips comments, processing instructions and doctypes. The content of
+//! `script` and `style` elements is kept as raw text, as in HTML. When reading
+//! HTML, a `<` that doesn't start a well-formed tag is read as text, as in
+//! `1<2`. Checking that tags are balanced is left to the caller.
+use anyhow::{anyhow, Result};
+
+/// Elements whose content is read as text rather than markup
+const RAW_TEXT: &[&str] = &["script", "style"];
+
+/// The name and value of each attribute of a tag, in order
+pub(crate) type Attributes = Vec<(String, String)>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token {
+    /// An opening tag; `empty` is set for a self-closing tag such as `<br/>`
+    Start {
+        name: String,
+        attributes: Attributes,
+        empty: bool,
+    },
+    End(String),
+    /// Text with its character references decoded
+    Text(String),
+}
+
+/// Decode character references such as `&amp;` and `&#x2014;`. References
+/// that aren't recognized are left as they are.
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(index) = rest.find('&') {
+        decoded.push_str(&rest[..index]);
+        rest = &rest[index..];
+
+        let reference = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        let character = reference.and_then(|reference| match reference {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => match reference.strip_prefix('#') {
+                Some(hex) if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16)
+                    .ok()
+                    .and_then(char::from_u32),
+                Some(decimal) => decimal.parse().ok().and_then(char::from_u32),
+                None => None,
+            },
+        });
+
+        match (reference, character) {
+            (Some(reference), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[reference.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Escape text for use in XML content or in a quoted attribute value. Tabs
+/// and line breaks are written as character references so that attribute
+/// values keep them, and characters that XML does not allow are dropped.
+pub(crate) fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push_str(&format!("&#{};", character as u32)),
+            '\u{0}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => (),
+            _ => escaped.push(character),
+        }
+    }
+
+    escaped
+}
+
+fn is_name_character(character: char) -> bool {
+    !character.is_whitespace() && !matches!(character, '/' | '>' | '=' | '"' | '\'' | '<')
+}
+
+/// Read the attributes and end of an opening tag, starting just after its
+/// name. Returns the attributes, whether the tag closed itself, and the
+/// input after the tag.
+fn read_attributes(mut rest: &str) -> Result<(Attributes, bool, &str)> {
+    let mut attributes = Vec::new();
+
+    loop {
+        rest = rest.trim_start();
+
+        if let Some(after) = rest.strip_prefix("/>") {
+            return Ok((attributes, true, after));
+        } else if let Some(after) = rest.strip_prefix('>') {
+            return Ok((attributes, false, after));
+        } else if rest.starts_with('/') {
+            // A stray slash, as in `<a href=x / >`
+            rest = &rest[1..];
+            continue;
+        }
+
+        let length = rest.find(|c| !is_name_character(c)).unwrap_or(rest.len());
+        if length == 0 {
+            return Err(anyhow!("Unterminated tag"));
+        }
+        let name = rest[..length].to_owned();
+        rest = rest[length..].trim_start();
+
+        let value = match rest.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start();
+                match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let end = after[1..]
+                            .find(quote)
+                            .ok_or_else(|| anyhow!("Unterminated value for attribute {}", name))?;
+                        rest = &after[end + 2..];
+                        decode_entities(&after[1..end + 1])
+                    }
+                    // Unquoted values are allowed in HTML
+                    _ => {
+                        let end = after
+                            .find(|c: char| c.is_whitespace() || c == '>')
+                            .unwrap_or(after.len());
+                        rest = &after[end..];
+                        decode_entities(&after[..end])
+                    }
+                }
+            }
+            None => String::new(),
+        };
+
+        attributes.push((name, value));
+    }
+}
+
+/// Read the tag at the start of the input, returning it and the input after
+/// it. Returns `None` if the `<` is not followed by a tag name, and an error
+/// if the tag is malformed.
+fn read_tag(input: &str) -> Result<Option<(Token, &str)>> {
+    let (closing, after) = match input.strip_prefix("</") {
+        Some(after) => (true, after),
+        None => (false, &input[1..]),
+    };
+    let length = after.find(|c| !is_name_character(c)).unwrap_or(after.len());
+
+    if length == 0 {
+        return Ok(None);
+    }
+
+    let name = after[..length].to_owned();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err(anyhow!("Invalid tag name {:?}", name));
+    }
+
+    let rest = &after[length..];
+    if closing {
+        // Anything between the name and the `>` is ignored, as in HTML
+        let end = rest
+            .find(['<', '>'])
+            .filter(|end| rest[*end..].starts_with('>'))
+            .ok_or_else(|| anyhow!("Unterminated tag"))?;
+        return Ok(Some((Token::End(name), &rest[end + 1..])));
+    }
+
+    let (attributes, empty, rest) = read_attributes(rest)?;
+    Ok(Some((
+        Token::Start {
+            name,
+            attributes,
+            empty,
+        },
+        rest,
+    )))
+}
+
+/// Split an XML document into tags and text
+pub(crate) fn tokenize(input: &str) -> Result<Vec<Token>> {
+    split(input, false)
+}
+
+/// Split an HTML document into tags and text. Unlike [tokenize], a `<` that
+/// doesn't start a well-formed tag is read as text.
+pub(crate) fn tokenize_html(input: &str) -> Result<Vec<Token>> {
+    split(input, true)
+}
+
+fn split(input: &str, lenient: bool) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            tokens.push(Token::Text(decode_entities(rest)));
+            break;
+        };
+
+        if start > 0 {
+            tokens.push(Token::Text(decode_entities(&rest[..start])));
+        }
+        rest = &rest[start..];
+
+        let skip_to = |rest: &str, end: &str| -> Result<usize> {
+            rest.find(end)
+                .map(|index| index + end.len())
+                .ok_or_else(|| anyhow!("Expected {:?} before the end of the document", end))
+        };
+
+        if rest.starts_with("<!--") {
+            rest = &rest[skip_to(rest, "-->")?..];
+        } else if let Some(after) = rest.strip_prefix("<![CDATA[") {
+            let end = skip_to(after, "]]>")?;
+            tokens.push(Token::Text(after[..end - 3].to_owned()));
+            rest = &after[end..];
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = &rest[skip_to(rest, ">")?..];
+        } else {
+            let tag = match read_tag(rest) {
+                Err(_) if lenient => None,
+                tag => tag?,
+            };
+
+            let Some((token, after_tag)) = tag else {
+                // A `<` that doesn't start a tag is text
+                tokens.push(Token::Text("<".into()));
+                rest = &rest[1..];
+                continue;
+            };
+            rest = after_tag;
+
+            // The content of a script or style is not markup
+            if let Token::Start {
+                name, empty: false, ..
+            } = &token
+            {
+                if RAW_TEXT.iter().any(|raw| raw.eq_ignore_ascii_case(name)) {
+                    let end = rest
+                        .to_ascii_lowercase()
+                        .find(&format!("</{}", name.to_ascii_lowercase()))
+                        .unwrap_or(rest.len());
+                    tokens.push(token);
+                    tokens.push(Token::Text(rest[..end].to_owned()));
+                    rest = &rest[end..];
+                    continue;
+                }
+            }
+
+            tokens.push(token);
+        }
+    }
+
+    Ok(tokens)
+}
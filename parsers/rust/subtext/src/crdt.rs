@@ -0,0 +1,571 @@
+//! A sequence CRDT for collaborative editing of Subtext documents.
+//!
+//! A document is modelled as a Replicated Growable Array (RGA) of blocks,
+//! where each block in turn holds an RGA of characters containing the raw
+//! line of Subtext (sigil included). Replicas exchange [Operation]s, which
+//! may be applied in any order and any number of times; replicas that have
+//! seen the same set of operations always materialize the same document.
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    str::FromStr,
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::{block::Block, primitive::Entity};
+
+/// Identifies a single replica taking part in an editing session. Every
+/// replica must be given a distinct site ID.
+pub type SiteId = u64;
+
+/// A globally unique identifier for an inserted element, made up of a
+/// Lamport timestamp and the ID of the site that created it. IDs are
+/// totally ordered, which is what makes concurrent inserts deterministic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OpId {
+    pub counter: u64,
+    pub site: SiteId,
+}
+
+impl Display for OpId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.counter, self.site)
+    }
+}
+
+impl FromStr for OpId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (counter, site) = s
+            .split_once('@')
+            .ok_or_else(|| anyhow!("Could not parse {} as an operation ID", s))?;
+
+        Ok(OpId {
+            counter: counter.parse()?,
+            site: site.parse()?,
+        })
+    }
+}
+
+/// A single edit to a document. Inserts refer to the element they follow
+/// (`None` meaning the start of the sequence).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Operation {
+    InsertBlock {
+        id: OpId,
+        after: Option<OpId>,
+    },
+    DeleteBlock {
+        id: OpId,
+    },
+    InsertChar {
+        block: OpId,
+        id: OpId,
+        after: Option<OpId>,
+        value: char,
+    },
+    DeleteChar {
+        block: OpId,
+        id: OpId,
+    },
+}
+
+impl Operation {
+    /// The Lamport timestamp carried by the operation, if it creates a new
+    /// element
+    fn counter(&self) -> Option<u64> {
+        match self {
+            Operation::InsertBlock { id, .. } | Operation::InsertChar { id, .. } => {
+                Some(id.counter)
+            }
+            _ => None,
+        }
+    }
+
+    /// Serialize a list of operations, one per line
+    pub fn encode_all(operations: &[Operation]) -> String {
+        operations
+            .iter()
+            .map(|operation| operation.to_string())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Parse a list of operations serialized with [Operation::encode_all]
+    pub fn decode_all(input: &str) -> Result<Vec<Operation>> {
+        input
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(Operation::from_str)
+            .collect()
+    }
+}
+
+fn format_after(after: &Option<OpId>) -> String {
+    match after {
+        Some(id) => id.to_string(),
+        None => "^".into(),
+    }
+}
+
+fn parse_after(input: &str) -> Result<Option<OpId>> {
+    match input {
+        "^" => Ok(None),
+        id => Ok(Some(OpId::from_str(id)?)),
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operation::InsertBlock { id, after } => write!(f, "+b {} {}", id, format_after(after)),
+            Operation::DeleteBlock { id } => write!(f, "-b {}", id),
+            Operation::InsertChar {
+                block,
+                id,
+                after,
+                value,
+            } => write!(
+                f,
+                "+c {} {} {} {:x}",
+                block,
+                id,
+                format_after(after),
+                *value as u32
+            ),
+            Operation::DeleteChar { block, id } => write!(f, "-c {} {}", block, id),
+        }
+    }
+}
+
+impl FromStr for Operation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split(' ').collect::<Vec<&str>>();
+
+        Ok(match parts.as_slice() {
+            ["+b", id, after] => Operation::InsertBlock {
+                id: id.parse()?,
+                after: parse_after(after)?,
+            },
+            ["-b", id] => Operation::DeleteBlock { id: id.parse()? },
+            ["+c", block, id, after, value] => Operation::InsertChar {
+                block: block.parse()?,
+                id: id.parse()?,
+                after: parse_after(after)?,
+                value: char::from_u32(u32::from_str_radix(value, 16)?)
+                    .ok_or_else(|| anyhow!("Invalid character code {}", value))?,
+            },
+            ["-c", block, id] => Operation::DeleteChar {
+                block: block.parse()?,
+                id: id.parse()?,
+            },
+            _ => return Err(anyhow!("Could not parse {} as an operation", s)),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    id: OpId,
+    value: T,
+    deleted: bool,
+}
+
+/// A Replicated Growable Array, stored as a flat list of nodes in document
+/// order. Deleted nodes are kept as tombstones so that concurrent inserts
+/// can still find their anchors. The position of every node is indexed by
+/// its ID, so that looking up an anchor does not scan the list.
+#[derive(Debug, Clone)]
+struct Rga<T> {
+    nodes: Vec<Node<T>>,
+    positions: HashMap<OpId, usize>,
+}
+
+impl<T> Default for Rga<T> {
+    fn default() -> Self {
+        Rga {
+            nodes: Vec::new(),
+            positions: HashMap::new(),
+        }
+    }
+}
+
+impl<T> Rga<T> {
+    fn position(&self, id: &OpId) -> Option<usize> {
+        self.positions.get(id).copied()
+    }
+
+    fn contains(&self, id: &OpId) -> bool {
+        self.position(id).is_some()
+    }
+
+    fn get_mut(&mut self, id: &OpId) -> Option<&mut T> {
+        let position = self.position(id)?;
+        Some(&mut self.nodes[position].value)
+    }
+
+    /// Insert a value following the `after` anchor. Concurrent inserts at the
+    /// same anchor are ordered by descending ID, so every replica places them
+    /// identically. Returns false if the anchor is not known yet.
+    fn insert(&mut self, after: Option<OpId>, id: OpId, value: T) -> bool {
+        if self.contains(&id) {
+            return true;
+        }
+
+        let mut index = match after {
+            Some(after) => match self.position(&after) {
+                Some(position) => position + 1,
+                None => return false,
+            },
+            None => 0,
+        };
+
+        while index < self.nodes.len() && self.nodes[index].id > id {
+            index += 1;
+        }
+
+        self.nodes.insert(
+            index,
+            Node {
+                id,
+                value,
+                deleted: false,
+            },
+        );
+
+        // Nodes after the insertion point have shifted by one
+        for (position, node) in self.nodes.iter().enumerate().skip(index) {
+            self.positions.insert(node.id, position);
+        }
+
+        true
+    }
+
+    /// Mark a node as deleted. Returns false if the node is not known yet.
+    fn delete(&mut self, id: &OpId) -> bool {
+        match self.position(id) {
+            Some(position) => {
+                self.nodes[position].deleted = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn visible(&self) -> impl Iterator<Item = &Node<T>> {
+        self.nodes.iter().filter(|node| !node.deleted)
+    }
+
+    fn visible_id(&self, index: usize) -> Option<OpId> {
+        self.visible().nth(index).map(|node| node.id)
+    }
+
+    fn visible_len(&self) -> usize {
+        self.visible().count()
+    }
+}
+
+/// A single replica of a collaboratively edited Subtext document
+#[derive(Debug, Clone)]
+pub struct Replica {
+    site: SiteId,
+    clock: u64,
+    blocks: Rga<Rga<char>>,
+    log: Vec<Operation>,
+    pending: Vec<Operation>,
+    seen: HashSet<Operation>,
+}
+
+impl Replica {
+    pub fn new(site: SiteId) -> Self {
+        Replica {
+            site,
+            clock: 0,
+            blocks: Rga::default(),
+            log: Vec::new(),
+            pending: Vec::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Create a replica whose initial content is the given Subtext, with one
+    /// block per line
+    pub fn load(site: SiteId, input: &[u8]) -> Result<Self> {
+        let input = std::str::from_utf8(input)?;
+        let mut replica = Replica::new(site);
+
+        for (index, line) in input.split('\n').enumerate() {
+            replica.insert_block(index, line.trim_end_matches('\r'))?;
+        }
+
+        Ok(replica)
+    }
+
+    /// Create a copy of this replica with the same content and history, to
+    /// be edited by a different site
+    pub fn fork(&self, site: SiteId) -> Self {
+        let mut replica = self.clone();
+        replica.site = site;
+        replica
+    }
+
+    pub fn site(&self) -> SiteId {
+        self.site
+    }
+
+    /// All operations this replica has applied, in the order it applied them
+    pub fn operations(&self) -> &[Operation] {
+        &self.log
+    }
+
+    /// The number of visible blocks in the document
+    pub fn len(&self) -> usize {
+        self.blocks.visible_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn next_id(&mut self) -> OpId {
+        self.clock += 1;
+        OpId {
+            counter: self.clock,
+            site: self.site,
+        }
+    }
+
+    fn block_id(&self, index: usize) -> Result<OpId> {
+        self.blocks
+            .visible_id(index)
+            .ok_or_else(|| anyhow!("No block at index {}", index))
+    }
+
+    /// Insert a new block with the given raw line of Subtext so that it ends
+    /// up at `index`. Returns the operations to broadcast to other replicas.
+    pub fn insert_block(&mut self, index: usize, text: &str) -> Result<Vec<Operation>> {
+        if index > self.len() {
+            return Err(anyhow!(
+                "Cannot insert block at index {} of {}",
+                index,
+                self.len()
+            ));
+        }
+
+        if text.contains(['\n', '\r']) {
+            return Err(anyhow!("A block may not contain a line break"));
+        }
+
+        let after = match index {
+            0 => None,
+            index => Some(self.block_id(index - 1)?),
+        };
+        let id = self.next_id();
+        let mut operations = vec![Operation::InsertBlock { id, after }];
+        self.apply(Operation::InsertBlock { id, after });
+
+        let mut after = None;
+        for value in text.chars() {
+            let char_id = self.next_id();
+            let operation = Operation::InsertChar {
+                block: id,
+                id: char_id,
+                after,
+                value,
+            };
+            self.apply(operation.clone());
+            operations.push(operation);
+            after = Some(char_id);
+        }
+
+        Ok(operations)
+    }
+
+    /// Delete the block at `index`
+    pub fn delete_block(&mut self, index: usize) -> Result<Vec<Operation>> {
+        let operation = Operation::DeleteBlock {
+            id: self.block_id(index)?,
+        };
+        self.apply(operation.clone());
+        Ok(vec![operation])
+    }
+
+    /// Insert text into the block at `block_index`, starting at the given
+    /// character offset
+    pub fn insert_text(
+        &mut self,
+        block_index: usize,
+        offset: usize,
+        text: &str,
+    ) -> Result<Vec<Operation>> {
+        if text.contains(['\n', '\r']) {
+            return Err(anyhow!("A block may not contain a line break"));
+        }
+
+        let block = self.block_id(block_index)?;
+        let chars = self
+            .blocks
+            .get_mut(&block)
+            .ok_or_else(|| anyhow!("No block with ID {}", block))?;
+
+        let mut after = match offset {
+            0 => None,
+            offset => Some(
+                chars
+                    .visible_id(offset - 1)
+                    .ok_or_else(|| anyhow!("Offset {} is out of bounds", offset))?,
+            ),
+        };
+
+        let mut operations = Vec::new();
+        for value in text.chars() {
+            let id = self.next_id();
+            let operation = Operation::InsertChar {
+                block,
+                id,
+                after,
+                value,
+            };
+            self.apply(operation.clone());
+            operations.push(operation);
+            after = Some(id);
+        }
+
+        Ok(operations)
+    }
+
+    /// Delete `length` characters from the block at `block_index`, starting
+    /// at the given character offset
+    pub fn delete_text(
+        &mut self,
+        block_index: usize,
+        offset: usize,
+        length: usize,
+    ) -> Result<Vec<Operation>> {
+        let block = self.block_id(block_index)?;
+        let chars = self
+            .blocks
+            .get_mut(&block)
+            .ok_or_else(|| anyhow!("No block with ID {}", block))?;
+
+        let ids = chars
+            .visible()
+            .skip(offset)
+            .take(length)
+            .map(|node| node.id)
+            .collect::<Vec<OpId>>();
+
+        if ids.len() < length {
+            return Err(anyhow!(
+                "Cannot delete {} characters at offset {}",
+                length,
+                offset
+            ));
+        }
+
+        let operations = ids
+            .into_iter()
+            .map(|id| Operation::DeleteChar { block, id })
+            .collect::<Vec<Operation>>();
+
+        for operation in operations.iter() {
+            self.apply(operation.clone());
+        }
+
+        Ok(operations)
+    }
+
+    /// Apply an operation, whether it originated locally or at another
+    /// replica. Operations whose causal dependencies have not been seen yet
+    /// are held back until they can be integrated.
+    pub fn apply(&mut self, operation: Operation) {
+        if !self.seen.insert(operation.clone()) {
+            return;
+        }
+
+        if let Some(counter) = operation.counter() {
+            self.clock = u64::max(self.clock, counter);
+        }
+
+        if !self.integrate(&operation) {
+            self.pending.push(operation);
+            return;
+        }
+
+        self.log.push(operation);
+
+        // Integrating an operation may have unblocked pending ones
+        loop {
+            let pending = std::mem::take(&mut self.pending);
+            let count = pending.len();
+
+            for operation in pending {
+                if self.integrate(&operation) {
+                    self.log.push(operation);
+                } else {
+                    self.pending.push(operation);
+                }
+            }
+
+            if self.pending.len() == count {
+                break;
+            }
+        }
+    }
+
+    /// Apply every operation known to another replica
+    pub fn merge(&mut self, other: &Replica) {
+        for operation in other.log.iter().chain(other.pending.iter()) {
+            self.apply(operation.clone());
+        }
+    }
+
+    fn integrate(&mut self, operation: &Operation) -> bool {
+        match operation {
+            Operation::InsertBlock { id, after } => self.blocks.insert(*after, *id, Rga::default()),
+            Operation::DeleteBlock { id } => self.blocks.delete(id),
+            Operation::InsertChar {
+                block,
+                id,
+                after,
+                value,
+            } => match self.blocks.get_mut(block) {
+                Some(chars) => chars.insert(*after, *id, *value),
+                None => false,
+            },
+            Operation::DeleteChar { block, id } => match self.blocks.get_mut(block) {
+                Some(chars) => chars.delete(id),
+                None => false,
+            },
+        }
+    }
+
+    /// Serialize the current state of the document as Subtext
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_string().into_bytes()
+    }
+
+    /// Materialize the current state of the document as parsed blocks
+    pub fn to_blocks<E>(&self) -> Result<Vec<Block<E>>>
+    where
+        E: From<Entity> + AsRef<Entity>,
+    {
+        Ok(crate::parse(&self.to_bytes())?.collect())
+    }
+}
+
+impl Display for Replica {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines = self
+            .blocks
+            .visible()
+            .map(|block| block.value.visible().map(|node| node.value).collect())
+            .collect::<Vec<String>>();
+
+        write!(f, "{}", lines.join("\n"))
+    }
+}
@@ -0,0 +1,221 @@
+//! Decentralized identifiers (DIDs), which name peers in slashlinks such as
+//! `@did:key:z6Mk.../foo`.
+//!
+//! Any DID that follows the generic syntax of the DID Core spec is accepted.
+//! The `did:key` method is also understood: its identifier is a multibase
+//! public key, which is decoded so that the type of the key is known.
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, Result};
+
+use crate::cid::{decode_multibase, encode_base58, read_varint, write_varint};
+
+/// The kinds of public key a `did:key` DID may hold, by multicodec code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyType {
+    Ed25519,
+    X25519,
+    Secp256k1,
+    Bls12381G1,
+    Bls12381G2,
+    P256,
+    P384,
+    P521,
+    Rsa,
+}
+
+impl KeyType {
+    const ALL: [KeyType; 9] = [
+        KeyType::Ed25519,
+        KeyType::X25519,
+        KeyType::Secp256k1,
+        KeyType::Bls12381G1,
+        KeyType::Bls12381G2,
+        KeyType::P256,
+        KeyType::P384,
+        KeyType::P521,
+        KeyType::Rsa,
+    ];
+
+    /// The multicodec code that prefixes a key of this type
+    pub fn code(&self) -> u64 {
+        match self {
+            KeyType::Ed25519 => 0xed,
+            KeyType::X25519 => 0xec,
+            KeyType::Secp256k1 => 0xe7,
+            KeyType::Bls12381G1 => 0xea,
+            KeyType::Bls12381G2 => 0xeb,
+            KeyType::P256 => 0x1200,
+            KeyType::P384 => 0x1201,
+            KeyType::P521 => 0x1202,
+            KeyType::Rsa => 0x1205,
+        }
+    }
+
+    pub fn from_code(code: u64) -> Option<KeyType> {
+        KeyType::ALL
+            .into_iter()
+            .find(|key_type| key_type.code() == code)
+    }
+
+    /// The length in bytes of a key of this type, if it is fixed. Elliptic
+    /// curve keys other than Ed25519 and X25519 are in compressed form, and
+    /// RSA keys are DER-encoded.
+    pub fn key_length(&self) -> Option<usize> {
+        match self {
+            KeyType::Ed25519 | KeyType::X25519 => Some(32),
+            KeyType::Secp256k1 | KeyType::P256 => Some(33),
+            KeyType::Bls12381G1 => Some(48),
+            KeyType::Bls12381G2 => Some(96),
+            KeyType::P384 => Some(49),
+            KeyType::P521 => Some(67),
+            KeyType::Rsa => None,
+        }
+    }
+}
+
+/// A public key, as held by a `did:key` DID
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PublicKey {
+    pub key_type: KeyType,
+    pub bytes: Vec<u8>,
+}
+
+impl PublicKey {
+    /// Decode a base58btc multibase key (`z...`) with its multicodec prefix
+    pub fn from_multibase(input: &str) -> Result<Self> {
+        if !input.starts_with('z') {
+            return Err(anyhow!("A did:key public key must be base58btc (z...)"));
+        }
+
+        let bytes = decode_multibase(input)?;
+        let mut cursor = bytes.as_slice();
+        let code = read_varint(&mut cursor, "key type")?;
+        let key_type =
+            KeyType::from_code(code).ok_or_else(|| anyhow!("Unsupported key type 0x{:x}", code))?;
+
+        let valid = match key_type.key_length() {
+            Some(length) => cursor.len() == length,
+            None => !cursor.is_empty(),
+        };
+        if !valid {
+            return Err(anyhow!(
+                "{:?} keys cannot be {} bytes long",
+                key_type,
+                cursor.len()
+            ));
+        }
+
+        Ok(PublicKey {
+            key_type,
+            bytes: cursor.to_vec(),
+        })
+    }
+
+    /// Encode the key the way a `did:key` DID holds it
+    pub fn to_multibase(&self) -> String {
+        let mut bytes = Vec::new();
+        write_varint(self.key_type.code(), &mut bytes);
+        bytes.extend_from_slice(&self.bytes);
+
+        format!("z{}", encode_base58(&bytes))
+    }
+}
+
+/// A DID, split into its method and method-specific identifier:
+/// `did:{method}:{id}`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Did {
+    pub method: String,
+    pub id: String,
+}
+
+impl Did {
+    /// The `did:key` DID of a public key
+    pub fn from_public_key(key: &PublicKey) -> Did {
+        Did {
+            method: "key".into(),
+            id: key.to_multibase(),
+        }
+    }
+
+    /// The public key of a `did:key` DID, or `None` for other methods
+    pub fn public_key(&self) -> Option<PublicKey> {
+        match self.method.as_str() {
+            // The key was validated when the DID was parsed
+            "key" => PublicKey::from_multibase(&self.id).ok(),
+            _ => None,
+        }
+    }
+}
+
+fn is_id_char(char: char) -> bool {
+    char.is_ascii_alphanumeric() || matches!(char, '.' | '-' | '_')
+}
+
+/// Check a method-specific identifier: segments of ID characters and
+/// percent-encoded bytes separated by colons, where the last segment may
+/// not be empty
+fn validate_id(id: &str) -> Result<()> {
+    if id.is_empty() || id.ends_with(':') {
+        return Err(anyhow!("The identifier of a DID cannot be empty"));
+    }
+
+    let mut chars = id.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            '%' => {
+                let escape = chars.by_ref().take(2).collect::<String>();
+                if escape.len() != 2 || !escape.chars().all(|char| char.is_ascii_hexdigit()) {
+                    return Err(anyhow!("Invalid percent-encoding %{} in DID", escape));
+                }
+            }
+            ':' => (),
+            char if is_id_char(char) => (),
+            char => return Err(anyhow!("Invalid character {:?} in DID", char)),
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse and validate a DID such as `did:key:z6Mk...` or `did:web:example.com`
+impl FromStr for Did {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s
+            .strip_prefix("did:")
+            .ok_or_else(|| anyhow!("A DID must start with did:"))?;
+        let (method, id) = rest
+            .split_once(':')
+            .ok_or_else(|| anyhow!("A DID must have a method and an identifier"))?;
+
+        if method.is_empty()
+            || !method
+                .chars()
+                .all(|char| char.is_ascii_lowercase() || char.is_ascii_digit())
+        {
+            return Err(anyhow!(
+                "The method of a DID must be lowercase letters and digits, not {:?}",
+                method
+            ));
+        }
+        validate_id(id)?;
+
+        if method == "key" {
+            PublicKey::from_multibase(id)?;
+        }
+
+        Ok(Did {
+            method: method.to_owned(),
+            id: id.to_owned(),
+        })
+    }
+}
+
+impl Display for Did {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "did:{}:{}", self.method, self.id)
+    }
+}
@@ -0,0 +1,117 @@
+//! A canonical formatter for Subtext. Formatting only ever touches
+//! whitespace and line breaks: the sequence of non-blank blocks, their
+//! types and their content are left as they were.
+use anyhow::{anyhow, Result};
+
+use crate::{block::Block, primitive::Entity};
+
+/// Configures which normalizations the formatter applies
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    /// Interpret `\r\n` and `\r` as `\n`, as the spec requires of parsers,
+    /// and write only `\n`
+    pub normalize_newlines: bool,
+    /// Put exactly one space between a sigil and the block content
+    pub sigil_spacing: bool,
+    /// Remove spaces and tabs at the end of every line
+    pub trim_trailing_whitespace: bool,
+    /// The longest run of consecutive blank lines to keep, or `None` to keep
+    /// them all
+    pub max_blank_lines: Option<usize>,
+    /// End the document with a single `\n`. Trailing blank lines are
+    /// dropped either way.
+    pub final_newline: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            normalize_newlines: true,
+            sigil_spacing: true,
+            trim_trailing_whitespace: true,
+            max_blank_lines: Some(1),
+            final_newline: true,
+        }
+    }
+}
+
+/// Normalize `\r\n` and `\r` line breaks to `\n`
+pub fn normalize_newlines(input: &str) -> String {
+    input.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Format a buffer of Subtext with the given options
+pub fn format(input: &[u8], options: &FormatOptions) -> Result<String> {
+    let input =
+        std::str::from_utf8(input).map_err(|_| anyhow!("Could not interpret bytes as UTF-8"))?;
+    let input = match options.normalize_newlines {
+        true => normalize_newlines(input),
+        false => input.to_owned(),
+    };
+
+    let blocks: Vec<Block<Entity>> = crate::parse(input.as_bytes())?.collect();
+
+    Ok(format_blocks(&blocks, options))
+}
+
+/// Format already-parsed blocks with the given options
+pub fn format_blocks<E>(blocks: &[Block<E>], options: &FormatOptions) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let mut lines = Vec::<String>::new();
+    let mut blank_run = 0usize;
+
+    for block in blocks {
+        if let Block::Blank(_) = block {
+            blank_run += 1;
+            if matches!(options.max_blank_lines, Some(max) if blank_run > max) {
+                continue;
+            }
+        } else {
+            blank_run = 0;
+        }
+
+        lines.push(format_block(block, options));
+    }
+
+    // The parser dissolves a terminating newline, so a trailing blank line
+    // would not survive being formatted again
+    while matches!(lines.last(), Some(line) if line.trim().is_empty()) {
+        lines.pop();
+    }
+
+    if options.final_newline && !lines.is_empty() {
+        lines.push(String::new());
+    }
+
+    lines.join("\n")
+}
+
+fn format_block<E>(block: &Block<E>, options: &FormatOptions) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let line = match block {
+        Block::Header(_) | Block::List(_) | Block::Quote(_) if options.sigil_spacing => {
+            let sigil = match block {
+                Block::Header(_) => '#',
+                Block::List(_) => '-',
+                _ => '>',
+            };
+            let content = block.to_text_content();
+            let content = content.trim_start_matches([' ', '\t']);
+
+            match content.is_empty() {
+                true => sigil.to_string(),
+                false => format!("{} {}", sigil, content),
+            }
+        }
+        _ => block.to_string(),
+    };
+
+    match options.trim_trailing_whitespace {
+        true => line.trim_end_matches([' ', '\t']).to_owned(),
+        false => line,
+    }
+}
@@ -0,0 +1,360 @@
+//! Serialization of parsed Subtext to and from JSON. Requires the `serde`
+//! feature.
+//!
+//! [Block] and [Entity] implement `Serialize` and `Deserialize` directly,
+//! and [to_json] and [from_json] read and write a whole document in the
+//! versioned schema below. The schema only changes in ways that old readers
+//! can ignore (such as new optional fields) without a change of version.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "blocks": [
+//!     {
+//!       "type": "header",
+//!       "entities": [
+//!         { "kind": "sigil", "text": "#" },
+//!         { "kind": "empty-space", "text": " " },
+//!         { "kind": "text", "text": "Hello, " },
+//!         { "kind": "slash-link", "text": "/world" }
+//!       ],
+//!       "span": { "start": 0, "end": 15 }
+//!     },
+//!     {
+//!       "type": "blank",
+//!       "entities": [{ "kind": "empty-space", "text": "" }]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! - `type` is one of `header`, `paragraph`, `quote`, `list` or `blank`.
+//! - `kind` is one of `sigil`, `text`, `empty-space`, `slash-link`,
+//!   `hyper-link` or `wiki-link`.
+//! - Concatenating the `text` of a block's entities gives the line exactly
+//!   as it appeared in the source.
+//! - `span` is optional. When present, it is the byte range of the line in
+//!   the source, not including its line break.
+//!
+//! Deserializing checks that the entities of each block could have come
+//! from a parser: a sigil may only be the first entity of a header, quote
+//! or list, a paragraph may not start with a sigil character, a blank block
+//! holds a single empty space, links look like links, and no entity spans a
+//! line break.
+use anyhow::{anyhow, Result};
+use serde::{
+    de::Error as _,
+    ser::{SerializeSeq, SerializeStruct},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+    block::Block,
+    primitive::{peer_link_length, Entity},
+    str::SharedString,
+};
+
+/// The version of the JSON schema that [to_json] writes and [from_json]
+/// reads
+pub const VERSION: u64 = 1;
+
+/// A byte range of the source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum EntityKind {
+    Sigil,
+    Text,
+    EmptySpace,
+    SlashLink,
+    HyperLink,
+    WikiLink,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum BlockKind {
+    Header,
+    Paragraph,
+    Quote,
+    List,
+    Blank,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawEntity {
+    kind: EntityKind,
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct RawBlock {
+    #[serde(rename = "type")]
+    kind: BlockKind,
+    entities: Vec<Entity>,
+    #[serde(default)]
+    #[allow(dead_code)]
+    span: Option<Span>,
+}
+
+impl Serialize for Entity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let kind = match self {
+            Entity::Sigil(_) => EntityKind::Sigil,
+            Entity::TextSpan(_) => EntityKind::Text,
+            Entity::EmptySpace(_) => EntityKind::EmptySpace,
+            Entity::SlashLink(_) => EntityKind::SlashLink,
+            Entity::HyperLink(_) => EntityKind::HyperLink,
+            Entity::WikiLink(_) => EntityKind::WikiLink,
+        };
+
+        RawEntity {
+            kind,
+            text: self.to_string(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Check that text is plausible for an entity of the given kind
+fn validate_entity(kind: EntityKind, text: &str) -> Result<()> {
+    if text.contains('\n') {
+        return Err(anyhow!(
+            "Entity text may not contain a line break: {:?}",
+            text
+        ));
+    }
+
+    let is_valid = match kind {
+        EntityKind::Sigil => matches!(text, "#" | ">" | "-"),
+        EntityKind::Text => !text.is_empty(),
+        EntityKind::EmptySpace => text.chars().all(char::is_whitespace),
+        EntityKind::SlashLink => match text.strip_prefix('/') {
+            Some(rest) => !rest.is_empty() && !rest.contains(char::is_whitespace),
+            None => peer_link_length(text) == Some(text.len()),
+        },
+        EntityKind::HyperLink => ["http://", "https://", "ipfs://"]
+            .iter()
+            .any(|scheme| text.starts_with(scheme)),
+        EntityKind::WikiLink => text.starts_with("[["),
+    };
+
+    match is_valid {
+        true => Ok(()),
+        false => Err(anyhow!("{:?} is not a valid {:?} entity", text, kind)),
+    }
+}
+
+impl<'de> Deserialize<'de> for Entity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let RawEntity { kind, text } = RawEntity::deserialize(deserializer)?;
+        validate_entity(kind, &text).map_err(D::Error::custom)?;
+
+        let text = SharedString::from(text.as_str());
+        Ok(match kind {
+            EntityKind::Sigil => Entity::Sigil(text),
+            EntityKind::Text => Entity::TextSpan(text),
+            EntityKind::EmptySpace => Entity::EmptySpace(text),
+            EntityKind::SlashLink => Entity::SlashLink(text),
+            EntityKind::HyperLink => Entity::HyperLink(text),
+            EntityKind::WikiLink => Entity::WikiLink(text),
+        })
+    }
+}
+
+/// Serializes a block's entities as a sequence of plain entities
+struct Entities<'a, E>(Vec<&'a E>);
+
+impl<E> Serialize for Entities<'_, E>
+where
+    E: AsRef<Entity>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for entity in &self.0 {
+            seq.serialize_element(entity.as_ref())?;
+        }
+        seq.end()
+    }
+}
+
+/// Serializes a block along with its place in the source
+struct SpannedBlock<'a, E>
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    block: &'a Block<E>,
+    span: Option<Span>,
+}
+
+impl<E> Serialize for SpannedBlock<'_, E>
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (kind, entities) = match self.block {
+            Block::Header(entities) => (BlockKind::Header, entities.iter().collect()),
+            Block::Paragraph(entities) => (BlockKind::Paragraph, entities.iter().collect()),
+            Block::Quote(entities) => (BlockKind::Quote, entities.iter().collect()),
+            Block::List(entities) => (BlockKind::List, entities.iter().collect()),
+            Block::Blank(entity) => (BlockKind::Blank, vec![entity]),
+        };
+
+        let fields = if self.span.is_some() { 3 } else { 2 };
+        let mut state = serializer.serialize_struct("Block", fields)?;
+        state.serialize_field("type", &kind)?;
+        state.serialize_field("entities", &Entities(entities))?;
+        if let Some(span) = self.span {
+            state.serialize_field("span", &span)?;
+        }
+        state.end()
+    }
+}
+
+impl<E> Serialize for Block<E>
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SpannedBlock {
+            block: self,
+            span: None,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Check that entities could have been parsed as a block of the given kind
+fn validate_block(kind: BlockKind, entities: &[Entity]) -> Result<()> {
+    let sigil = match kind {
+        BlockKind::Header => Some("#"),
+        BlockKind::Quote => Some(">"),
+        BlockKind::List => Some("-"),
+        BlockKind::Paragraph | BlockKind::Blank => None,
+    };
+
+    if let Some(index) = entities
+        .iter()
+        .skip(sigil.map_or(0, |_| 1))
+        .position(|entity| matches!(entity, Entity::Sigil(_)))
+    {
+        return Err(anyhow!(
+            "A sigil may only start a block, but entity {} of a {:?} is a sigil",
+            index + sigil.map_or(0, |_| 1),
+            kind
+        ));
+    }
+
+    match (kind, sigil, entities) {
+        (BlockKind::Blank, _, [Entity::EmptySpace(_)]) => Ok(()),
+        (BlockKind::Blank, _, _) => Err(anyhow!("A blank block must hold a single empty space")),
+        (_, Some(sigil), [Entity::Sigil(text), ..]) if text.as_ref() == sigil => Ok(()),
+        (_, Some(sigil), _) => Err(anyhow!("A {:?} must start with the sigil {}", kind, sigil)),
+        (_, None, []) => Err(anyhow!("A paragraph must have at least one entity")),
+        (_, None, entities) if entities.iter().all(|e| matches!(e, Entity::EmptySpace(_))) => {
+            Err(anyhow!("A paragraph must have some content"))
+        }
+        (_, None, [first, ..]) if first.to_string().starts_with(['#', '>', '-']) => Err(anyhow!(
+            "A paragraph may not start with a sigil character: {:?}",
+            first.to_string()
+        )),
+        _ => Ok(()),
+    }
+}
+
+impl<'de, E> Deserialize<'de> for Block<E>
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let RawBlock { kind, entities, .. } = RawBlock::deserialize(deserializer)?;
+        validate_block(kind, &entities).map_err(D::Error::custom)?;
+
+        let mut entities = entities.into_iter().map(E::from);
+        Ok(match kind {
+            BlockKind::Header => Block::Header(entities.collect()),
+            BlockKind::Paragraph => Block::Paragraph(entities.collect()),
+            BlockKind::Quote => Block::Quote(entities.collect()),
+            BlockKind::List => Block::List(entities.collect()),
+            BlockKind::Blank => Block::Blank(
+                entities
+                    .next()
+                    .ok_or_else(|| D::Error::custom("A blank block must hold an entity"))?,
+            ),
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(bound(serialize = "E: From<Entity> + AsRef<Entity>"))]
+struct DocumentOut<'a, E>
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    version: u64,
+    blocks: Vec<SpannedBlock<'a, E>>,
+}
+
+#[derive(Deserialize)]
+struct DocumentVersion {
+    version: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "E: From<Entity> + AsRef<Entity>"))]
+struct DocumentIn<E>
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    blocks: Vec<Block<E>>,
+}
+
+/// Serialize blocks as a JSON document. With `spans`, each block records
+/// the byte range of its line, assuming that lines were separated by `\n`.
+pub fn to_json<E>(blocks: &[Block<E>], spans: bool) -> Result<String>
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let mut start = 0usize;
+    let blocks = blocks
+        .iter()
+        .map(|block| {
+            let end = start + block.to_bytes().len();
+            let span = Span { start, end };
+            start = end + 1;
+
+            SpannedBlock {
+                block,
+                span: spans.then_some(span),
+            }
+        })
+        .collect();
+
+    Ok(serde_json::to_string(&DocumentOut {
+        version: VERSION,
+        blocks,
+    })?)
+}
+
+/// Read blocks from a JSON document, validating them along the way
+pub fn from_json<E>(json: &str) -> Result<Vec<Block<E>>>
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let DocumentVersion { version } = serde_json::from_str(json)?;
+    if version != VERSION {
+        return Err(anyhow!(
+            "Unsupported schema version {} (expected {})",
+            version,
+            VERSION
+        ));
+    }
+
+    let DocumentIn { blocks } = serde_json::from_str(json)?;
+    Ok(blocks)
+}
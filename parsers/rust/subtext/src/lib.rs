@@ -1,19 +1,66 @@
 #[macro_use]
 extern crate log;
 
+// The lint allowances below cover the original parser's style, so that it
+// passes clippy without being rewritten
+
+pub mod anchor;
+#[allow(
+    clippy::match_like_matches_macro,
+    clippy::to_string_trait_impl,
+    clippy::manual_unwrap_or,
+    clippy::manual_unwrap_or_default
+)]
 pub mod block;
+pub mod builder;
+pub mod cid;
+pub mod convert;
+pub mod crdt;
+pub mod did;
+pub mod format;
+pub mod lint;
+#[allow(clippy::len_zero)]
 mod parse;
+pub mod petname;
+#[allow(
+    mismatched_lifetime_syntaxes,
+    clippy::needless_borrow,
+    clippy::enum_variant_names
+)]
 mod predicate;
+#[allow(
+    clippy::inherent_to_string,
+    clippy::assign_op_pattern,
+    clippy::needless_return,
+    clippy::single_match,
+    clippy::redundant_pattern_matching
+)]
 pub mod primitive;
+pub mod render;
+#[allow(clippy::match_like_matches_macro)]
 mod sequence;
+pub mod sexpr;
+pub mod site;
 mod slashlink;
+mod slug_path;
 pub mod str;
+pub mod transclude;
+#[allow(
+    clippy::collapsible_str_replace,
+    clippy::len_zero,
+    clippy::match_like_matches_macro
+)]
 pub mod util;
 pub use slashlink::*;
+pub use slug_path::*;
 
 pub use parse::parse;
 
+#[cfg(feature = "serde")]
+pub mod json;
+
 #[cfg(feature = "stream")]
+#[allow(clippy::get_first)]
 mod stream;
 #[cfg(feature = "stream")]
 pub use stream::*;
@@ -0,0 +1,410 @@
+//! A linter that walks parsed blocks and reports common Subtext mistakes.
+//! Each [Rule] can be disabled or given a different [Severity], and rules
+//! offer a [Fix] where it can be applied without guessing at intent.
+use std::{collections::HashMap, fmt::Display, ops::Range};
+
+use anyhow::{anyhow, Result};
+
+use crate::{block::Block, primitive::Entity};
+
+/// Sigils the spec reserves for future use, other than the whitespace ones
+const RESERVED_SIGILS: &[char] = &['$', '@', '!', '%', '~', '|', ':', '*', '+', '=', '\\', 'λ'];
+
+/// Punctuation that is likely meant to end a sentence rather than a URL
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?', ')'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    /// A line starts with a sigil the spec reserves for future use
+    ReservedSigil,
+    /// A slashlink contains characters outside of `[a-zA-Z0-9-_/]`
+    InvalidSlashlink,
+    /// A `[[` is never closed by `]]`
+    UnterminatedWikiLink,
+    /// A bare URL ends with punctuation that was probably not meant to be
+    /// part of it
+    UrlTrailingPunctuation,
+    /// A line is terminated by `\r\n` or `\r` instead of `\n`
+    CarriageReturn,
+    /// A paragraph starts with a tab or a run of spaces followed by a sigil,
+    /// as an indented list item might, so the sigil is not recognized. A
+    /// single space is how a sigil is deliberately escaped, so it is not
+    /// flagged, and no fix is offered.
+    LeadingWhitespaceBeforeSigil,
+    /// A blank line contains tabs
+    TabOnlyLine,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 7] = [
+        Rule::ReservedSigil,
+        Rule::InvalidSlashlink,
+        Rule::UnterminatedWikiLink,
+        Rule::UrlTrailingPunctuation,
+        Rule::CarriageReturn,
+        Rule::LeadingWhitespaceBeforeSigil,
+        Rule::TabOnlyLine,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::ReservedSigil => "reserved-sigil",
+            Rule::InvalidSlashlink => "invalid-slashlink",
+            Rule::UnterminatedWikiLink => "unterminated-wikilink",
+            Rule::UrlTrailingPunctuation => "url-trailing-punctuation",
+            Rule::CarriageReturn => "carriage-return",
+            Rule::LeadingWhitespaceBeforeSigil => "leading-whitespace-before-sigil",
+            Rule::TabOnlyLine => "tab-only-line",
+        }
+    }
+
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            Rule::InvalidSlashlink | Rule::UnterminatedWikiLink => Severity::Error,
+            Rule::TabOnlyLine => Severity::Info,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Which rules are enabled, and at what severity
+#[derive(Debug, Clone)]
+pub struct LintConfig {
+    severities: HashMap<Rule, Option<Severity>>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            severities: Rule::ALL
+                .iter()
+                .map(|rule| (*rule, Some(rule.default_severity())))
+                .collect(),
+        }
+    }
+}
+
+impl LintConfig {
+    pub fn disable(mut self, rule: Rule) -> Self {
+        self.severities.insert(rule, None);
+        self
+    }
+
+    pub fn severity(mut self, rule: Rule, severity: Severity) -> Self {
+        self.severities.insert(rule, Some(severity));
+        self
+    }
+
+    /// The severity of a rule, or `None` if it is disabled
+    pub fn get(&self, rule: Rule) -> Option<Severity> {
+        self.severities.get(&rule).copied().flatten()
+    }
+}
+
+/// A 1-based line and column (counted in characters) in the source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Replace a byte range of the source with new text
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fix {
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub rule: Rule,
+    pub severity: Severity,
+    pub message: String,
+    pub location: Location,
+    /// The byte range of the source the diagnostic refers to
+    pub range: Range<usize>,
+    pub fix: Option<Fix>,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:?} [{}]: {}",
+            self.location, self.severity, self.rule, self.message
+        )
+    }
+}
+
+/// Collects diagnostics for one line at a time
+struct LineContext<'a> {
+    config: &'a LintConfig,
+    line: &'a str,
+    line_number: usize,
+    offset: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl LineContext<'_> {
+    /// Report a problem with the byte range `start..end` of the current line
+    fn report(
+        &mut self,
+        rule: Rule,
+        start: usize,
+        end: usize,
+        message: String,
+        replacement: Option<String>,
+    ) {
+        let severity = match self.config.get(rule) {
+            Some(severity) => severity,
+            None => return,
+        };
+        let range = self.offset + start..self.offset + end;
+
+        self.diagnostics.push(Diagnostic {
+            rule,
+            severity,
+            message,
+            location: Location {
+                line: self.line_number,
+                column: self.line[..start].chars().count() + 1,
+            },
+            fix: replacement.map(|replacement| Fix {
+                range: range.clone(),
+                replacement,
+            }),
+            range,
+        });
+    }
+}
+
+/// Lint a buffer of Subtext, returning diagnostics in source order
+pub fn lint(input: &[u8], config: &LintConfig) -> Result<Vec<Diagnostic>> {
+    let source =
+        std::str::from_utf8(input).map_err(|_| anyhow!("Could not interpret bytes as UTF-8"))?;
+    let blocks: Vec<Block<Entity>> = crate::parse(input)?.collect();
+    let mut diagnostics = Vec::new();
+    let mut offset = 0usize;
+
+    for (index, block) in blocks.iter().enumerate() {
+        let line = block.to_string();
+        let mut context = LineContext {
+            config,
+            line: &line,
+            line_number: index + 1,
+            offset,
+            diagnostics: Vec::new(),
+        };
+
+        let has_line_feed = source
+            .get(offset + line.len()..)
+            .is_some_and(|rest| rest.starts_with('\n'));
+
+        lint_line(&mut context, block, has_line_feed);
+        diagnostics.append(&mut context.diagnostics);
+        offset += line.len() + 1;
+    }
+
+    Ok(diagnostics)
+}
+
+fn lint_line(context: &mut LineContext, block: &Block<Entity>, has_line_feed: bool) {
+    let line = context.line;
+
+    for (index, _) in line.match_indices('\r') {
+        let is_crlf = index + 1 == line.len() && has_line_feed;
+        context.report(
+            Rule::CarriageReturn,
+            index,
+            index + 1,
+            match is_crlf {
+                true => "Line ends with \\r\\n instead of \\n".into(),
+                false => "Line is broken with \\r instead of \\n".into(),
+            },
+            Some(match is_crlf {
+                true => String::new(),
+                false => "\n".into(),
+            }),
+        );
+    }
+
+    match block {
+        Block::Blank(_) => {
+            if line.contains('\t') {
+                context.report(
+                    Rule::TabOnlyLine,
+                    0,
+                    line.len(),
+                    "Blank line contains tabs".into(),
+                    Some(String::new()),
+                );
+            }
+            return;
+        }
+        Block::Paragraph(entities) => lint_line_start(context, entities.first()),
+        // `---` is read as a list item whose content starts with `--`
+        Block::List(_) if line.starts_with("---") => {
+            context.report(
+                Rule::ReservedSigil,
+                0,
+                3,
+                "The sigil --- is reserved for future use".into(),
+                None,
+            );
+        }
+        _ => (),
+    }
+
+    let entities = match block {
+        Block::Header(entities)
+        | Block::Paragraph(entities)
+        | Block::Quote(entities)
+        | Block::List(entities) => entities,
+        Block::Blank(_) => return,
+    };
+
+    let mut start = 0usize;
+    for entity in entities {
+        let text = entity.to_string();
+        let end = start + text.len();
+        let trimmed = text.trim_end_matches('\r');
+
+        match entity {
+            Entity::SlashLink(_) => {
+                let path = trimmed.split('#').next().unwrap_or_default();
+                // The peer of a peer-qualified link was checked by the parser
+                let slug = match path.strip_prefix('@') {
+                    Some(link) => link.split_once('/').map_or("", |(_, slug)| slug),
+                    None => path.get(1..).unwrap_or_default(),
+                };
+                if let Some(invalid) = slug
+                    .chars()
+                    .find(|c| !matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '/'))
+                {
+                    context.report(
+                        Rule::InvalidSlashlink,
+                        start,
+                        start + trimmed.len(),
+                        format!("Slashlink {} contains the character {:?}", trimmed, invalid),
+                        None,
+                    );
+                }
+            }
+            Entity::WikiLink(_) if !trimmed.ends_with("]]") => {
+                context.report(
+                    Rule::UnterminatedWikiLink,
+                    start,
+                    start + trimmed.len(),
+                    "Wikilink is missing its closing ]]".into(),
+                    None,
+                );
+            }
+            Entity::HyperLink(_) if trimmed.ends_with(TRAILING_PUNCTUATION) => {
+                context.report(
+                    Rule::UrlTrailingPunctuation,
+                    start,
+                    start + trimmed.len(),
+                    format!(
+                        "URL {} ends with punctuation that will be treated as part of it",
+                        trimmed
+                    ),
+                    None,
+                );
+            }
+            _ => (),
+        }
+
+        start = end;
+    }
+}
+
+/// Checks that only apply to the beginning of a paragraph
+fn lint_line_start(context: &mut LineContext, first: Option<&Entity>) {
+    let line = context.line;
+    let content = line.trim_start_matches([' ', '\t']);
+    let indent = line.len() - content.len();
+
+    if line.starts_with("  ") || line.starts_with('\t') {
+        context.report(
+            Rule::ReservedSigil,
+            0,
+            indent,
+            "Leading tabs and runs of spaces are reserved for future use".into(),
+            None,
+        );
+    } else if let Some(sigil) = line
+        .chars()
+        .next()
+        .filter(|c| RESERVED_SIGILS.contains(c))
+        // A peer link (`@cdata/foo`) starts with the reserved `@`
+        .filter(|_| !matches!(first, Some(Entity::SlashLink(_))))
+    {
+        context.report(
+            Rule::ReservedSigil,
+            0,
+            sigil.len_utf8(),
+            format!("The sigil {} is reserved for future use", sigil),
+            None,
+        );
+    }
+
+    // A single space is the conventional escape for a sigil, as written by
+    // the builder and the importers
+    let escaped = line.starts_with(' ') && indent == 1;
+
+    if indent > 0 && !escaped && content.starts_with(['#', '-', '>']) {
+        context.report(
+            Rule::LeadingWhitespaceBeforeSigil,
+            0,
+            indent,
+            "Leading whitespace prevents the sigil from being recognized".into(),
+            // Removing the whitespace would change the type of the block
+            None,
+        );
+    }
+}
+
+/// Apply the fixes offered by a set of diagnostics. Fixes that overlap one
+/// that has already been applied are skipped.
+pub fn apply_fixes(input: &str, diagnostics: &[Diagnostic]) -> String {
+    let mut fixes = diagnostics
+        .iter()
+        .filter_map(|diagnostic| diagnostic.fix.as_ref())
+        .collect::<Vec<&Fix>>();
+    fixes.sort_by_key(|fix| fix.range.start);
+
+    let mut output = String::new();
+    let mut cursor = 0usize;
+
+    for fix in fixes {
+        if fix.range.start < cursor || fix.range.end > input.len() {
+            continue;
+        }
+
+        output.push_str(&input[cursor..fix.range.start]);
+        output.push_str(&fix.replacement);
+        cursor = fix.range.end;
+    }
+
+    output.push_str(&input[cursor..]);
+    output
+}
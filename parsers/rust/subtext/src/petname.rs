@@ -0,0 +1,281 @@
+//! Resolve petnames, the local names given to peers, to DIDs.
+//!
+//! A peer link such as `@jordan.gordon.morgon/foo` names its peer through a
+//! chain of address books. Like a domain name it is read from the right:
+//! `morgon` is looked up in the local address book, `gordon` in morgon's
+//! address book, and `jordan` in gordon's. The last DID found is the peer
+//! the link refers to.
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::{Peer, Slashlink};
+
+/// Maps petnames to DIDs, both for the local peer and for the peers it
+/// knows. Any `Fn(Option<&str>, &str) -> Result<Option<String>>` closure is
+/// an address book.
+pub trait AddressBook {
+    /// The DID that a peer's address book gives a petname, where `peer` is
+    /// the DID of that peer or `None` for the local address book
+    fn lookup(&self, peer: Option<&str>, petname: &str) -> Result<Option<String>>;
+}
+
+impl<F> AddressBook for F
+where
+    F: Fn(Option<&str>, &str) -> Result<Option<String>>,
+{
+    fn lookup(&self, peer: Option<&str>, petname: &str) -> Result<Option<String>> {
+        self(peer, petname)
+    }
+}
+
+/// An address book held in memory. Petnames are case-insensitive.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryAddressBook {
+    /// Petnames and their DIDs, by the DID of the peer they belong to
+    books: BTreeMap<Option<String>, BTreeMap<String, String>>,
+}
+
+impl MemoryAddressBook {
+    /// Give a DID a petname in a peer's address book, or in the local one
+    /// when `peer` is `None`
+    pub fn insert(&mut self, peer: Option<&str>, petname: &str, did: &str) {
+        self.books
+            .entry(peer.map(str::to_owned))
+            .or_default()
+            .insert(petname.to_lowercase(), did.to_owned());
+    }
+
+    /// Remove a petname, returning the DID it was given
+    pub fn remove(&mut self, peer: Option<&str>, petname: &str) -> Option<String> {
+        self.books
+            .get_mut(&peer.map(str::to_owned))?
+            .remove(&petname.to_lowercase())
+    }
+}
+
+impl AddressBook for MemoryAddressBook {
+    fn lookup(&self, peer: Option<&str>, petname: &str) -> Result<Option<String>> {
+        Ok(self
+            .books
+            .get(&peer.map(str::to_owned))
+            .and_then(|book| book.get(&petname.to_lowercase()))
+            .cloned())
+    }
+}
+
+fn is_valid_petname(petname: &str) -> bool {
+    !petname.is_empty()
+        && petname
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_')
+}
+
+/// An address book stored in a text file. Each line gives a petname and a
+/// DID, separated by whitespace. The lines at the top are the local address
+/// book, and a `[did]` line starts the address book of that peer:
+///
+/// ```text
+/// # Comments start with a hash
+/// morgon did:key:z6MkMorgon
+///
+/// [did:key:z6MkMorgon]
+/// gordon did:key:z6MkGordon
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileAddressBook {
+    path: PathBuf,
+    book: MemoryAddressBook,
+}
+
+impl FileAddressBook {
+    /// Read an address book, which is empty if the file does not exist yet
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let book = match path.exists() {
+            true => {
+                let text = fs::read_to_string(&path)
+                    .with_context(|| format!("Could not read {}", path.display()))?;
+                FileAddressBook::parse(&text)
+                    .with_context(|| format!("Could not parse {}", path.display()))?
+            }
+            false => MemoryAddressBook::default(),
+        };
+
+        Ok(FileAddressBook { path, book })
+    }
+
+    fn parse(text: &str) -> Result<MemoryAddressBook> {
+        let mut book = MemoryAddressBook::default();
+        let mut peer = None;
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(section) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                let did = section.trim();
+                if !did.starts_with("did:") {
+                    return Err(anyhow!("Line {}: {} is not a DID", index + 1, did));
+                }
+                peer = Some(did.to_owned());
+                continue;
+            }
+
+            match line.split_whitespace().collect::<Vec<&str>>()[..] {
+                [petname, did] if is_valid_petname(petname) && did.starts_with("did:") => {
+                    book.insert(peer.as_deref(), petname, did)
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "Line {}: expected a petname and a DID but found {:?}",
+                        index + 1,
+                        line
+                    ))
+                }
+            }
+        }
+
+        Ok(book)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Give a DID a petname; see [MemoryAddressBook::insert]. The change is
+    /// kept in memory until [FileAddressBook::save] is called.
+    pub fn insert(&mut self, peer: Option<&str>, petname: &str, did: &str) -> Result<()> {
+        if !is_valid_petname(petname) {
+            return Err(anyhow!("Invalid petname {:?}", petname));
+        }
+        if !did.starts_with("did:") || did.contains(char::is_whitespace) {
+            return Err(anyhow!("Invalid DID {:?}", did));
+        }
+
+        self.book.insert(peer, petname, did);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, peer: Option<&str>, petname: &str) -> Option<String> {
+        self.book.remove(peer, petname)
+    }
+
+    /// Write the address book back to its file
+    pub fn save(&self) -> Result<()> {
+        let sections = self
+            .book
+            .books
+            .iter()
+            .filter(|(_, petnames)| !petnames.is_empty())
+            .map(|(peer, petnames)| {
+                let header = peer.iter().map(|did| format!("[{}]\n", did));
+                let lines = petnames
+                    .iter()
+                    .map(|(petname, did)| format!("{} {}\n", petname, did));
+                header.chain(lines).collect::<String>()
+            })
+            .collect::<Vec<String>>();
+
+        fs::write(&self.path, sections.join("\n"))
+            .with_context(|| format!("Could not write {}", self.path.display()))
+    }
+}
+
+impl AddressBook for FileAddressBook {
+    fn lookup(&self, peer: Option<&str>, petname: &str) -> Result<Option<String>> {
+        self.book.lookup(peer, petname)
+    }
+}
+
+/// Resolves peers and slashlinks to DIDs through an address book
+pub struct PetnameResolver<B: AddressBook> {
+    book: B,
+    did: String,
+    max_depth: usize,
+}
+
+impl<B: AddressBook> PetnameResolver<B> {
+    /// A resolver for the local peer with the given DID, which slashlinks
+    /// without a peer refer to
+    pub fn new(book: B, did: &str) -> Self {
+        PetnameResolver {
+            book,
+            did: did.to_owned(),
+            max_depth: 8,
+        }
+    }
+
+    /// The longest chain of petnames to follow; the default is 8
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// The DID of a peer. A chain of petnames that passes through the same
+    /// peer twice is an error.
+    pub fn resolve_peer(&self, peer: &Peer) -> Result<String> {
+        let names = match peer {
+            Peer::Name(names) => names,
+            Peer::Did(did) => return Ok(did.clone()),
+            Peer::None => return Ok(self.did.clone()),
+        };
+
+        if names.len() > self.max_depth {
+            return Err(anyhow!(
+                "@{} is a chain of more than {} petnames",
+                names.join("."),
+                self.max_depth
+            ));
+        }
+
+        let mut visited = HashSet::new();
+        let mut current: Option<String> = None;
+
+        for name in names.iter().rev() {
+            let name = name.to_lowercase();
+            let did = self
+                .book
+                .lookup(current.as_deref(), &name)?
+                .ok_or_else(|| {
+                    anyhow!(
+                        "No peer named {} in the address book of {}",
+                        name,
+                        current.as_deref().unwrap_or("the local peer")
+                    )
+                })?;
+
+            if !visited.insert(did.clone()) {
+                return Err(anyhow!(
+                    "@{} leads back to {} at {}",
+                    names.join("."),
+                    did,
+                    name
+                ));
+            }
+            current = Some(did);
+        }
+
+        current.ok_or_else(|| anyhow!("A peer name needs at least one petname"))
+    }
+
+    /// The DID of a slashlink's peer and its normalized slug, if it has one
+    pub fn resolve(&self, slashlink: &Slashlink) -> Result<(String, Option<String>)> {
+        let slashlink = slashlink.normalize();
+        let did = self
+            .resolve_peer(&slashlink.peer)
+            .with_context(|| format!("Could not resolve {}", slashlink))?;
+
+        Ok((did, slashlink.slug))
+    }
+}
@@ -2,6 +2,7 @@ use crate::sequence::Sequence;
 
 pub const CARRIAGE_RETURN_NEW_LINE_SEQUENCE: &[char] = &['\r', '\n'];
 pub const SLASH_LINK_SEQUENCE: &[char] = &['/'];
+pub const PEER_LINK_SEQUENCE: &[char] = &['@'];
 pub const WIKI_LINK_OPEN_SEQUENCE: &[char] = &['[', '['];
 pub const WIKI_LINK_CLOSE_SEQUENCE: &[char] = &[']', ']'];
 pub const HTTPS_LINK_SEQUENCE: &[char] = &['h', 't', 't', 'p', 's', ':', '/', '/'];
@@ -107,9 +108,16 @@ pub fn slash_link_predicate() -> impl FnMut(&char) -> Option<usize> {
     sequence_to_predicate(Sequence::new(SLASH_LINK_SEQUENCE, Some(' ')))
 }
 
+pub fn peer_link_predicate() -> impl FnMut(&char) -> Option<usize> {
+    sequence_to_predicate(Sequence::new(PEER_LINK_SEQUENCE, Some(' ')))
+}
+
 #[derive(Debug)]
 pub enum ParseLinkAs {
     SlashLink,
+    /// A slashlink that starts with a peer (`@cdata/foo`), or a bare peer
+    /// mention (`@cdata`)
+    PeerLink,
     HyperLink,
     WikiLink,
 }
@@ -118,6 +126,7 @@ pub fn link_predicate() -> impl FnMut(&char) -> Option<(usize, ParseLinkAs)> {
     let mut is_hyper_link = hyper_link_predicate();
     let mut is_slash_link = slash_link_predicate();
     let mut is_wiki_link_open = wiki_link_open_predicate();
+    let mut is_peer_link = peer_link_predicate();
 
     move |token: &char| {
         if let Some(steps) = is_hyper_link(token) {
@@ -132,6 +141,10 @@ pub fn link_predicate() -> impl FnMut(&char) -> Option<(usize, ParseLinkAs)> {
             return Some((steps, ParseLinkAs::WikiLink));
         }
 
+        if let Some(steps) = is_peer_link(token) {
+            return Some((steps, ParseLinkAs::PeerLink));
+        }
+
         None
     }
 }
@@ -5,6 +5,7 @@ use crate::{
     predicate::{
         link_predicate, white_space_predicate, wiki_link_delimiter_predicate, ParseLinkAs,
     },
+    slashlink::Slashlink,
     util::cut,
 };
 
@@ -103,6 +104,92 @@ pub fn parse_slash_link(input: SharedString) -> Result<(Entity, usize), Subtendr
     }
 }
 
+/// The length of the peer-qualified slashlink (`@cdata/foo`) or bare peer
+/// mention (`@cdata`) at the start of some text, if there is one. Peer
+/// names are dot-separated chains of letters, digits, dashes and
+/// underscores, and DIDs are `did:{method}:{id}`; the slug and fragment
+/// follow the same rules as in a slashlink. Trailing punctuation is left
+/// out, and text that continues with another `@` (as in an address like
+/// `@ben@example.com`) is not a link.
+pub fn peer_link_length(text: &str) -> Option<usize> {
+    let rest = text.strip_prefix('@')?;
+    let bytes = rest.as_bytes();
+    let is_word = |byte: &u8| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_');
+    let count = |from: usize, matches: &dyn Fn(&u8) -> bool| {
+        bytes[from..]
+            .iter()
+            .take_while(|byte| matches(byte))
+            .count()
+    };
+
+    let mut end = match rest.strip_prefix("did:") {
+        Some(did) => {
+            let method = count(4, &|byte| {
+                byte.is_ascii_lowercase() || byte.is_ascii_digit()
+            });
+            if method == 0 || did.as_bytes().get(method) != Some(&b':') {
+                return None;
+            }
+            let start = 5 + method;
+            let mut end = start
+                + count(start, &|byte| {
+                    is_word(byte) || matches!(byte, b'.' | b':' | b'%')
+                });
+            while end > start && matches!(bytes[end - 1], b'.' | b':') {
+                end -= 1;
+            }
+            (end > start).then_some(end)?
+        }
+        None => {
+            let mut end = count(0, &|byte| is_word(byte) || *byte == b'.');
+            while end > 0 && bytes[end - 1] == b'.' {
+                end -= 1;
+            }
+            if end == 0 || rest[..end].split('.').any(str::is_empty) {
+                return None;
+            }
+            end
+        }
+    };
+
+    if bytes.get(end) == Some(&b'/') {
+        let mut slug_end = end + 1 + count(end + 1, &|byte| is_word(byte) || *byte == b'/');
+        while slug_end > end + 1 && bytes[slug_end - 1] == b'/' {
+            slug_end -= 1;
+        }
+
+        if slug_end > end + 1 {
+            end = slug_end;
+
+            if bytes.get(end) == Some(&b'#') {
+                let fragment = count(end + 1, &is_word);
+                if fragment > 0 {
+                    end += 1 + fragment;
+                }
+            }
+        }
+    }
+
+    if bytes.get(end) == Some(&b'@') {
+        return None;
+    }
+
+    let length = end + 1;
+    text[..length].parse::<Slashlink>().ok()?;
+
+    Some(length)
+}
+
+/// Parse the peer link at the start of the input; see [peer_link_length]
+pub fn parse_peer_link(input: SharedString) -> Result<(Entity, usize), SubtendrilError> {
+    let length = peer_link_length(&input).unwrap_or(1);
+
+    Ok((
+        Entity::SlashLink(input.try_subtendril(0, length as u32)?),
+        length,
+    ))
+}
+
 pub fn parse_hyper_link(input: SharedString) -> Result<(Entity, usize), SubtendrilError> {
     match parse_until(input, white_space_predicate()) {
         Ok((value, steps)) => Ok((Entity::HyperLink(value), steps)),
@@ -167,7 +254,13 @@ where
         // Check if we met the link predicate criteria; if we did, make an
         // entity out of the text span we have seen so far and then parse
         // the link
-        if let Some((match_length, parse_as)) = is_link(&token) {
+        // An `@` only starts a link when a valid peer follows it
+        let link = match is_link(&token) {
+            Some((_, ParseLinkAs::PeerLink)) if peer_link_length(&input[index..]).is_none() => None,
+            link => link,
+        };
+
+        if let Some((match_length, parse_as)) = link {
             end = index - (match_length - 1);
 
             if end > start {
@@ -184,6 +277,7 @@ where
             let (link_entity, steps) = match parse_as {
                 ParseLinkAs::HyperLink => parse_hyper_link(link_input)?,
                 ParseLinkAs::SlashLink => parse_slash_link(link_input)?,
+                ParseLinkAs::PeerLink => parse_peer_link(link_input)?,
                 ParseLinkAs::WikiLink => parse_wiki_link(link_input)?,
             };
 
@@ -198,6 +292,10 @@ where
                 }
                 Some((_, any)) => {
                     is_link(&any);
+                    // Keep a single character that ends the input after a link
+                    if iter.peek().is_none() {
+                        end = start + any.len_utf8();
+                    }
                     continue 'parse;
                 }
                 _ => {
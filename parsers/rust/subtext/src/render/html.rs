@@ -0,0 +1,164 @@
+//! Render Subtext blocks as semantic HTML.
+use std::collections::HashMap;
+
+use crate::{
+    block::{group_blocks, Block},
+    primitive::Entity,
+    util::to_slug,
+};
+
+use super::{wiki_link_label, LinkResolver, LinkTarget};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HtmlOptions {
+    /// Give each header an `id` derived from its text, so that it can be
+    /// linked to
+    pub header_ids: bool,
+}
+
+/// Escape text for use in HTML content or in a quoted attribute value
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character),
+        }
+    }
+
+    escaped
+}
+
+/// Render blocks as HTML with the default options
+pub fn render_html<E>(blocks: &[Block<E>], resolver: &impl LinkResolver) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    render_html_with(blocks, resolver, &HtmlOptions::default())
+}
+
+/// Render blocks as HTML. Headers become `<h1>`, paragraphs `<p>`,
+/// contiguous list items a single `<ul>` and contiguous quotes a single
+/// `<blockquote>`. Blank blocks are skipped.
+pub fn render_html_with<E>(
+    blocks: &[Block<E>],
+    resolver: &impl LinkResolver,
+    options: &HtmlOptions,
+) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let mut ids = HashMap::<String, usize>::new();
+    let mut elements = Vec::<String>::new();
+
+    for group in group_blocks(blocks) {
+        match group.first() {
+            Some(Block::List(_)) => elements.push(format!(
+                "<ul>\n{}\n</ul>",
+                wrap_each(group, "li", resolver).join("\n")
+            )),
+            Some(Block::Quote(_)) => elements.push(format!(
+                "<blockquote>\n{}\n</blockquote>",
+                wrap_each(group, "p", resolver).join("\n")
+            )),
+            Some(Block::Paragraph(_)) => elements.append(&mut wrap_each(group, "p", resolver)),
+            Some(Block::Header(_)) => {
+                for block in group {
+                    let content = render_inline(block, resolver);
+
+                    elements.push(match options.header_ids {
+                        true => format!(
+                            "<h1 id=\"{}\">{}</h1>",
+                            escape_html(&unique_id(block, &mut ids)),
+                            content
+                        ),
+                        false => format!("<h1>{}</h1>", content),
+                    });
+                }
+            }
+            Some(Block::Blank(_)) | None => (),
+        }
+    }
+
+    elements.join("\n")
+}
+
+fn wrap_each<E>(blocks: &[Block<E>], tag: &str, resolver: &impl LinkResolver) -> Vec<String>
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    blocks
+        .iter()
+        .map(|block| format!("<{}>{}</{}>", tag, render_inline(block, resolver), tag))
+        .collect()
+}
+
+/// Derive an ID for a header from its text, adding a numeric suffix if an
+/// earlier header already has the same ID. `ids` holds every ID handed out
+/// so far, along with the last suffix tried for it.
+pub(crate) fn unique_id<E>(block: &Block<E>, ids: &mut HashMap<String, usize>) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let slug = match to_slug(&block.to_text_content()) {
+        Ok(slug) if !slug.is_empty() => slug.replace('/', "-"),
+        _ => "section".into(),
+    };
+
+    // A suffixed ID may already belong to a header whose own text ends in
+    // that number, so keep counting until an unused ID turns up
+    let mut count = ids.get(&slug).copied().unwrap_or(0);
+    let id = loop {
+        count += 1;
+        let id = match count {
+            1 => slug.clone(),
+            count => format!("{}-{}", slug, count),
+        };
+        if !ids.contains_key(&id) {
+            break id;
+        }
+    };
+
+    ids.insert(slug, count);
+    ids.entry(id.clone()).or_insert(1);
+    id
+}
+
+fn link(url: Option<String>, text: &str) -> String {
+    match url {
+        Some(url) => format!(
+            "<a href=\"{}\">{}</a>",
+            escape_html(&url),
+            escape_html(text)
+        ),
+        None => escape_html(text),
+    }
+}
+
+/// Render the content entities of a block as inline HTML
+pub fn render_inline<E>(block: &Block<E>, resolver: &impl LinkResolver) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    block
+        .to_content_entities()
+        .iter()
+        .map(|entity| match entity.as_ref() {
+            Entity::SlashLink(text) => link(
+                resolver.resolve(&LinkTarget::SlashLink(text.as_ref())),
+                text.as_ref(),
+            ),
+            Entity::WikiLink(text) => {
+                let label = wiki_link_label(text.as_ref());
+                link(resolver.resolve(&LinkTarget::WikiLink(label)), label)
+            }
+            Entity::HyperLink(text) => link(Some(text.to_string()), text.as_ref()),
+            entity => escape_html(&entity.to_string()),
+        })
+        .collect()
+}
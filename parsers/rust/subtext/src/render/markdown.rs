@@ -0,0 +1,116 @@
+//! Render Subtext blocks as CommonMark.
+use crate::{
+    block::{group_blocks, Block},
+    primitive::Entity,
+};
+
+use super::{wiki_link_label, LinkResolver, LinkTarget};
+
+/// Characters that may start Markdown syntax anywhere in a line
+const INLINE_SYNTAX: &[char] = &['\\', '`', '*', '_', '[', ']', '<', '&', '!'];
+
+/// Characters that start a Markdown block when they lead a line
+const BLOCK_SYNTAX: &[char] = &['#', '-', '+', '>', '=', '~', '|'];
+
+/// Escape text so that it renders literally in Markdown
+pub fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for character in text.chars() {
+        if INLINE_SYNTAX.contains(&character) {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+
+    escaped
+}
+
+/// Escape characters at the start of a line that Markdown would otherwise
+/// read as the beginning of a heading, list, quote or similar
+pub(crate) fn escape_line_start(line: String) -> String {
+    if line.starts_with(BLOCK_SYNTAX) {
+        return format!("\\{}", line);
+    }
+
+    // An ordered list item: digits followed by `.` or `)`
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 && line[digits..].starts_with(['.', ')']) {
+        return format!("{}\\{}", &line[..digits], &line[digits..]);
+    }
+
+    line
+}
+
+fn link(url: Option<String>, text: &str) -> String {
+    match url {
+        Some(url) if url.contains([' ', '(', ')', '<', '>']) => {
+            format!(
+                "[{}](<{}>)",
+                escape_markdown(text),
+                url.replace(['<', '>'], "")
+            )
+        }
+        Some(url) => format!("[{}]({})", escape_markdown(text), url),
+        None => escape_markdown(text),
+    }
+}
+
+/// Render the content entities of a block as inline Markdown
+pub fn render_inline<E>(block: &Block<E>, resolver: &impl LinkResolver) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let line = block
+        .to_content_entities()
+        .iter()
+        .map(|entity| match entity.as_ref() {
+            Entity::SlashLink(text) => link(
+                resolver.resolve(&LinkTarget::SlashLink(text.as_ref())),
+                text.as_ref(),
+            ),
+            Entity::WikiLink(text) => {
+                let label = wiki_link_label(text.as_ref());
+                link(resolver.resolve(&LinkTarget::WikiLink(label)), label)
+            }
+            Entity::HyperLink(text) => format!("<{}>", text.as_ref()),
+            entity => escape_markdown(&entity.to_string()),
+        })
+        .collect::<String>();
+
+    escape_line_start(line)
+}
+
+/// Render blocks as CommonMark. Every paragraph becomes its own Markdown
+/// paragraph (Markdown would otherwise merge adjacent lines), contiguous
+/// list items and quotes are kept together, and blank blocks are dropped.
+pub fn render_markdown<E>(blocks: &[Block<E>], resolver: &impl LinkResolver) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let mut sections = Vec::<String>::new();
+
+    for group in group_blocks(blocks) {
+        let lines = group.iter().map(|block| render_inline(block, resolver));
+
+        match group.first() {
+            Some(Block::Header(_)) => sections.extend(lines.map(|line| format!("# {}", line))),
+            Some(Block::Paragraph(_)) => sections.extend(lines),
+            Some(Block::List(_)) => sections.push(
+                lines
+                    .map(|line| format!("- {}", line))
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            ),
+            Some(Block::Quote(_)) => sections.push(
+                lines
+                    .map(|line| format!("> {}", line))
+                    .collect::<Vec<String>>()
+                    .join("\n>\n"),
+            ),
+            Some(Block::Blank(_)) | None => (),
+        }
+    }
+
+    sections.join("\n\n")
+}
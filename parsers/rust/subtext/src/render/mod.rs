@@ -0,0 +1,45 @@
+//! Renderers that turn parsed Subtext blocks into other formats.
+pub mod html;
+pub mod markdown;
+pub mod plain;
+pub mod template;
+pub mod terminal;
+
+pub use html::{render_html, render_html_with, HtmlOptions};
+pub use markdown::render_markdown;
+pub use plain::{render_plain, strip, PlainOptions};
+pub use template::{render_template, Template, Templates};
+pub use terminal::{render_terminal, ColorMode, TerminalOptions};
+
+/// The target of a link whose URL depends on where the document is being
+/// published
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkTarget<'a> {
+    /// A slashlink as written, including its leading `/`, or its leading
+    /// `@` when it names a peer (`@cdata/foo`)
+    SlashLink(&'a str),
+    /// The label of a wikilink, without its brackets
+    WikiLink(&'a str),
+}
+
+/// Resolves slashlinks and wikilinks to URLs. Returning `None` renders the
+/// link as plain text. Any `Fn(&LinkTarget) -> Option<String>` closure is a
+/// resolver.
+pub trait LinkResolver {
+    fn resolve(&self, target: &LinkTarget) -> Option<String>;
+}
+
+impl<F> LinkResolver for F
+where
+    F: Fn(&LinkTarget) -> Option<String>,
+{
+    fn resolve(&self, target: &LinkTarget) -> Option<String> {
+        self(target)
+    }
+}
+
+/// Strip the brackets from a wikilink, tolerating a missing `]]`
+pub(crate) fn wiki_link_label(text: &str) -> &str {
+    let text = text.trim_start_matches("[[");
+    text.strip_suffix("]]").unwrap_or(text)
+}
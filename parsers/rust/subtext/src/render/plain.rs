@@ -0,0 +1,156 @@
+//! Render Subtext blocks as plain text, for search snippets, notifications
+//! and text-to-speech. Block content is written without sigils, one line per
+//! block, so block types are lost.
+use crate::{block::Block, primitive::Entity};
+
+use super::wiki_link_label;
+
+/// How links are written in plain text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkText {
+    /// Keep every link exactly as written
+    Raw,
+    /// Write slashlinks and wikilinks as words: `/natural-selection`
+    /// becomes `natural selection`
+    Words,
+    /// Write slashlinks and wikilinks as words and drop URLs altogether
+    DropUrls,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlainOptions {
+    pub links: LinkText,
+    /// Keep at most one empty line between blocks, and none at the start or
+    /// end of the text
+    pub collapse_blank_lines: bool,
+    /// Wrap lines longer than this many characters. The lines that continue
+    /// a list item or quote are indented.
+    pub width: Option<usize>,
+}
+
+impl Default for PlainOptions {
+    fn default() -> Self {
+        PlainOptions {
+            links: LinkText::Raw,
+            collapse_blank_lines: false,
+            width: None,
+        }
+    }
+}
+
+/// The indent for lines that continue a wrapped list item or quote
+const HANGING_INDENT: &str = "  ";
+
+/// Write a slashlink as the words of its slug
+fn slug_words(slashlink: &str) -> String {
+    slashlink
+        .split(['/', '-', '_', '#'])
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Render the content of a block as plain text
+pub fn render_inline<E>(block: &Block<E>, options: &PlainOptions) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let text = block
+        .to_content_entities()
+        .iter()
+        .map(|entity| match (entity.as_ref(), options.links) {
+            (Entity::SlashLink(text), LinkText::Words | LinkText::DropUrls) => {
+                slug_words(text.as_ref())
+            }
+            (Entity::WikiLink(text), LinkText::Words | LinkText::DropUrls) => {
+                wiki_link_label(text.as_ref()).to_owned()
+            }
+            (Entity::HyperLink(_), LinkText::DropUrls) => String::new(),
+            (entity, _) => entity.to_string(),
+        })
+        .collect::<String>();
+
+    match options.links {
+        // Dropped URLs leave gaps behind
+        LinkText::DropUrls => text.split_whitespace().collect::<Vec<&str>>().join(" "),
+        _ => text,
+    }
+}
+
+/// Wrap text to a width, breaking only at whitespace. Words longer than the
+/// width get a line of their own.
+fn wrap(text: &str, width: usize, indent: &str) -> Vec<String> {
+    let mut lines = Vec::<String>::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        let length = line.chars().count();
+
+        if length > 0 && length + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+
+        if line.is_empty() {
+            if !lines.is_empty() {
+                line.push_str(indent);
+            }
+        } else {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Render blocks as plain text with the given options
+pub fn render_plain<E>(blocks: &[Block<E>], options: &PlainOptions) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let mut lines = Vec::<String>::new();
+
+    for block in blocks {
+        if let Block::Blank(_) = block {
+            if !options.collapse_blank_lines
+                || matches!(lines.last(), Some(line) if !line.is_empty())
+            {
+                lines.push(String::new());
+            }
+            continue;
+        }
+
+        let text = render_inline(block, options);
+        match options.width {
+            Some(width) => {
+                let indent = match block {
+                    Block::List(_) | Block::Quote(_) => HANGING_INDENT,
+                    _ => "",
+                };
+                lines.append(&mut wrap(&text, width, indent));
+            }
+            None => lines.push(text),
+        }
+    }
+
+    if options.collapse_blank_lines {
+        while matches!(lines.last(), Some(line) if line.is_empty()) {
+            lines.pop();
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Render blocks as plain text with the default options, like
+/// `blocks_to_plain` in the Python implementation
+pub fn strip<E>(blocks: &[Block<E>]) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    render_plain(blocks, &PlainOptions::default())
+}
@@ -0,0 +1,362 @@
+//! Render Subtext blocks through user-supplied templates, one for each kind
+//! of block and each kind of inline entity.
+//!
+//! Templates are text with `{name}` placeholders. `{#name}…{/name}` keeps
+//! its contents only when the value is non-empty, and `{^name}…{/name}` only
+//! when it is empty. `{{` and `}}` write a literal `{` and `}`, and unknown
+//! names are empty.
+//!
+//! Block templates see these values:
+//!
+//! - `content`: the block's entities, each rendered through its template
+//! - `text`: the block's text content, escaped
+//! - `id`: a unique slug of a header's text, escaped (empty for other blocks)
+//! - `kind`: `header`, `paragraph`, `list`, `quote` or `blank`
+//! - `index`: the block's position in its group of contiguous blocks of the
+//!   same kind, counting from 1
+//! - `first` and `last`: non-empty for the first and last block of a group
+//!
+//! Entity templates see these values:
+//!
+//! - `text`: the entity as written, escaped
+//! - `label`: the text a link is shown with, escaped
+//! - `url`: the URL a link resolves to, escaped, or empty if it does not
+//!   resolve
+//! - `source`: the entity as written, _not_ escaped
+//! - `kind`: `text`, `slashlink`, `hyperlink` or `wikilink`
+//! - `first` and `last`: non-empty for the first and last entity of a block
+//!
+//! Rendered groups are joined with [Templates::group_separator], and groups
+//! that render as nothing are dropped.
+use std::{collections::HashMap, str::FromStr};
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    block::{group_blocks, Block},
+    primitive::Entity,
+};
+
+use super::{
+    html::{escape_html, unique_id},
+    markdown::{escape_line_start, escape_markdown},
+    wiki_link_label, LinkResolver, LinkTarget,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Value(String),
+    Section {
+        name: String,
+        inverted: bool,
+        nodes: Vec<Node>,
+    },
+}
+
+/// A parsed template
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl FromStr for Template {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self> {
+        // Open sections, innermost last, with the nodes read inside each
+        let mut stack = vec![(String::new(), false, Vec::<Node>::new())];
+        let mut rest = source;
+
+        while let Some(start) = rest.find('{') {
+            let nodes = &mut stack.last_mut().unwrap().2;
+            nodes.push(Node::Text(rest[..start].replace("}}", "}")));
+
+            let after = &rest[start + 1..];
+            if let Some(after) = after.strip_prefix('{') {
+                nodes.push(Node::Text("{".into()));
+                rest = after;
+                continue;
+            }
+
+            let end = after
+                .find('}')
+                .ok_or_else(|| anyhow!("Unclosed placeholder in template"))?;
+            let tag = after[..end].trim();
+            rest = &after[end + 1..];
+
+            let (prefix, name) = match tag.strip_prefix(['#', '^', '/']) {
+                Some(name) => (tag.chars().next(), name.trim()),
+                None => (None, tag),
+            };
+            if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                return Err(anyhow!("Invalid placeholder {{{}}} in template", tag));
+            }
+
+            match prefix {
+                Some('/') => {
+                    let (open, inverted, children) = match stack.len() {
+                        1 => return Err(anyhow!("Unexpected {{/{}}} in template", name)),
+                        _ => stack.pop().unwrap(),
+                    };
+                    if open != name {
+                        return Err(anyhow!("Expected {{/{}}} but found {{/{}}}", open, name));
+                    }
+                    stack.last_mut().unwrap().2.push(Node::Section {
+                        name: open,
+                        inverted,
+                        nodes: children,
+                    });
+                }
+                Some(prefix) => stack.push((name.to_owned(), prefix == '^', Vec::new())),
+                None => nodes.push(Node::Value(name.to_owned())),
+            }
+        }
+
+        if let Some((open, ..)) = stack.get(1) {
+            return Err(anyhow!("Unclosed {{#{}}} in template", open));
+        }
+
+        let (_, _, mut nodes) = stack.pop().unwrap();
+        nodes.push(Node::Text(rest.replace("}}", "}")));
+        nodes.retain(|node| !matches!(node, Node::Text(text) if text.is_empty()));
+
+        Ok(Template { nodes })
+    }
+}
+
+/// The values a template is rendered with
+type Context = [(&'static str, String)];
+
+impl Template {
+    fn render(&self, context: &Context, output: &mut String) {
+        render_nodes(&self.nodes, context, output);
+    }
+}
+
+fn render_nodes(nodes: &[Node], context: &Context, output: &mut String) {
+    let value = |name: &str| {
+        context
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map_or("", |(_, value)| value.as_str())
+    };
+
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Value(name) => output.push_str(value(name)),
+            Node::Section {
+                name,
+                inverted,
+                nodes,
+            } => {
+                if value(name).is_empty() == *inverted {
+                    render_nodes(nodes, context, output);
+                }
+            }
+        }
+    }
+}
+
+/// A template for every kind of block and entity, and how to escape the
+/// values they are given
+#[derive(Debug, Clone)]
+pub struct Templates {
+    pub header: Template,
+    pub paragraph: Template,
+    pub list: Template,
+    pub quote: Template,
+    pub blank: Template,
+    /// Plain text, including whitespace
+    pub text: Template,
+    pub slashlink: Template,
+    pub hyperlink: Template,
+    pub wikilink: Template,
+    /// Written between groups of contiguous blocks of the same kind
+    pub group_separator: String,
+    /// Escapes the `text`, `label` and `id` values
+    pub escape: fn(&str) -> String,
+    /// Escapes the `url` value
+    pub escape_url: fn(&str) -> String,
+    /// Applied to the rendered `content` of each block
+    pub finish_content: fn(String) -> String,
+}
+
+fn template(source: &str) -> Template {
+    source.parse().expect("Built-in templates are valid")
+}
+
+impl Templates {
+    /// Templates that render the same HTML as [super::render_html]
+    pub fn html() -> Self {
+        let link = "{#url}<a href=\"{url}\">{label}</a>{/url}{^url}{label}{/url}";
+
+        Templates {
+            header: template("<h1>{content}</h1>{^last}\n{/last}"),
+            paragraph: template("<p>{content}</p>{^last}\n{/last}"),
+            list: template("{#first}<ul>\n{/first}<li>{content}</li>\n{#last}</ul>{/last}"),
+            quote: template(
+                "{#first}<blockquote>\n{/first}<p>{content}</p>\n{#last}</blockquote>{/last}",
+            ),
+            blank: template(""),
+            text: template("{text}"),
+            slashlink: template(link),
+            hyperlink: template(link),
+            wikilink: template(link),
+            group_separator: "\n".into(),
+            escape: escape_html,
+            escape_url: escape_html,
+            finish_content: |content| content,
+        }
+    }
+
+    /// Templates that render the same CommonMark as
+    /// [super::render_markdown]
+    pub fn markdown() -> Self {
+        let link = "{#url}[{label}]({url}){/url}{^url}{label}{/url}";
+
+        Templates {
+            header: template("# {content}{^last}\n\n{/last}"),
+            paragraph: template("{content}{^last}\n\n{/last}"),
+            list: template("- {content}{^last}\n{/last}"),
+            quote: template("> {content}{^last}\n>\n{/last}"),
+            blank: template(""),
+            text: template("{text}"),
+            slashlink: template(link),
+            hyperlink: template("<{source}>"),
+            wikilink: template(link),
+            group_separator: "\n\n".into(),
+            escape: escape_markdown,
+            escape_url: |url| match url.contains([' ', '(', ')', '<', '>']) {
+                true => format!("<{}>", url.replace(['<', '>'], "")),
+                false => url.to_owned(),
+            },
+            finish_content: escape_line_start,
+        }
+    }
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Templates::html()
+    }
+}
+
+fn flag(value: bool) -> String {
+    match value {
+        true => "true".into(),
+        false => String::new(),
+    }
+}
+
+/// Render the content entities of a block through the entity templates
+fn render_entities<E>(
+    block: &Block<E>,
+    templates: &Templates,
+    resolver: &impl LinkResolver,
+) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let entities = block.to_content_entities();
+    let mut output = String::new();
+
+    for (index, entity) in entities.iter().enumerate() {
+        let entity = entity.as_ref();
+        let source = entity.to_string();
+
+        let (template, kind, label, url) = match entity {
+            Entity::SlashLink(text) => (
+                &templates.slashlink,
+                "slashlink",
+                source.clone(),
+                resolver.resolve(&LinkTarget::SlashLink(text.as_ref())),
+            ),
+            Entity::WikiLink(text) => {
+                let label = wiki_link_label(text.as_ref());
+                (
+                    &templates.wikilink,
+                    "wikilink",
+                    label.to_owned(),
+                    resolver.resolve(&LinkTarget::WikiLink(label)),
+                )
+            }
+            Entity::HyperLink(_) => (
+                &templates.hyperlink,
+                "hyperlink",
+                source.clone(),
+                Some(source.clone()),
+            ),
+            _ => (&templates.text, "text", source.clone(), None),
+        };
+
+        let context = [
+            ("kind", kind.to_owned()),
+            ("text", (templates.escape)(&source)),
+            ("label", (templates.escape)(&label)),
+            (
+                "url",
+                url.map(|url| (templates.escape_url)(&url))
+                    .unwrap_or_default(),
+            ),
+            ("first", flag(index == 0)),
+            ("last", flag(index + 1 == entities.len())),
+            ("source", source),
+        ];
+        template.render(&context, &mut output);
+    }
+
+    output
+}
+
+/// Render blocks through a set of templates
+pub fn render_template<E>(
+    blocks: &[Block<E>],
+    templates: &Templates,
+    resolver: &impl LinkResolver,
+) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let mut ids = HashMap::<String, usize>::new();
+    let mut groups = Vec::<String>::new();
+
+    for group in group_blocks(blocks) {
+        let mut output = String::new();
+
+        for (index, block) in group.iter().enumerate() {
+            let (template, kind) = match block {
+                Block::Header(_) => (&templates.header, "header"),
+                Block::Paragraph(_) => (&templates.paragraph, "paragraph"),
+                Block::List(_) => (&templates.list, "list"),
+                Block::Quote(_) => (&templates.quote, "quote"),
+                Block::Blank(_) => (&templates.blank, "blank"),
+            };
+            let id = match block {
+                Block::Header(_) => unique_id(block, &mut ids),
+                _ => String::new(),
+            };
+
+            let context = [
+                ("kind", kind.to_owned()),
+                (
+                    "content",
+                    (templates.finish_content)(render_entities(block, templates, resolver)),
+                ),
+                ("text", (templates.escape)(&block.to_text_content())),
+                ("id", (templates.escape)(&id)),
+                ("index", (index + 1).to_string()),
+                ("first", flag(index == 0)),
+                ("last", flag(index + 1 == group.len())),
+            ];
+            template.render(&context, &mut output);
+        }
+
+        if !output.is_empty() {
+            groups.push(output);
+        }
+    }
+
+    groups.join(&templates.group_separator)
+}
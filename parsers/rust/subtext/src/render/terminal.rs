@@ -0,0 +1,256 @@
+//! Render Subtext blocks for reading in a terminal. Headers are bold and
+//! colored, list items get a bullet, quotes get a dim bar, and links are
+//! underlined and made clickable with OSC 8 escape sequences. Every block is
+//! wrapped to the width of the terminal.
+use std::io::IsTerminal;
+
+use crate::{block::Block, primitive::Entity};
+
+use super::{wiki_link_label, LinkResolver, LinkTarget};
+
+const RESET: &str = "\x1b[0m";
+const HEADER: &str = "\x1b[1;35m";
+const DIM: &str = "\x1b[2m";
+const LINK: &str = "\x1b[4;36m";
+const LINK_RESET: &str = "\x1b[24;39m";
+
+/// The width to wrap to when it can't be detected
+const DEFAULT_WIDTH: usize = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Always write colors and hyperlinks
+    Always,
+    /// Never write escape sequences
+    Never,
+    /// Write colors and hyperlinks only when standard output is a terminal
+    /// and `NO_COLOR` is not set
+    Auto,
+}
+
+impl ColorMode {
+    pub fn enabled(&self) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerminalOptions {
+    pub color: ColorMode,
+    /// The column to wrap at, or `None` to use the `COLUMNS` environment
+    /// variable, falling back to 80
+    pub width: Option<usize>,
+}
+
+impl Default for TerminalOptions {
+    fn default() -> Self {
+        TerminalOptions {
+            color: ColorMode::Auto,
+            width: None,
+        }
+    }
+}
+
+impl TerminalOptions {
+    fn width(&self) -> usize {
+        self.width
+            .or_else(|| std::env::var("COLUMNS").ok()?.parse().ok())
+            .filter(|width| *width > 0)
+            .unwrap_or(DEFAULT_WIDTH)
+    }
+}
+
+/// A run of text with no whitespace in it, which is never broken across
+/// lines
+#[derive(Debug, Default)]
+struct Word {
+    /// The number of columns the word takes up, ignoring escape sequences
+    width: usize,
+    text: String,
+}
+
+impl Word {
+    fn push(&mut self, text: &str, visible: &str) {
+        self.width += visible.chars().count();
+        self.text.push_str(text);
+    }
+}
+
+/// Remove control characters other than tabs, so that text from a note
+/// can't write escape sequences of its own to the terminal
+fn strip_controls(text: &str) -> String {
+    text.chars()
+        .filter(|character| *character == '\t' || !character.is_control())
+        .collect()
+}
+
+/// Write a link, wrapped in an OSC 8 hyperlink to `url` if there is one,
+/// then go back to the `style` of the block it is in. URLs containing
+/// control characters are dropped, as they could end the escape sequence
+/// early.
+fn link(text: &str, url: Option<String>, color: bool, style: &str) -> String {
+    let url = url.filter(|url| !url.contains(char::is_control));
+
+    match (color, url) {
+        (true, Some(url)) => format!(
+            "\x1b]8;;{}\x1b\\{}{}{}{}\x1b]8;;\x1b\\",
+            url, LINK, text, LINK_RESET, style
+        ),
+        (true, None) => format!("{}{}{}{}", LINK, text, LINK_RESET, style),
+        (false, _) => text.to_owned(),
+    }
+}
+
+/// Turn a path into a `file://` URL, leaving other URLs alone
+fn file_url(path: String) -> String {
+    match path.contains("://") {
+        true => path,
+        false => format!("file://{}", path),
+    }
+}
+
+/// Split the content of a block into words, styling links along the way
+fn words<E>(block: &Block<E>, resolver: &impl LinkResolver, color: bool, style: &str) -> Vec<Word>
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let mut words = Vec::<Word>::new();
+    let mut word = Word::default();
+
+    for entity in block.to_content_entities() {
+        let text = strip_controls(&entity.as_ref().to_string());
+        let styled = match entity.as_ref() {
+            Entity::HyperLink(url) => link(&text, Some(url.to_string()), color, style),
+            Entity::SlashLink(slashlink) => link(
+                &text,
+                resolver
+                    .resolve(&LinkTarget::SlashLink(slashlink.as_ref()))
+                    .map(file_url),
+                color,
+                style,
+            ),
+            Entity::WikiLink(label) => link(
+                &text,
+                resolver
+                    .resolve(&LinkTarget::WikiLink(wiki_link_label(label.as_ref())))
+                    .map(file_url),
+                color,
+                style,
+            ),
+            _ => {
+                for (index, part) in text.split(char::is_whitespace).enumerate() {
+                    if index > 0 && word.width > 0 {
+                        words.push(std::mem::take(&mut word));
+                    }
+                    word.push(part, part);
+                }
+                continue;
+            }
+        };
+        word.push(&styled, &text);
+    }
+
+    if word.width > 0 {
+        words.push(word);
+    }
+
+    words
+}
+
+/// The number of columns text takes up, skipping SGR escape sequences
+fn visible_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut escape = false;
+
+    for character in text.chars() {
+        match (escape, character) {
+            (true, 'm') => escape = false,
+            (true, _) => (),
+            (false, '\x1b') => escape = true,
+            (false, _) => width += 1,
+        }
+    }
+
+    width
+}
+
+/// Wrap words into lines no wider than `width`, including a prefix on the
+/// first line and an indent on the rest
+fn wrap(words: Vec<Word>, width: usize, prefix: (&str, &str)) -> Vec<String> {
+    let prefix_width = visible_width(prefix.0).max(visible_width(prefix.1));
+    let width = width.saturating_sub(prefix_width).max(1);
+
+    let mut lines = Vec::<String>::new();
+    let mut line = String::new();
+    let mut line_width = 0usize;
+
+    for word in words {
+        if line_width > 0 && line_width + 1 + word.width > width {
+            lines.push(std::mem::take(&mut line));
+            line_width = 0;
+        }
+        if line_width > 0 {
+            line.push(' ');
+            line_width += 1;
+        }
+        line.push_str(&word.text);
+        line_width += word.width;
+    }
+    lines.push(line);
+
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(index, line)| match index {
+            0 => format!("{}{}", prefix.0, line),
+            _ => format!("{}{}", prefix.1, line),
+        })
+        .collect()
+}
+
+/// Render blocks for a terminal
+pub fn render_terminal<E>(
+    blocks: &[Block<E>],
+    resolver: &impl LinkResolver,
+    options: &TerminalOptions,
+) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let color = options.color.enabled();
+    let width = options.width();
+    let bar = match color {
+        true => format!("{}│{} ", DIM, RESET),
+        false => "│ ".into(),
+    };
+    let mut lines = Vec::<String>::new();
+
+    for block in blocks {
+        let style = match block {
+            Block::Header(_) => HEADER,
+            _ => "",
+        };
+        let words = words(block, resolver, color, style);
+
+        match block {
+            Block::Header(_) => lines.extend(wrap(words, width, ("", "")).into_iter().map(
+                |line| match color {
+                    true => format!("{}{}{}", style, line, RESET),
+                    false => line,
+                },
+            )),
+            Block::List(_) => lines.append(&mut wrap(words, width, ("• ", "  "))),
+            Block::Quote(_) => lines.append(&mut wrap(words, width, (&bar, &bar))),
+            Block::Paragraph(_) => lines.append(&mut wrap(words, width, ("", ""))),
+            Block::Blank(_) => lines.push(String::new()),
+        }
+    }
+
+    lines.join("\n")
+}
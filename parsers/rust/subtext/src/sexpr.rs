@@ -0,0 +1,275 @@
+//! Print and read blocks in the s-expression notation that the
+//! specification uses to describe parse results:
+//!
+//! ```text
+//! ((header "Evolution")
+//!  (text "See " (slashlink "/natural-selection") " for more")
+//!  (blank)
+//!  (list "Mutation")
+//!  (quote (hyperlink "https://example.com")))
+//! ```
+//!
+//! Paragraphs are written `text`, as in the specification. Plain text and
+//! whitespace are written as strings, and links as `(slashlink ...)`,
+//! `(hyperlink ...)` and `(wikilink ...)`. The sigil of a header, list item
+//! or quote, and any whitespace that follows it, is implied by the block's
+//! name; the content of a blank line is not recorded.
+use anyhow::{anyhow, Result};
+
+use crate::{block::Block, primitive::Entity, str::SharedString};
+
+/// Write a string literal, escaping quotes, backslashes and control
+/// characters
+fn write_string(output: &mut String, text: &str) {
+    output.push('"');
+    for character in text.chars() {
+        match character {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            _ => output.push(character),
+        }
+    }
+    output.push('"');
+}
+
+/// Print a block as an s-expression
+pub fn to_sexpr<E>(block: &Block<E>) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let (name, entities) = match block {
+        Block::Header(_) => ("header", block.to_content_entities()),
+        Block::List(_) => ("list", block.to_content_entities()),
+        Block::Quote(_) => ("quote", block.to_content_entities()),
+        // Leading whitespace is significant in a paragraph: it stops a
+        // sigil character from being read as a sigil
+        Block::Paragraph(entities) => ("text", entities.iter().collect()),
+        Block::Blank(_) => return "(blank)".into(),
+    };
+
+    let mut output = format!("({}", name);
+    let mut text = String::new();
+
+    for entity in entities {
+        let kind = match entity.as_ref() {
+            Entity::SlashLink(_) => "slashlink",
+            Entity::HyperLink(_) => "hyperlink",
+            Entity::WikiLink(_) => "wikilink",
+            entity => {
+                text.push_str(&entity.to_string());
+                continue;
+            }
+        };
+
+        if !text.is_empty() {
+            output.push(' ');
+            write_string(&mut output, &std::mem::take(&mut text));
+        }
+        output.push_str(&format!(" ({} ", kind));
+        write_string(&mut output, &entity.as_ref().to_string());
+        output.push(')');
+    }
+
+    if !text.is_empty() {
+        output.push(' ');
+        write_string(&mut output, &text);
+    }
+    output.push(')');
+    output
+}
+
+/// Print blocks as a list of s-expressions, one block per line
+pub fn blocks_to_sexpr<E>(blocks: &[Block<E>]) -> String
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let blocks = blocks.iter().map(to_sexpr).collect::<Vec<String>>();
+    format!("({})", blocks.join("\n "))
+}
+
+#[derive(Debug, PartialEq)]
+enum Sexpr {
+    Symbol(String),
+    String(String),
+    List(Vec<Sexpr>),
+}
+
+/// Reads s-expressions from text
+struct Reader<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+}
+
+impl Reader<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn read(&mut self) -> Result<Sexpr> {
+        self.skip_whitespace();
+
+        match self.chars.next() {
+            Some((_, '(')) => {
+                let mut items = Vec::new();
+                loop {
+                    self.skip_whitespace();
+                    match self.chars.peek() {
+                        Some((_, ')')) => {
+                            self.chars.next();
+                            return Ok(Sexpr::List(items));
+                        }
+                        Some(_) => items.push(self.read()?),
+                        None => return Err(anyhow!("Unexpected end of input in a list")),
+                    }
+                }
+            }
+            Some((_, '"')) => {
+                let mut text = String::new();
+                loop {
+                    match self.chars.next() {
+                        Some((_, '"')) => return Ok(Sexpr::String(text)),
+                        Some((index, '\\')) => text.push(match self.chars.next() {
+                            Some((_, 'n')) => '\n',
+                            Some((_, 'r')) => '\r',
+                            Some((_, 't')) => '\t',
+                            Some((_, c @ ('"' | '\\'))) => c,
+                            _ => return Err(anyhow!("Invalid escape at position {}", index)),
+                        }),
+                        Some((_, c)) => text.push(c),
+                        None => return Err(anyhow!("Unexpected end of input in a string")),
+                    }
+                }
+            }
+            Some((index, ')')) => Err(anyhow!("Unexpected ) at position {}", index)),
+            Some((_, c)) => {
+                let mut symbol = c.to_string();
+                while let Some((_, c)) = self
+                    .chars
+                    .next_if(|(_, c)| !c.is_whitespace() && !matches!(c, '(' | ')' | '"'))
+                {
+                    symbol.push(c);
+                }
+                Ok(Sexpr::Symbol(symbol))
+            }
+            None => Err(anyhow!("Unexpected end of input")),
+        }
+    }
+
+    fn read_all(input: &str) -> Result<Sexpr> {
+        let mut reader = Reader {
+            chars: input.char_indices().peekable(),
+        };
+        let sexpr = reader.read()?;
+
+        reader.skip_whitespace();
+        match reader.chars.next() {
+            Some((index, _)) => Err(anyhow!("Unexpected input at position {}", index)),
+            None => Ok(sexpr),
+        }
+    }
+}
+
+/// Turn a block s-expression back into a line of Subtext and parse it,
+/// checking that the parser reads the line the way the s-expression says
+fn read_block<E>(sexpr: &Sexpr) -> Result<Block<E>>
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    let (name, content) = match sexpr {
+        Sexpr::List(items) => match items.split_first() {
+            Some((Sexpr::Symbol(name), content)) => (name.as_str(), content),
+            _ => return Err(anyhow!("A block must start with its type")),
+        },
+        _ => return Err(anyhow!("Expected a block but found {:?}", sexpr)),
+    };
+
+    let mut text = String::new();
+    for item in content {
+        match item {
+            Sexpr::String(string) => text.push_str(string),
+            Sexpr::List(items) => match items.as_slice() {
+                [Sexpr::Symbol(kind), Sexpr::String(link)]
+                    if matches!(kind.as_str(), "slashlink" | "hyperlink" | "wikilink") =>
+                {
+                    text.push_str(link)
+                }
+                _ => return Err(anyhow!("Expected an inline entity but found {:?}", item)),
+            },
+            Sexpr::Symbol(symbol) => return Err(anyhow!("Unexpected symbol {}", symbol)),
+        }
+    }
+
+    if text.contains('\n') {
+        return Err(anyhow!("A block may not contain a line break"));
+    }
+
+    let line = match (name, text.is_empty()) {
+        ("blank", true) => return Ok(Block::Blank(Entity::EmptySpace("".into()).into())),
+        ("blank", false) => return Err(anyhow!("A blank block has no content")),
+        ("text", _) => text,
+        ("header", true) => "#".into(),
+        ("list", true) => "-".into(),
+        ("quote", true) => ">".into(),
+        ("header", false) => format!("# {}", text),
+        ("list", false) => format!("- {}", text),
+        ("quote", false) => format!("> {}", text),
+        (name, _) => return Err(anyhow!("Unknown block type {}", name)),
+    };
+
+    let (block, _) = crate::block::parse::<E>(SharedString::from(line.as_str()))
+        .map_err(|_| anyhow!("Could not parse {:?}", line))?;
+
+    // Links and block types only mean what the s-expression says if the
+    // parser agrees
+    let expected = Reader::read_all(&to_sexpr(&block))?;
+    if &expected != sexpr {
+        return Err(anyhow!(
+            "{} would be parsed as {}",
+            write_sexpr(sexpr),
+            to_sexpr(&block)
+        ));
+    }
+
+    Ok(block)
+}
+
+/// Write an s-expression that has been read, for error messages
+fn write_sexpr(sexpr: &Sexpr) -> String {
+    match sexpr {
+        Sexpr::Symbol(symbol) => symbol.clone(),
+        Sexpr::String(string) => {
+            let mut output = String::new();
+            write_string(&mut output, string);
+            output
+        }
+        Sexpr::List(items) => format!(
+            "({})",
+            items
+                .iter()
+                .map(write_sexpr)
+                .collect::<Vec<String>>()
+                .join(" ")
+        ),
+    }
+}
+
+/// Read a single block from an s-expression such as `(text "Hello")`
+pub fn block_from_sexpr<E>(input: &str) -> Result<Block<E>>
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    read_block(&Reader::read_all(input)?)
+}
+
+/// Read a list of blocks, as printed by [blocks_to_sexpr]
+pub fn blocks_from_sexpr<E>(input: &str) -> Result<Vec<Block<E>>>
+where
+    E: From<Entity> + AsRef<Entity>,
+{
+    match Reader::read_all(input)? {
+        Sexpr::List(items) => items.iter().map(read_block).collect(),
+        sexpr => Err(anyhow!("Expected a list of blocks but found {:?}", sexpr)),
+    }
+}
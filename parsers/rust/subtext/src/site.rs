@@ -0,0 +1,403 @@
+//! Publish a directory of `.subtext` notes as a static website.
+//!
+//! Every note becomes a page at `{slug}.html`, titled after its first header
+//! and followed by a list of the pages that link to it. Slashlinks and
+//! wikilinks resolve to the page with the matching slug, and links that
+//! match no page are collected into a report. An `index.html` lists every
+//! page; a note with the slug `index` is shown above that list.
+//!
+//! Builds are incremental. Each page is keyed on a hash of its source and
+//! of everything else that shows up on it, and the keys of the last build
+//! are kept in the output directory, so a rebuild only writes the pages
+//! that changed and removes the pages whose notes are gone.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    block::Block,
+    primitive::Entity,
+    render::{
+        html::{escape_html, render_html_with, HtmlOptions},
+        wiki_link_label, LinkTarget,
+    },
+    util::to_slug,
+};
+
+/// The file in the output directory that records the last build
+pub const MANIFEST: &str = ".subtext-site";
+
+/// The slug of the generated index page
+const INDEX: &str = "index";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiteOptions {
+    /// The directory of notes, which is searched recursively
+    pub source: PathBuf,
+    /// The directory to write pages to
+    pub output: PathBuf,
+    /// The title of the site, shown on the index page and in page titles
+    pub title: String,
+}
+
+/// A link to a page that does not exist
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BrokenLink {
+    /// The slug of the page the link is on
+    pub page: String,
+    /// The link as it was written
+    pub link: String,
+}
+
+/// What a build did
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Build {
+    /// Slugs of the pages that were written, including `index`
+    pub written: Vec<String>,
+    /// Slugs of the pages that were already up to date
+    pub unchanged: Vec<String>,
+    /// Slugs of the pages that were deleted because their note is gone
+    pub removed: Vec<String>,
+    pub broken_links: Vec<BrokenLink>,
+}
+
+/// A parsed note
+#[derive(Debug)]
+struct Note {
+    source: Vec<u8>,
+    blocks: Vec<Block<Entity>>,
+    title: String,
+    /// Slugs of the pages this note links to
+    links: BTreeSet<String>,
+}
+
+/// The slug a slashlink or wikilink refers to
+fn link_slug(target: &LinkTarget) -> Option<String> {
+    let slug = match target {
+        LinkTarget::SlashLink(slashlink) => to_slug(link_fragment(slashlink).0),
+        LinkTarget::WikiLink(label) => to_slug(label),
+    };
+
+    slug.ok().filter(|slug| !slug.is_empty())
+}
+
+/// Split a slashlink into the link to a page and the fragment that points
+/// into it, as in `/evolution#questions`
+fn link_fragment(slashlink: &str) -> (&str, Option<&str>) {
+    match slashlink.split_once('#') {
+        Some((link, fragment)) => (link, Some(fragment)),
+        None => (slashlink, None),
+    }
+}
+
+/// The slugs of every slashlink and wikilink in some blocks, along with
+/// the links as written. Links to other peers' notes are left out.
+fn links(blocks: &[Block<Entity>]) -> Vec<(String, Option<String>)> {
+    blocks
+        .iter()
+        .flat_map(|block| block.to_content_entities())
+        .filter_map(|entity| match entity {
+            Entity::SlashLink(text) if text.starts_with('@') => None,
+            Entity::SlashLink(text) => Some((
+                text.to_string(),
+                link_slug(&LinkTarget::SlashLink(text.as_ref())),
+            )),
+            Entity::WikiLink(text) => Some((
+                text.to_string(),
+                link_slug(&LinkTarget::WikiLink(wiki_link_label(text.as_ref()))),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Find every note under a directory, keyed by slug
+fn read_notes(source: &Path) -> Result<BTreeMap<String, Note>> {
+    let mut paths = Vec::new();
+    let mut directories = vec![source.to_path_buf()];
+
+    while let Some(directory) = directories.pop() {
+        let entries = fs::read_dir(&directory)
+            .with_context(|| format!("Could not read {}", directory.display()))?;
+
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                directories.push(path);
+            } else if path
+                .extension()
+                .is_some_and(|extension| extension == "subtext")
+            {
+                paths.push(path);
+            }
+        }
+    }
+
+    let mut notes = BTreeMap::new();
+    for path in paths {
+        let relative = path.strip_prefix(source)?.with_extension("");
+        let name = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let slug = to_slug(&name)?;
+        if slug.is_empty() {
+            return Err(anyhow!("{} has no usable slug", path.display()));
+        }
+
+        let source =
+            fs::read(&path).with_context(|| format!("Could not read {}", path.display()))?;
+        let blocks: Vec<Block<Entity>> = crate::parse(&source)
+            .with_context(|| format!("Could not parse {}", path.display()))?
+            .collect();
+        let title = blocks
+            .iter()
+            .find(|block| matches!(block, Block::Header(_)))
+            .map(|header| header.to_text_content().trim().to_owned())
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| slug.clone());
+        let links = links(&blocks)
+            .into_iter()
+            .filter_map(|(_, slug)| slug)
+            .collect();
+
+        let note = Note {
+            source,
+            blocks,
+            title,
+            links,
+        };
+        if notes.insert(slug.clone(), note).is_some() {
+            return Err(anyhow!("More than one note has the slug {}", slug));
+        }
+    }
+
+    Ok(notes)
+}
+
+/// The URL of a page, relative to the page with the slug `from`
+fn page_url(from: &str, to: &str) -> String {
+    format!("{}{}.html", "../".repeat(from.matches('/').count()), to)
+}
+
+fn page(site_title: &str, title: &str, body: &str) -> String {
+    let title = match title == site_title {
+        true => escape_html(title),
+        false => format!("{} | {}", escape_html(title), escape_html(site_title)),
+    };
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}\n</body>\n</html>\n",
+        title, body
+    )
+}
+
+/// Read the keys of the last build
+fn read_manifest(output: &Path) -> BTreeMap<String, String> {
+    fs::read_to_string(output.join(MANIFEST))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(key, slug)| (slug.to_owned(), key.to_owned()))
+        .collect()
+}
+
+/// The notes of a site and the links between them
+struct Site<'a> {
+    options: &'a SiteOptions,
+    notes: BTreeMap<String, Note>,
+    /// The slugs of the notes that link to each slug
+    backlinks: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Site<'_> {
+    /// The slugs of the pages, with the index last
+    fn pages(&self) -> Vec<String> {
+        self.notes
+            .keys()
+            .filter(|slug| *slug != INDEX)
+            .cloned()
+            .chain([INDEX.to_owned()])
+            .collect()
+    }
+
+    /// Each of the given slugs with the title of its page, one per line
+    fn titles<'b>(&self, slugs: impl Iterator<Item = &'b String>) -> String {
+        slugs
+            .map(|slug| format!("{} {}\n", slug, self.notes[slug].title))
+            .collect()
+    }
+
+    /// A hash of everything that the page with the given slug shows: its
+    /// note, which of its links lead somewhere, and the pages it lists
+    fn key(&self, slug: &str) -> String {
+        let note = self.notes.get(slug);
+        let existing = note
+            .map(|note| {
+                note.links
+                    .iter()
+                    .filter(|target| self.notes.contains_key(*target))
+                    .map(|target| format!("{}\n", target))
+                    .collect::<String>()
+            })
+            .unwrap_or_default();
+        let listed = match slug {
+            INDEX => self.titles(self.notes.keys().filter(|slug| *slug != INDEX)),
+            _ => self.titles(self.backlinks.get(slug).into_iter().flatten()),
+        };
+
+        let mut hasher = Sha256::new();
+        for part in [
+            self.options.title.as_bytes(),
+            note.map_or(&[], |note| &note.source),
+            existing.as_bytes(),
+            listed.as_bytes(),
+        ] {
+            hasher.update((part.len() as u64).to_be_bytes());
+            hasher.update(part);
+        }
+
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Render the main content of a note, with links resolved relative to it
+    fn content(&self, slug: &str, note: &Note) -> String {
+        let resolve = |target: &LinkTarget| {
+            if matches!(target, LinkTarget::SlashLink(link) if link.starts_with('@')) {
+                return None;
+            }
+            let fragment = match target {
+                LinkTarget::SlashLink(link) => link_fragment(link).1,
+                LinkTarget::WikiLink(_) => None,
+            };
+            link_slug(target)
+                .filter(|target| self.notes.contains_key(target))
+                .map(|target| match fragment {
+                    Some(fragment) => format!("{}#{}", page_url(slug, &target), fragment),
+                    None => page_url(slug, &target),
+                })
+        };
+
+        let html = render_html_with(&note.blocks, &resolve, &HtmlOptions { header_ids: true });
+        format!("<main>\n{}\n</main>", html)
+    }
+
+    /// A list of links to pages, as seen from the page with the slug `from`
+    fn list<'b>(&self, from: &str, slugs: impl Iterator<Item = &'b String>) -> String {
+        let items = slugs
+            .map(|slug| {
+                format!(
+                    "<li><a href=\"{}\">{}</a></li>",
+                    escape_html(&page_url(from, slug)),
+                    escape_html(&self.notes[slug].title)
+                )
+            })
+            .collect::<Vec<String>>();
+
+        format!("<ul>\n{}\n</ul>", items.join("\n"))
+    }
+
+    fn render(&self, slug: &str) -> String {
+        let site_title = &self.options.title;
+
+        if slug == INDEX {
+            let mut body = match self.notes.get(INDEX) {
+                Some(note) => self.content(INDEX, note),
+                None => format!("<h1>{}</h1>", escape_html(site_title)),
+            };
+            body.push_str(&format!(
+                "\n<nav class=\"pages\">\n{}\n</nav>",
+                self.list(INDEX, self.notes.keys().filter(|slug| *slug != INDEX))
+            ));
+            return page(site_title, site_title, &body);
+        }
+
+        let note = &self.notes[slug];
+        let mut body = self.content(slug, note);
+        if let Some(backlinks) = self.backlinks.get(slug) {
+            body.push_str(&format!(
+                "\n<nav class=\"backlinks\">\n<h2>Backlinks</h2>\n{}\n</nav>",
+                self.list(slug, backlinks.iter())
+            ));
+        }
+        page(site_title, &note.title, &body)
+    }
+}
+
+/// Build the site, writing only the pages that changed since the last build
+pub fn build(options: &SiteOptions) -> Result<Build> {
+    let notes = read_notes(&options.source)?;
+    let mut build = Build::default();
+
+    let mut backlinks = BTreeMap::<String, BTreeSet<String>>::new();
+    for (slug, note) in &notes {
+        for target in note.links.iter().filter(|target| *target != slug) {
+            backlinks
+                .entry(target.clone())
+                .or_default()
+                .insert(slug.clone());
+        }
+
+        for (link, target) in links(&note.blocks) {
+            if !target.is_some_and(|target| notes.contains_key(&target)) {
+                build.broken_links.push(BrokenLink {
+                    page: slug.clone(),
+                    link,
+                });
+            }
+        }
+    }
+
+    let site = Site {
+        options,
+        notes,
+        backlinks,
+    };
+    let previous = read_manifest(&options.output);
+    let mut keys = BTreeMap::<String, String>::new();
+
+    for slug in site.pages() {
+        let key = site.key(&slug);
+        let path = options.output.join(format!("{}.html", slug));
+
+        if previous.get(&slug) == Some(&key) && path.exists() {
+            build.unchanged.push(slug.clone());
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, site.render(&slug))
+                .with_context(|| format!("Could not write {}", path.display()))?;
+            build.written.push(slug.clone());
+        }
+
+        keys.insert(slug, key);
+    }
+
+    for slug in previous.keys().filter(|slug| !keys.contains_key(*slug)) {
+        let path = options.output.join(format!("{}.html", slug));
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Could not remove {}", path.display()))?;
+        }
+        build.removed.push(slug.clone());
+    }
+
+    let manifest = keys
+        .iter()
+        .map(|(slug, key)| format!("{} {}\n", key, slug))
+        .collect::<String>();
+    fs::write(options.output.join(MANIFEST), manifest)?;
+
+    Ok(build)
+}
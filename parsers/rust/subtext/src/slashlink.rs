@@ -1,24 +1,54 @@
 use anyhow::anyhow;
-use std::{fmt::Display, str::FromStr};
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+use crate::{cid::Cid, did::Did, primitive::Entity};
 
 /// The various forms that the "peer" part of a slashlink may take
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum Peer {
     Name(Vec<String>),
     Did(String),
     None,
 }
 
+impl Peer {
+    /// The parsed DID of a [Peer::Did]
+    pub fn did(&self) -> Option<Did> {
+        match self {
+            Peer::Did(did) => Did::from_str(did).ok(),
+            _ => None,
+        }
+    }
+}
+
 /// A slashlink is form of reference to content in a Sphere. It consists of a
 /// peer part and a slug part. A slashlink with just the slug looks like:
 /// `/foo`. A slashlink with just the peer looks like: `@cdata`. With both
 /// parts, the link would look like: `@cdata/foo`.
 ///
+/// A slashlink may also end in a fragment that points into the linked
+/// document, either at a block ID or at a section: `/foo#b3f9c1` or
+/// `/foo#questions` (see [crate::anchor]).
+///
+/// When the slug is a CID (`/bafkrei...`), the slashlink refers to an
+/// immutable version of a document rather than to whatever the slug
+/// currently points at.
+///
+/// Slashlinks are compared, hashed and ordered by their normalized form
+/// (see [Slashlink::normalize]), so `/Evolution` and `/evolution/` are
+/// equal.
+///
 /// This struct makes it easier to parse a slashlink from a string.
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, Clone)]
 pub struct Slashlink {
     pub peer: Peer,
     pub slug: Option<String>,
+    pub fragment: Option<String>,
 }
 
 impl FromStr for Slashlink {
@@ -28,6 +58,14 @@ impl FromStr for Slashlink {
         let mut parsing_peer = false;
         let mut parsing_link = false;
 
+        let (s, fragment) = match s.split_once('#') {
+            Some((link, fragment)) if is_valid_fragment(fragment) => {
+                (link, Some(fragment.to_owned()))
+            }
+            Some(_) => return Err(anyhow!("Could not parse {} as SlashLink", s)),
+            None => (s, None),
+        };
+
         let mut raw_peer = String::new();
         let mut slug = None;
 
@@ -51,8 +89,10 @@ impl FromStr for Slashlink {
             }
         }
 
-        let peer = if raw_peer.len() > 0 {
+        let peer = if !raw_peer.is_empty() {
             if raw_peer.starts_with("did:") {
+                Did::from_str(&raw_peer)
+                    .map_err(|error| anyhow!("Invalid DID in {}: {}", s, error))?;
                 Peer::Did(raw_peer)
             } else {
                 Peer::Name(raw_peer.split('.').map(|s| s.to_owned()).collect())
@@ -61,14 +101,186 @@ impl FromStr for Slashlink {
             Peer::None
         };
 
-        if peer == Peer::None && slug == None {
+        if peer == Peer::None && slug.is_none() {
             Err(anyhow!("Could not parse {} as SlashLink", s))
         } else {
-            Ok(Slashlink { peer, slug })
+            Ok(Slashlink {
+                peer,
+                slug,
+                fragment,
+            })
+        }
+    }
+}
+
+fn lowercase(text: &str) -> impl Iterator<Item = char> + '_ {
+    text.chars().flat_map(char::to_lowercase)
+}
+
+/// Whether a fragment follows the same grammar as a slug segment: one or
+/// more letters, digits, dashes and underscores
+fn is_valid_fragment(fragment: &str) -> bool {
+    !fragment.is_empty()
+        && fragment
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+impl TryFrom<&Entity> for Slashlink {
+    type Error = anyhow::Error;
+
+    fn try_from(entity: &Entity) -> Result<Self, Self::Error> {
+        match entity {
+            Entity::SlashLink(text) => Slashlink::from_str(text.as_ref()),
+            entity => Err(anyhow!("{:?} is not a slashlink", entity)),
         }
     }
 }
 
+impl Slashlink {
+    /// The canonical form of this slashlink. Peer names, slugs and fragments
+    /// are lowercased, and empty segments of the slug (from duplicate,
+    /// leading or trailing slashes) are removed. DIDs and slugs that have the
+    /// shape of a CID (see [Cid::is_candidate]) are case sensitive, so they
+    /// are left as they are.
+    pub fn normalize(&self) -> Slashlink {
+        let peer = match &self.peer {
+            Peer::Name(names) => Peer::Name(names.iter().map(|name| name.to_lowercase()).collect()),
+            peer => peer.clone(),
+        };
+
+        let slug = match (&self.slug, self.is_case_sensitive()) {
+            (Some(slug), false) => Some(
+                slug.split('/')
+                    .filter(|segment| !segment.is_empty())
+                    .collect::<Vec<&str>>()
+                    .join("/")
+                    .to_lowercase(),
+            )
+            .filter(|slug| !slug.is_empty()),
+            (slug, _) => slug.clone(),
+        };
+
+        Slashlink {
+            peer,
+            slug,
+            fragment: self
+                .fragment
+                .as_ref()
+                .map(|fragment| fragment.to_lowercase()),
+        }
+    }
+
+    /// The characters of the normalized slashlink as it is displayed, which
+    /// identify it for comparison and hashing. They are produced one at a
+    /// time, so that comparing or hashing a slashlink does not allocate.
+    fn key(&self) -> impl Iterator<Item = char> + '_ {
+        let (names, did) = match &self.peer {
+            Peer::Name(names) => (names.as_slice(), ""),
+            Peer::Did(did) => (&[][..], did.as_str()),
+            Peer::None => (&[][..], ""),
+        };
+        let peer = (self.peer != Peer::None)
+            .then_some('@')
+            .into_iter()
+            .chain(names.iter().enumerate().flat_map(move |(index, name)| {
+                (index > 0)
+                    .then_some('.')
+                    .into_iter()
+                    .chain(lowercase(name))
+            }))
+            .chain(did.chars());
+
+        // CID slugs never contain a slash
+        let case_sensitive = self.is_case_sensitive();
+        let slug = self
+            .slug
+            .iter()
+            .flat_map(|slug| slug.split('/'))
+            .filter(|segment| !segment.is_empty())
+            .flat_map(move |segment| {
+                let lowercased = (!case_sensitive).then(|| lowercase(segment));
+                let verbatim = case_sensitive.then(|| segment.chars());
+                std::iter::once('/').chain(
+                    lowercased
+                        .into_iter()
+                        .flatten()
+                        .chain(verbatim.into_iter().flatten()),
+                )
+            });
+
+        let fragment = self
+            .fragment
+            .iter()
+            .flat_map(move |fragment| std::iter::once('#').chain(lowercase(fragment)));
+
+        peer.chain(slug).chain(fragment)
+    }
+
+    /// Whether the slug may be a CID, and so must keep its case. This only
+    /// looks at the shape of the slug, so that comparing slashlinks never
+    /// has to decode one.
+    fn is_case_sensitive(&self) -> bool {
+        self.slug.as_deref().is_some_and(Cid::is_candidate)
+    }
+
+    /// Create a slashlink that refers to the given content
+    pub fn for_cid(cid: &Cid) -> Self {
+        Slashlink {
+            peer: Peer::None,
+            slug: Some(cid.to_string()),
+            fragment: None,
+        }
+    }
+
+    /// Create a slashlink to an immutable version of a serialized Subtext
+    /// document
+    pub fn for_document(bytes: &[u8]) -> Self {
+        Slashlink::for_cid(&Cid::of_document(bytes))
+    }
+
+    /// The CID this slashlink refers to, if its slug is a CID
+    pub fn cid(&self) -> Option<Cid> {
+        self.try_cid().ok().flatten()
+    }
+
+    /// The CID this slashlink refers to, or an error explaining why a slug
+    /// that has the shape of a CID does not decode as one. Slugs that do
+    /// not look like a CID at all are `Ok(None)`.
+    pub fn try_cid(&self) -> Result<Option<Cid>, anyhow::Error> {
+        match self.slug.as_deref() {
+            Some(slug) if Cid::is_candidate(slug) => Cid::from_str(slug).map(Some),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl PartialEq for Slashlink {
+    fn eq(&self, other: &Self) -> bool {
+        self.key().eq(other.key())
+    }
+}
+
+impl Eq for Slashlink {}
+
+impl Hash for Slashlink {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().for_each(|character| character.hash(state));
+    }
+}
+
+impl PartialOrd for Slashlink {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Slashlink {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(other.key())
+    }
+}
+
 impl Display for Slashlink {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.peer {
@@ -82,15 +294,23 @@ impl Display for Slashlink {
             None => Ok(()),
         }?;
 
+        match &self.fragment {
+            Some(fragment) => write!(f, "#{}", fragment),
+            None => Ok(()),
+        }?;
+
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
+    use std::{collections::HashSet, str::FromStr};
 
-    use crate::slashlink::{Peer, Slashlink};
+    use crate::{
+        primitive::Entity,
+        slashlink::{Peer, Slashlink},
+    };
 
     #[test]
     fn it_can_parse_a_basic_slashlink() {
@@ -160,9 +380,27 @@ mod tests {
         assert_eq!(slashlink.slug, None);
     }
 
+    #[test]
+    fn it_can_parse_a_slashlink_with_a_fragment() {
+        let slashlink = Slashlink::from_str("/evolution#b3f9c1").unwrap();
+
+        assert_eq!(slashlink.peer, Peer::None);
+        assert_eq!(slashlink.slug, Some("evolution".into()));
+        assert_eq!(slashlink.fragment, Some("b3f9c1".into()));
+
+        let slashlink = Slashlink::from_str("@cdata/evolution#questions").unwrap();
+
+        assert_eq!(slashlink.peer, Peer::Name(vec!["cdata".into()]));
+        assert_eq!(slashlink.slug, Some("evolution".into()));
+        assert_eq!(slashlink.fragment, Some("questions".into()));
+        assert_eq!(slashlink.to_string(), "@cdata/evolution#questions");
+    }
+
     #[test]
     fn it_will_not_parse_a_non_slashlink() {
-        let non_slashlinks = vec!["cdata", "@", "/", "@/", "foo/bar"];
+        let non_slashlinks = vec![
+            "cdata", "@", "/", "@/", "foo/bar", "/foo#", "#foo", "/foo#a#b", "/foo#a b", "/foo#a.",
+        ];
         for test_case in non_slashlinks {
             println!("Checking {}", test_case);
             assert!(Slashlink::from_str(test_case).is_err())
@@ -170,6 +408,186 @@ mod tests {
     }
 
     #[test]
-    #[ignore = "TODO(subconsciousnetwork/subtext#36)"]
-    fn it_can_parse_a_slashlink_that_is_a_cid() {}
+    fn it_can_parse_a_slashlink_that_is_a_cid() {
+        let slashlink =
+            Slashlink::from_str("/bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e")
+                .unwrap();
+        let cid = slashlink.cid().unwrap();
+
+        assert_eq!(cid.version, 1);
+        assert_eq!(cid.codec_name(), Some("raw"));
+        assert_eq!(cid.digest.len(), 32);
+
+        let slashlink =
+            Slashlink::from_str("/QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG").unwrap();
+        let cid = slashlink.cid().unwrap();
+
+        assert_eq!(cid.version, 0);
+        assert_eq!(cid.codec_name(), Some("dag-pb"));
+        assert_eq!(
+            cid.to_string(),
+            "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG"
+        );
+    }
+
+    #[test]
+    fn it_does_not_treat_an_ordinary_slug_as_a_cid() {
+        let slashlink = Slashlink::from_str("/banana-bread").unwrap();
+
+        assert_eq!(slashlink.cid(), None);
+    }
+
+    #[test]
+    fn it_treats_a_slug_that_does_not_decode_as_an_ordinary_slug() {
+        for test_case in [
+            "/bananabreadrecipefromgrandmawithextrachocolatechips",
+            "/zebrastripesandleopardspotsandotheranimalpatterns0",
+            "/QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbd0",
+            "/bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36",
+        ] {
+            let slashlink = Slashlink::from_str(test_case).unwrap();
+
+            assert_eq!(slashlink.slug.as_deref(), Some(&test_case[1..]));
+            assert_eq!(slashlink.cid(), None, "{}", test_case);
+        }
+    }
+
+    #[test]
+    fn it_reports_why_a_slug_shaped_like_a_cid_is_malformed() {
+        let slashlink =
+            Slashlink::from_str("/QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbd0").unwrap();
+        let error = slashlink.try_cid().unwrap_err().to_string();
+
+        assert!(
+            error.contains("Invalid base58 character '0' at position 46"),
+            "{}",
+            error
+        );
+
+        let slashlink =
+            Slashlink::from_str("/bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36")
+                .unwrap();
+        let error = slashlink.try_cid().unwrap_err().to_string();
+
+        assert!(error.contains("declares a 32 byte digest"), "{}", error);
+
+        let slashlink = Slashlink::from_str("/banana-bread").unwrap();
+
+        assert!(matches!(slashlink.try_cid(), Ok(None)));
+    }
+
+    #[test]
+    fn it_rejects_a_malformed_did() {
+        let error = Slashlink::from_str("@did:/foo").unwrap_err().to_string();
+        assert!(error.starts_with("Invalid DID in @did:/foo"), "{}", error);
+
+        for test_case in [
+            "@did:Key:abc",
+            "@did:web:example.com:",
+            "@did:key:z6MkAlice",
+        ] {
+            assert!(Slashlink::from_str(test_case).is_err(), "{}", test_case);
+        }
+
+        let slashlink = Slashlink::from_str("@did:web:example.com%3A8080/foo").unwrap();
+        assert_eq!(slashlink.peer.did().unwrap().method, "web");
+    }
+
+    #[test]
+    fn it_computes_the_cid_of_a_document() {
+        let slashlink = Slashlink::for_document(b"# Hello, world!");
+        let parsed = Slashlink::from_str(&slashlink.to_string()).unwrap();
+
+        assert_eq!(parsed, slashlink);
+        assert_eq!(
+            parsed.cid(),
+            Some(crate::cid::Cid::of_document(b"# Hello, world!"))
+        );
+        assert!(slashlink.to_string().starts_with("/bafkrei"));
+    }
+
+    #[test]
+    fn it_normalizes_case_and_redundant_slashes() {
+        let slashlink = Slashlink::from_str("@Cdata.Ben/Evolution//Natural-Selection/#Questions")
+            .unwrap()
+            .normalize();
+
+        assert_eq!(
+            slashlink.to_string(),
+            "@cdata.ben/evolution/natural-selection#questions"
+        );
+
+        let slashlink = Slashlink::from_str(
+            "@did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK/QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG",
+        )
+        .unwrap()
+        .normalize();
+
+        assert_eq!(
+            slashlink.to_string(),
+            "@did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK/QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG"
+        );
+    }
+
+    #[test]
+    fn it_compares_and_hashes_the_normalized_form() {
+        let links = [
+            "/Evolution",
+            "/evolution",
+            "/evolution/",
+            "/Evolution/Notes",
+            "/evolution//notes",
+        ]
+        .iter()
+        .map(|link| Slashlink::from_str(link).unwrap())
+        .collect::<Vec<Slashlink>>();
+
+        assert_eq!(links[0], links[1]);
+        assert_eq!(links[3], links[4]);
+        assert_ne!(links[0], links[3]);
+        assert_eq!(links.iter().collect::<HashSet<_>>().len(), 2);
+
+        let mut sorted = [
+            Slashlink::from_str("/Zebra").unwrap(),
+            Slashlink::from_str("/apple").unwrap(),
+            Slashlink::from_str("@cdata/mango").unwrap(),
+        ];
+        sorted.sort();
+        assert_eq!(
+            sorted
+                .iter()
+                .map(|link| link.to_string())
+                .collect::<Vec<_>>(),
+            vec!["/apple", "/Zebra", "@cdata/mango"]
+        );
+    }
+
+    #[test]
+    fn it_compares_the_same_characters_that_normalize_prints() {
+        for link in [
+            "/Evolution//Notes/",
+            "@Cdata.Ben/Foo#Questions",
+            "@Cdata",
+            "@did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK/QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG",
+            "/bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e#top",
+            "/QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbd0",
+        ] {
+            let slashlink = Slashlink::from_str(link).unwrap();
+            assert_eq!(
+                slashlink.key().collect::<String>(),
+                slashlink.normalize().to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn it_converts_a_slashlink_entity() {
+        let slashlink = Slashlink::try_from(&Entity::SlashLink("@cdata/foo#bar".into())).unwrap();
+
+        assert_eq!(slashlink.peer, Peer::Name(vec!["cdata".into()]));
+        assert_eq!(slashlink.slug, Some("foo".into()));
+        assert_eq!(slashlink.fragment, Some("bar".into()));
+
+        assert!(Slashlink::try_from(&Entity::TextSpan("/foo".into())).is_err());
+    }
 }
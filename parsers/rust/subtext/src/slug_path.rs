@@ -0,0 +1,158 @@
+use anyhow::{anyhow, Result};
+use std::{collections::BTreeSet, fmt::Display, str::FromStr};
+
+use crate::{Peer, Slashlink};
+
+/// A hierarchical slug, such as `journal/2021-10-09`, as a list of
+/// segments. Each segment must match the spec's grammar for slashlink path
+/// parts: one or more ASCII letters, digits, dashes and underscores.
+///
+/// Paths order segment by segment, so every path under a prefix sorts
+/// directly after the prefix itself (see [SlugPath::descendants]).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SlugPath {
+    segments: Vec<String>,
+}
+
+impl SlugPath {
+    /// Create a path from its segments, checking that each one is valid
+    pub fn new<S>(segments: impl IntoIterator<Item = S>) -> Result<Self>
+    where
+        S: Into<String>,
+    {
+        let segments = segments
+            .into_iter()
+            .map(|segment| {
+                let segment = segment.into();
+                match SlugPath::is_valid_segment(&segment) {
+                    true => Ok(segment),
+                    false => Err(anyhow!("Invalid slug segment {:?}", segment)),
+                }
+            })
+            .collect::<Result<Vec<String>>>()?;
+
+        match segments.is_empty() {
+            true => Err(anyhow!("A slug path needs at least one segment")),
+            false => Ok(SlugPath { segments }),
+        }
+    }
+
+    /// Whether some text is a valid path segment on its own
+    pub fn is_valid_segment(segment: &str) -> bool {
+        !segment.is_empty()
+            && segment
+                .chars()
+                .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_')
+    }
+
+    pub fn segments(&self) -> &[String] {
+        &self.segments
+    }
+
+    /// The last segment of the path
+    pub fn name(&self) -> &str {
+        self.segments.last().map_or("", |segment| segment.as_str())
+    }
+
+    /// The path without its last segment, or `None` for a top-level path
+    pub fn parent(&self) -> Option<SlugPath> {
+        match self.segments.len() {
+            0 | 1 => None,
+            length => Some(SlugPath {
+                segments: self.segments[..length - 1].to_vec(),
+            }),
+        }
+    }
+
+    /// The path with one or more segments (`child` or `child/grandchild`)
+    /// added to the end
+    pub fn join(&self, child: &str) -> Result<SlugPath> {
+        let child = SlugPath::from_str(child)?;
+
+        Ok(SlugPath {
+            segments: [self.segments.clone(), child.segments].concat(),
+        })
+    }
+
+    /// Whether the path is the given prefix or lies under it, comparing
+    /// whole segments (`/journal` is not a prefix of `/journal-archive`)
+    pub fn starts_with(&self, prefix: &SlugPath) -> bool {
+        self.segments.starts_with(&prefix.segments)
+    }
+
+    /// Resolve a path written relative to this one, the way a relative URL
+    /// is resolved against the page it appears on: `sibling` and
+    /// `./sibling` replace the last segment, each leading `../` moves up a
+    /// level, and a path that starts with `/` is absolute.
+    pub fn resolve(&self, relative: &str) -> Result<SlugPath> {
+        if relative.starts_with('/') {
+            return SlugPath::from_str(relative);
+        }
+
+        let mut segments = self.segments[..self.segments.len() - 1].to_vec();
+        let mut rest = relative;
+
+        loop {
+            if let Some(after) = rest.strip_prefix("./") {
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("../") {
+                if segments.pop().is_none() {
+                    return Err(anyhow!("{} goes above the top level of {}", relative, self));
+                }
+                rest = after;
+            } else {
+                break;
+            }
+        }
+
+        match segments.is_empty() {
+            true => SlugPath::from_str(rest),
+            false => SlugPath { segments }.join(rest),
+        }
+    }
+
+    /// The paths in a set that lie under this one, not counting this path
+    /// itself
+    pub fn descendants<'a>(
+        &'a self,
+        paths: &'a BTreeSet<SlugPath>,
+    ) -> impl Iterator<Item = &'a SlugPath> {
+        paths
+            .range(self..)
+            .skip_while(move |path| *path == self)
+            .take_while(move |path| path.starts_with(self))
+    }
+
+    /// A slashlink to this path, without a peer
+    pub fn to_slashlink(&self) -> Slashlink {
+        Slashlink {
+            peer: Peer::None,
+            slug: Some(self.to_string()),
+            fragment: None,
+        }
+    }
+}
+
+/// Parse a path such as `journal/2021-10-09`, with or without a leading `/`
+impl FromStr for SlugPath {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SlugPath::new(s.strip_prefix('/').unwrap_or(s).split('/'))
+            .map_err(|error| anyhow!("Could not parse {} as a slug path: {}", s, error))
+    }
+}
+
+impl Display for SlugPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.segments.join("/"))
+    }
+}
+
+impl Slashlink {
+    /// The normalized slug of this slashlink as a path, if it has a slug and
+    /// every segment of it is valid
+    pub fn slug_path(&self) -> Option<SlugPath> {
+        self.normalize().slug?.parse().ok()
+    }
+}
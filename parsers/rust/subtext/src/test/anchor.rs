@@ -0,0 +1,73 @@
+use std::str::FromStr;
+
+use crate::{
+    anchor::{block_ids, resolve, Anchor},
+    block::Block,
+    parse,
+    primitive::Entity,
+    Slashlink,
+};
+
+const DOCUMENT: &str = r#"# Overview
+
+Evolution is a behavior that emerges in any system.
+
+# Questions
+
+- What systems exhibit evolutionary behavior?
+- Duplicate
+- Duplicate"#;
+
+fn blocks(input: &str) -> Vec<Block<Entity>> {
+    parse(input.as_bytes()).unwrap().collect()
+}
+
+#[test]
+fn it_assigns_ids_to_every_non_blank_block() {
+    let ids = block_ids(&blocks(DOCUMENT));
+
+    assert_eq!(ids.len(), 9);
+    assert!(ids[1].is_none());
+    assert!(ids[3].is_none());
+    assert_eq!(ids[0].as_ref().unwrap().as_str().len(), 6);
+}
+
+#[test]
+fn it_disambiguates_blocks_with_identical_content() {
+    let ids = block_ids(&blocks(DOCUMENT));
+    let first = ids[7].as_ref().unwrap().to_string();
+    let second = ids[8].as_ref().unwrap().to_string();
+
+    assert_eq!(second, format!("{}-2", first));
+}
+
+#[test]
+fn it_keeps_ids_stable_across_unrelated_edits() {
+    let before = block_ids(&blocks(DOCUMENT));
+    let edited = DOCUMENT.replace("# Overview\n", "# A new title\n\nAn inserted paragraph.\n");
+    let after = block_ids(&blocks(&edited));
+
+    assert_eq!(before[2], after[4]);
+    assert_eq!(before[6], after[8]);
+}
+
+#[test]
+fn it_resolves_a_block_id_fragment() {
+    let blocks = blocks(DOCUMENT);
+    let id = block_ids(&blocks)[2].clone().unwrap();
+    let slashlink = Slashlink::from_str(&format!("/evolution#{}", id)).unwrap();
+
+    assert_eq!(
+        resolve(&blocks, slashlink.fragment.as_ref().unwrap()),
+        Some(Anchor::Block(2))
+    );
+}
+
+#[test]
+fn it_resolves_a_section_fragment() {
+    let blocks = blocks(DOCUMENT);
+
+    assert_eq!(resolve(&blocks, "questions"), Some(Anchor::Section(4..9)));
+    assert_eq!(resolve(&blocks, "Overview"), Some(Anchor::Section(0..4)));
+    assert_eq!(resolve(&blocks, "missing"), None);
+}
@@ -45,3 +45,11 @@ fn it_converts_whitespace_to_bytes() {
 
     assert_round_trip(input);
 }
+
+#[test]
+fn it_converts_peer_links_to_bytes() {
+    let input = r#"Ask @cdata/foo or @did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK, and mail ben@example.com
+Trailing space after /foo "#;
+
+    assert_round_trip(input);
+}
@@ -0,0 +1,114 @@
+use crate::{
+    block::Block,
+    builder::Document,
+    lint::{lint, LintConfig},
+    parse,
+    primitive::Entity,
+};
+
+fn reparse(document: &Document) -> Vec<Block<Entity>> {
+    parse(&document.to_bytes()).unwrap().collect()
+}
+
+#[test]
+fn it_builds_a_document_that_reparses_identically() {
+    let document = Document::builder()
+        .header("Evolution")
+        .blank()
+        .paragraph(|p| {
+            p.text("Evolving systems exist in ")
+                .slashlink("punctuated-equilibrium")
+                .text(" and ")
+                .hyperlink("https://example.com")
+        })
+        .list(|l| l.wikilink("Mutation"))
+        .list(|l| l.text("Heredity"))
+        .quote(|q| q.text("There is only advantageous for the circumstances"))
+        .blank()
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        document.to_string(),
+        "# Evolution\n\nEvolving systems exist in /punctuated-equilibrium and https://example.com\n- [[Mutation]]\n- Heredity\n> There is only advantageous for the circumstances\n\n"
+    );
+    assert_eq!(reparse(&document), document.blocks());
+}
+
+#[test]
+fn it_rejects_a_paragraph_that_would_be_read_as_a_sigil_block() {
+    for text in ["# Not a header", "- Not a list", "> Not a quote"] {
+        assert!(Document::builder()
+            .paragraph(|p| p.text(text))
+            .build()
+            .is_err());
+    }
+}
+
+#[test]
+fn it_can_escape_a_paragraph_that_starts_with_a_sigil() {
+    let document = Document::builder()
+        .escape_sigils(true)
+        .paragraph(|p| p.text("# Not a header"))
+        .build()
+        .unwrap();
+
+    assert_eq!(document.to_string(), " # Not a header");
+    assert_eq!(reparse(&document), document.blocks());
+    assert_eq!(document.blocks()[0].to_text_content(), "# Not a header");
+}
+
+#[test]
+fn it_builds_documents_that_lint_clean() {
+    let document = Document::builder()
+        .escape_sigils(true)
+        .header("Evolution")
+        .paragraph(|p| p.text("# Not a header"))
+        .paragraph(|p| p.text("- Not a list item"))
+        .paragraph(|p| p.text("> Not a quote"))
+        .blank()
+        .list(|l| l.slashlink("mutation"))
+        .build()
+        .unwrap();
+
+    assert!(lint(&document.to_bytes(), &LintConfig::default())
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn it_rejects_content_that_would_be_misread() {
+    let cases = [
+        Document::builder().paragraph(|p| p.slashlink("foo bar")),
+        Document::builder().paragraph(|p| p.text("one\ntwo")),
+        Document::builder().list(|l| l.slashlink("foo").text(".")),
+        Document::builder().header("see https://example.com"),
+        Document::builder().quote(|q| q.hyperlink("ftp://example.com")),
+        Document::builder().paragraph(|p| p.wikilink("a]] b")),
+        Document::builder().paragraph(|p| p.peer_link("cdata/foo bar")),
+        Document::builder().paragraph(|p| p.text("mail").peer_link("cdata")),
+    ];
+
+    for builder in cases {
+        assert!(builder.build().is_err());
+    }
+}
+
+#[test]
+fn it_builds_peer_links_and_mentions() {
+    let document = Document::builder()
+        .paragraph(|p| {
+            p.text("Ask ")
+                .peer_link("@cdata")
+                .text(" about ")
+                .peer_link("did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK/evolution")
+        })
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        document.to_string(),
+        "Ask @cdata about @did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK/evolution"
+    );
+    assert_eq!(reparse(&document), document.blocks());
+}
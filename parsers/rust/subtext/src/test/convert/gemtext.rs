@@ -0,0 +1,151 @@
+use crate::{
+    block::Block,
+    convert::{from_gemtext, to_gemtext, Embed, GemtextOptions, LossKind},
+    lint::{lint, LintConfig},
+    parse,
+    primitive::Entity,
+    render::LinkTarget,
+};
+
+fn blocks(input: &str) -> Vec<Block<Entity>> {
+    parse(input.as_bytes()).unwrap().collect()
+}
+
+fn resolve(target: &LinkTarget) -> Option<String> {
+    match target {
+        LinkTarget::SlashLink(slashlink) => Some(format!("{}.gmi", &slashlink[1..])),
+        LinkTarget::WikiLink(_) => None,
+    }
+}
+
+#[test]
+fn it_exports_blocks_as_gemtext() {
+    let input = r#"# Evolution
+Evolution is a behavior, see /natural-selection
+
+- Mutation https://example.com/mutation
+- Heredity /heredity
+> There is no such thing as [[advantage]]
+ # Not a header"#;
+
+    assert_eq!(
+        to_gemtext(&blocks(input), &resolve),
+        r#"# Evolution
+Evolution is a behavior, see /natural-selection
+=> natural-selection.gmi /natural-selection
+
+* Mutation https://example.com/mutation
+* Heredity /heredity
+=> https://example.com/mutation
+=> heredity.gmi /heredity
+> There is no such thing as [[advantage]]
+ # Not a header
+"#
+    );
+}
+
+#[test]
+fn it_imports_gemtext() {
+    let input = r#"# Evolution
+## Mutation
+Some text
+=> https://example.com/mutation Mutation explained
+=> gemini://example.com/
+=> /notes/heredity.gmi Heredity
+
+* A list item
+>A quote
+- not a list"#;
+
+    let import = from_gemtext(input, &GemtextOptions::default()).unwrap();
+
+    assert_eq!(
+        import.to_subtext(),
+        r#"# Evolution
+# Mutation
+Some text
+Mutation explained https://example.com/mutation
+gemini://example.com/
+Heredity /notes/heredity
+
+- A list item
+> A quote
+ - not a list
+"#
+    );
+    assert_eq!(
+        import
+            .losses
+            .iter()
+            .map(|loss| loss.kind)
+            .collect::<Vec<LossKind>>(),
+        vec![LossKind::HeadingLevel, LossKind::Link]
+    );
+
+    match import.blocks.get(3) {
+        Some(Block::Paragraph(entities)) => {
+            assert!(matches!(entities.last(), Some(Entity::HyperLink(_))))
+        }
+        _ => panic!("Expected a paragraph with a hyperlink"),
+    }
+}
+
+#[test]
+fn it_reports_links_to_gemini_capsules() {
+    let import = from_gemtext(
+        "=> gemini://example.org/foo.gmi A page\n=> https://example.org/ Web",
+        &GemtextOptions::default(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        import.to_subtext(),
+        "A page gemini://example.org/foo.gmi\nWeb https://example.org/\n"
+    );
+    assert_eq!(import.losses.len(), 1);
+    assert_eq!(import.losses[0].kind, LossKind::Link);
+    assert!(import.losses[0]
+        .detail
+        .contains("gemini://example.org/foo.gmi"));
+}
+
+#[test]
+fn it_keeps_preformatted_lines_as_paragraphs() {
+    let input = "Before\n```ascii art\n  /\\_/\\\n\n # o.o\n```\nAfter";
+    let import = from_gemtext(input, &GemtextOptions::default()).unwrap();
+
+    assert_eq!(import.to_subtext(), "Before\n/\\_/\\\n\n # o.o\nAfter\n");
+    assert_eq!(import.losses.len(), 1);
+    assert_eq!(import.losses[0].kind, LossKind::CodeBlock);
+
+    // An escaped sigil is not mistaken for a stray indent
+    let import = from_gemtext("```\n# o.o\n```", &GemtextOptions::default()).unwrap();
+    let input = import.to_subtext();
+
+    assert_eq!(input, " # o.o\n");
+    assert!(lint(input.as_bytes(), &LintConfig::default())
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn it_extracts_preformatted_blocks_to_linked_files() {
+    let input = "Before\n```ascii art\n  /\\_/\\\n # o.o\n```\nAfter\n```\nunterminated";
+    let import = from_gemtext(
+        input,
+        &GemtextOptions {
+            preformatted: Embed::Extract,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        import.to_subtext(),
+        "Before\nascii art /ascii-art\nAfter\n/preformatted\n"
+    );
+    assert!(import.losses.is_empty());
+    assert_eq!(import.files.len(), 2);
+    assert_eq!(import.files[0].file_name(), "ascii-art.txt");
+    assert_eq!(import.files[0].content, "  /\\_/\\\n # o.o\n");
+    assert_eq!(import.files[1].content, "unterminated\n");
+}
@@ -0,0 +1,174 @@
+use crate::convert::{from_html, HtmlImportOptions, LinkStyle, LossKind};
+
+fn convert(input: &str) -> String {
+    from_html(input, &HtmlImportOptions::default())
+        .unwrap()
+        .to_subtext()
+}
+
+#[test]
+fn it_converts_a_clipped_page() {
+    let input = r#"<!DOCTYPE html>
+<html>
+<head>
+  <title>Evolution</title>
+  <style>p { color: red; }</style>
+</head>
+<body>
+  <h1>Evolution</h1>
+  <p>Evolution is a <em>behavior</em>,
+     see <a href="https://example.com/selection">natural   selection</a>.</p>
+  <script>if (a < b) { document.write("<p>Hidden</p>"); }</script>
+  <ul>
+    <li>Mutation
+    <li>Heredity<br>and variation
+  </ul>
+  <blockquote><p>There is only advantageous for the circumstances</p></blockquote>
+  <p># Not a header &amp; not a list</p>
+</body>
+</html>"#;
+
+    assert_eq!(
+        convert(input),
+        r#"# Evolution
+
+Evolution is a behavior, see natural selection https://example.com/selection .
+
+- Mutation
+- Heredity
+- and variation
+
+> There is only advantageous for the circumstances
+
+ # Not a header & not a list
+"#
+    );
+}
+
+#[test]
+fn it_writes_links_in_the_chosen_style() {
+    let input = r##"<p><a href="https://example.com">https://example.com</a>
+<a href="https://example.com/a">A</a> <a href="notes/b.html">B</a>
+<a href="#top">Top</a></p>"##;
+
+    let import = from_html(
+        input,
+        &HtmlImportOptions {
+            link_style: LinkStyle::Bracket,
+            base_url: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        import.to_subtext(),
+        "<https://example.com> A <https://example.com/a> B /notes/b Top\n"
+    );
+    assert_eq!(import.losses.len(), 1);
+    assert_eq!(import.losses[0].kind, LossKind::Link);
+}
+
+#[test]
+fn it_keeps_punctuation_after_a_relative_link_out_of_the_slashlink() {
+    let import = from_html(
+        r#"<p>see <a href="foo.html">x</a>.</p>"#,
+        &HtmlImportOptions::default(),
+    )
+    .unwrap();
+
+    assert_eq!(import.to_subtext(), "see x /foo .\n");
+    assert!(import.losses.is_empty());
+}
+
+#[test]
+fn it_resolves_relative_links_against_the_base_url() {
+    let input = r##"<p><a href="/about">About</a> <a href="b.html">B</a>
+<a href="//cdn.example.com/c">C</a> <a href="#top">Top</a></p>"##;
+
+    let import = from_html(
+        input,
+        &HtmlImportOptions {
+            link_style: LinkStyle::Bare,
+            base_url: Some("https://example.com/notes/a.html?x=1".into()),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        import.to_subtext(),
+        "About https://example.com/about B https://example.com/notes/b.html C https://cdn.example.com/c Top https://example.com/notes/a.html?x=1#top\n"
+    );
+    assert!(import.losses.is_empty());
+}
+
+#[test]
+fn it_reports_what_it_flattens() {
+    let input = r#"<h2>Details</h2>
+<ol><li>One<ul><li>Nested</li></ul></li></ol>
+<hr>
+<table><tr><th>Name</th><th>Kind</th></tr><tr><td>Finch</td><td>Bird</td></tr></table>
+<pre>
+fn main() {
+
+    println!("Hello");
+}
+</pre>
+<p><img src="finch.png" alt="A finch"></p>"#;
+
+    let import = from_html(input, &HtmlImportOptions::default()).unwrap();
+
+    assert_eq!(
+        import.to_subtext(),
+        r#"# Details
+
+- One
+- Nested
+
+- Name | Kind
+- Finch | Bird
+
+fn main() {
+
+println!("Hello");
+}
+
+A finch finch.png
+"#
+    );
+    assert_eq!(
+        import
+            .losses
+            .iter()
+            .map(|loss| loss.kind)
+            .collect::<Vec<LossKind>>(),
+        vec![
+            LossKind::HeadingLevel,
+            LossKind::ThematicBreak,
+            LossKind::Table,
+            LossKind::CodeBlock,
+            LossKind::Image,
+            // The relative image URL is not a link in Subtext
+            LossKind::Link,
+            LossKind::NestedList,
+        ]
+    );
+}
+
+#[test]
+fn it_reads_fragments_and_malformed_html() {
+    assert_eq!(
+        convert("Loose <b>text</b><p>Unclosed<p>Second</div>"),
+        "Loose text\n\nUnclosed\n\nSecond\n"
+    );
+    assert_eq!(convert(""), "");
+}
+
+#[test]
+fn it_reads_a_stray_angle_bracket_as_text() {
+    assert_eq!(
+        convert("<p>if 1<2 then</p><p>ok</p>"),
+        "if 1<2 then\n\nok\n"
+    );
+    assert_eq!(convert("<p>x a<b</p>"), "x a<b\n");
+    assert_eq!(convert("<p>a < b and c</ p>d</p>"), "a < b and c</ p>d\n");
+}
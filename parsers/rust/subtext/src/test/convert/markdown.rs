@@ -0,0 +1,233 @@
+use crate::convert::{from_markdown, markdown::LinkStyle, Embed, LossKind, MarkdownOptions};
+
+fn convert(input: &str, options: &MarkdownOptions) -> (String, Vec<LossKind>) {
+    let import = from_markdown(input, options).unwrap();
+    let losses = import.losses.iter().map(|loss| loss.kind).collect();
+
+    (import.to_subtext(), losses)
+}
+
+#[test]
+fn it_maps_every_heading_level_to_a_header() {
+    let (subtext, losses) = convert(
+        "# Evolution\n\nSome text.\n\n### Mutation\n\nMore text.",
+        &MarkdownOptions::default(),
+    );
+
+    assert_eq!(
+        subtext,
+        "# Evolution\n\nSome text.\n\n# Mutation\n\nMore text.\n"
+    );
+    assert_eq!(losses, vec![LossKind::HeadingLevel]);
+}
+
+#[test]
+fn it_joins_soft_breaks_and_splits_hard_breaks() {
+    let (subtext, _) = convert(
+        "One line\ncontinues here.  \nA new line.",
+        &MarkdownOptions::default(),
+    );
+
+    assert_eq!(subtext, "One line continues here.\nA new line.\n");
+}
+
+#[test]
+fn it_keeps_paragraphs_that_start_with_a_sigil_as_paragraphs() {
+    let (subtext, _) = convert("\\# not a header", &MarkdownOptions::default());
+
+    assert_eq!(subtext, " # not a header\n");
+}
+
+#[test]
+fn it_flattens_nested_lists() {
+    let (subtext, losses) = convert(
+        "- Mutation\n  - Point\n    - Silent\n- Heredity\n\n> A quote\n> > nested",
+        &MarkdownOptions::default(),
+    );
+
+    assert_eq!(
+        subtext,
+        "- Mutation\n- Point\n- Silent\n- Heredity\n\n> A quote\n> nested\n"
+    );
+    assert_eq!(losses, vec![LossKind::NestedQuote, LossKind::NestedList]);
+}
+
+#[test]
+fn it_keeps_lists_inside_a_quote_quoted_and_in_order() {
+    let (subtext, losses) = convert(
+        "> quoted\n>\n> - item one\n> - item two\n\nafter",
+        &MarkdownOptions::default(),
+    );
+
+    assert_eq!(subtext, "> quoted\n> - item one\n> - item two\n\nafter\n");
+    assert_eq!(losses, vec![LossKind::QuotedBlock]);
+}
+
+#[test]
+fn it_keeps_headings_and_code_inside_a_quote_quoted_and_in_order() {
+    let (subtext, losses) = convert("> body\n>\n> # Title", &MarkdownOptions::default());
+
+    assert_eq!(subtext, "> body\n> # Title\n");
+    assert_eq!(losses, vec![LossKind::QuotedBlock]);
+
+    let (subtext, losses) = convert(
+        "> ## Title\n>\n> ```\n> let a = 1;\n> ```\n>\n> body",
+        &MarkdownOptions::default(),
+    );
+
+    assert_eq!(subtext, "> # Title\n> let a = 1;\n> body\n");
+    assert_eq!(
+        losses,
+        vec![
+            LossKind::QuotedBlock,
+            LossKind::HeadingLevel,
+            LossKind::QuotedBlock,
+            LossKind::CodeBlock,
+        ]
+    );
+}
+
+#[test]
+fn it_factors_deep_list_subtrees_into_linked_notes() {
+    let import = from_markdown(
+        "- Mutation\n  - Point\n    - Silent\n- Heredity",
+        &MarkdownOptions {
+            max_list_depth: Some(1),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert_eq!(import.to_subtext(), "- Mutation /mutation\n- Heredity\n");
+    assert!(import.losses.is_empty());
+
+    let files = import
+        .files
+        .iter()
+        .map(|file| (file.file_name(), file.content.as_str()))
+        .collect::<Vec<(String, &str)>>();
+    assert_eq!(
+        files,
+        vec![
+            ("point.subtext".into(), "- Silent\n"),
+            ("mutation.subtext".into(), "- Point /point\n"),
+        ]
+    );
+}
+
+#[test]
+fn it_converts_links() {
+    let input = "See [the spec](https://subtext.pub/spec) and [notes](../notes/Evolution.md#top), \
+        <https://example.com> or [this section](#intro).";
+
+    let (subtext, losses) = convert(input, &MarkdownOptions::default());
+    assert_eq!(
+        subtext,
+        "See the spec https://subtext.pub/spec and notes /notes/evolution , \
+        https://example.com or this section.\n"
+    );
+    assert_eq!(losses, vec![LossKind::Link]);
+
+    let (subtext, _) = convert(
+        input,
+        &MarkdownOptions {
+            link_style: LinkStyle::Bracket,
+            ..Default::default()
+        },
+    );
+    assert!(subtext.starts_with("See the spec <https://subtext.pub/spec> and"));
+    assert!(subtext.contains(", <https://example.com> or"));
+}
+
+#[test]
+fn it_keeps_punctuation_after_a_link_out_of_the_url() {
+    let (subtext, losses) = convert(
+        "Read [the docs](https://example.com). Or [mail me](mailto:a@b.com).",
+        &MarkdownOptions::default(),
+    );
+
+    assert_eq!(
+        subtext,
+        "Read the docs https://example.com . Or mail me mailto:a@b.com .\n"
+    );
+    assert_eq!(losses, vec![LossKind::Link]);
+
+    let (subtext, losses) = convert("see [x](foo.md).", &MarkdownOptions::default());
+
+    assert_eq!(subtext, "see x /foo .\n");
+    assert!(losses.is_empty());
+}
+
+#[test]
+fn it_keeps_blocks_inside_list_items_in_order() {
+    let (subtext, _) = convert(
+        "- One\n\n  ## Inside\n\n  ```\n  code\n  ```\n\n  - Nested\n\n  | a |\n  |---|\n  | 1 |\n\n- Two",
+        &MarkdownOptions::default(),
+    );
+
+    assert_eq!(
+        subtext,
+        "- One\n# Inside\ncode\n- Nested\n- a\n- 1\n- Two\n"
+    );
+}
+
+#[test]
+fn it_reports_formatting_once_per_kind() {
+    let (subtext, losses) = convert(
+        "Some *emphasis*, *more* and `code` with ![a diagram](https://example.com/d.png)",
+        &MarkdownOptions::default(),
+    );
+
+    assert_eq!(
+        subtext,
+        "Some emphasis, more and code with a diagram https://example.com/d.png\n"
+    );
+    assert_eq!(
+        losses,
+        vec![LossKind::Formatting, LossKind::Formatting, LossKind::Image]
+    );
+}
+
+#[test]
+fn it_keeps_code_blocks_and_tables_as_text() {
+    let input = "```rust\nfn main() {\n\n    // -- done\n}\n```\n\n| a | b |\n|---|---|\n| 1 | 2 |";
+    let (subtext, losses) = convert(input, &MarkdownOptions::default());
+
+    assert_eq!(
+        subtext,
+        "fn main() {\n\n// -- done\n}\n\n- a | b\n- 1 | 2\n"
+    );
+    assert_eq!(losses, vec![LossKind::CodeBlock, LossKind::Table]);
+}
+
+#[test]
+fn it_extracts_code_blocks_and_tables_to_linked_files() {
+    let input =
+        "```rust\nfn main() {}\n```\n\n| a | b, c |\n|---|---|\n| 1 | 2 |\n\n```\nmore\n```";
+    let import = from_markdown(
+        input,
+        &MarkdownOptions {
+            code_blocks: Embed::Extract,
+            tables: Embed::Extract,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert_eq!(import.to_subtext(), "/code\n\n/table\n\n/code-2\n");
+    assert!(import.losses.is_empty());
+
+    let files = import
+        .files
+        .iter()
+        .map(|file| (file.file_name(), file.content.as_str()))
+        .collect::<Vec<(String, &str)>>();
+    assert_eq!(
+        files,
+        vec![
+            ("code.rs".into(), "fn main() {}\n"),
+            ("table.csv".into(), "a,\"b, c\"\n1,2\n"),
+            ("code-2.txt".into(), "more\n"),
+        ]
+    );
+}
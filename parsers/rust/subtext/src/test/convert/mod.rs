@@ -0,0 +1,6 @@
+mod gemtext;
+mod html;
+#[cfg(feature = "markdown")]
+mod markdown;
+mod opml;
+mod org;
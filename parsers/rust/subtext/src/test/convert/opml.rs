@@ -0,0 +1,220 @@
+use crate::{
+    block::Block,
+    convert::{from_opml, to_opml, ImportedFile, LossKind, OpmlOptions},
+    parse,
+    primitive::Entity,
+    render::LinkTarget,
+};
+
+fn blocks(input: &str) -> Vec<Block<Entity>> {
+    parse(input.as_bytes()).unwrap().collect()
+}
+
+fn resolve(target: &LinkTarget) -> Option<String> {
+    match target {
+        LinkTarget::SlashLink(slashlink) => Some(format!("{}.html", &slashlink[1..])),
+        LinkTarget::WikiLink(_) => None,
+    }
+}
+
+const OUTLINE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- Exported from an outliner -->
+<opml version="2.0">
+  <head>
+    <title>Evolution &amp; change</title>
+  </head>
+  <body>
+    <outline text="Inheritance">
+      <outline text="Genes" _note="Units of heredity">
+        <outline text="Alleles">
+          <outline text="Dominance"/>
+        </outline>
+      </outline>
+      <outline text="Epigenetics"/>
+    </outline>
+    <outline text="Further reading" type="link" url="https://example.com/reading"/>
+    <outline text="Selection">
+      <outline text="Natural" url="natural-selection.opml"/>
+    </outline>
+  </body>
+</opml>"#;
+
+#[test]
+fn it_exports_sections_and_lists_as_outlines() {
+    let input = r#"Evolution is change over time
+- Mutation
+- Heredity
+
+# Selection
+See /natural-selection
+> Only [[advantage]] & <fitness>
+# Empty"#;
+
+    assert_eq!(
+        to_opml(&blocks(input), "Evolution", &resolve),
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<opml version="2.0">
+  <head>
+    <title>Evolution</title>
+  </head>
+  <body>
+    <outline text="Evolution is change over time">
+      <outline text="Mutation"/>
+      <outline text="Heredity"/>
+    </outline>
+    <outline text="Selection">
+      <outline text="See /natural-selection" type="link" url="natural-selection.html"/>
+      <outline text="Only [[advantage]] &amp; &lt;fitness&gt;"/>
+    </outline>
+    <outline text="Empty"/>
+  </body>
+</opml>
+"#
+    );
+}
+
+#[test]
+fn it_imports_an_outline_as_headers_and_lists() {
+    let import = from_opml(OUTLINE, &OpmlOptions::default()).unwrap();
+
+    assert_eq!(
+        import.to_subtext(),
+        r#"# Evolution & change
+
+# Inheritance
+- Genes
+> Units of heredity
+- Alleles
+- Dominance
+- Epigenetics
+
+- Further reading https://example.com/reading
+
+# Selection
+- Natural /natural-selection
+"#
+    );
+    assert!(import.files.is_empty());
+    assert_eq!(import.losses.len(), 1);
+    assert_eq!(import.losses[0].kind, LossKind::NestedList);
+    assert_eq!(import.losses[0].detail, "2 nested outlines were flattened");
+}
+
+#[test]
+fn it_splits_outlines_below_the_cutoff_into_notes() {
+    let options = OpmlOptions { max_depth: Some(2) };
+    let import = from_opml(OUTLINE, &options).unwrap();
+
+    assert_eq!(
+        import.to_subtext(),
+        r#"# Evolution & change
+
+# Inheritance
+- Genes /genes
+> Units of heredity
+- Epigenetics
+
+- Further reading https://example.com/reading
+
+# Selection
+- Natural /natural-selection
+"#
+    );
+    assert_eq!(
+        import.files,
+        vec![ImportedFile {
+            slug: "genes".into(),
+            extension: "subtext".into(),
+            content: "# Alleles\n- Dominance\n".into(),
+        }]
+    );
+    assert!(import.losses.is_empty());
+}
+
+#[test]
+fn it_splits_every_level_into_a_note_at_depth_one() {
+    let input = r#"<opml version="1.0"><body>
+<outline text="A"><outline text="B"><outline text="C"/></outline></outline>
+</body></opml>"#;
+    let import = from_opml(input, &OpmlOptions { max_depth: Some(1) }).unwrap();
+
+    assert_eq!(import.to_subtext(), "- A /a\n");
+    assert_eq!(
+        import
+            .files
+            .iter()
+            .map(|file| (file.file_name(), file.content.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("b.subtext".into(), "- C\n"),
+            ("a.subtext".into(), "- B /b\n")
+        ]
+    );
+}
+
+#[test]
+fn it_round_trips_an_exported_document() {
+    let input = "# Selection\n- Natural\n- Sexual\n# Drift\nSmall populations";
+    let opml = to_opml(&blocks(input), "", &resolve);
+
+    assert_eq!(
+        from_opml(&opml, &OpmlOptions::default())
+            .unwrap()
+            .to_subtext(),
+        "# Selection\n- Natural\n- Sexual\n\n# Drift\n- Small populations\n"
+    );
+}
+
+#[test]
+fn it_escapes_exported_text_as_xml() {
+    let input = "Tom's <notes> & \"quotes\"";
+    let opml = to_opml(&blocks(input), "", &resolve);
+
+    assert!(opml.contains("<outline text=\"Tom&apos;s &lt;notes&gt; &amp; &quot;quotes&quot;\"/>"));
+    assert_eq!(
+        from_opml(&opml, &OpmlOptions::default())
+            .unwrap()
+            .to_subtext(),
+        format!("- {}\n", input)
+    );
+}
+
+#[test]
+fn it_reports_links_that_subtext_cannot_express() {
+    let input = r#"<opml version="2.0"><body>
+<outline text="Links">
+  <outline text="Mail" type="link" url="mailto:a@b.com"/>
+  <outline text="Site" type="link" url="https://example.com"/>
+</outline>
+</body></opml>"#;
+    let import = from_opml(input, &OpmlOptions::default()).unwrap();
+
+    assert_eq!(
+        import.to_subtext(),
+        "# Links\n- Mail mailto:a@b.com\n- Site https://example.com\n"
+    );
+    assert_eq!(
+        import
+            .losses
+            .iter()
+            .map(|loss| loss.kind)
+            .collect::<Vec<LossKind>>(),
+        vec![LossKind::Link]
+    );
+}
+
+#[test]
+fn it_rejects_documents_that_are_not_opml() {
+    for input in [
+        "<opml><body><outline text=\"A\"></body></opml>",
+        "<html><body></body></html>",
+        "<opml version=\"2.0\"><head/></opml>",
+        "<opml><body><outline text=\"A/></body></opml>",
+    ] {
+        assert!(
+            from_opml(input, &OpmlOptions::default()).is_err(),
+            "{}",
+            input
+        );
+    }
+}
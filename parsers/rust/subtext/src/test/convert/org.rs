@@ -0,0 +1,156 @@
+use crate::{
+    block::Block,
+    convert::{from_org, org_file_link, to_org, Embed, LossKind, OrgOptions},
+    parse,
+    primitive::Entity,
+    render::LinkTarget,
+};
+
+fn blocks(input: &str) -> Vec<Block<Entity>> {
+    parse(input.as_bytes()).unwrap().collect()
+}
+
+fn loss_kinds(losses: &[crate::convert::Loss]) -> Vec<LossKind> {
+    losses.iter().map(|loss| loss.kind).collect()
+}
+
+#[test]
+fn it_exports_blocks_as_org() {
+    let input = r#"# Evolution
+Evolution is a behavior, see /natural-selection
+- Mutation https://example.com/mutation
+- Heredity [[Genes]]
+> There is no such thing as advantageous
+> *in a general sense*
+
+ # Not a header
+ - Not a list item"#;
+
+    assert_eq!(
+        to_org(&blocks(input), &org_file_link),
+        "* Evolution
+Evolution is a behavior, see [[file:natural-selection.org]]
+- Mutation [[https://example.com/mutation]]
+- Heredity [[Genes]]
+#+BEGIN_QUOTE
+There is no such thing as advantageous
+,*in a general sense*
+#+END_QUOTE
+
+\u{200B}# Not a header
+\u{200B}- Not a list item
+"
+    );
+}
+
+#[test]
+fn it_imports_org() {
+    let input = r#"#+TITLE: Evolution
+#+AUTHOR: Someone
+* Mutation
+:PROPERTIES:
+:ID: 1234
+:END:
+Read [[https://example.com/mutation][this article]] and [[file:notes/heredity.org]].
+** Heredity
+- See [[Natural selection]]
+  + [[id:1234][a nested item]]
+1. Ordered
+
+#+begin_quote
+There is no such thing as advantageous
+,* in a general sense
+#+end_quote
+# A comment"#;
+
+    let import = from_org(input, &OrgOptions::default()).unwrap();
+
+    assert_eq!(
+        import.to_subtext(),
+        r#"# Evolution
+# Mutation
+Read this article https://example.com/mutation and /notes/heredity .
+# Heredity
+- See [[Natural selection]]
+- a nested item
+- Ordered
+
+> There is no such thing as advantageous
+> * in a general sense
+"#
+    );
+    assert_eq!(
+        loss_kinds(&import.losses),
+        vec![
+            LossKind::Metadata,
+            LossKind::Metadata,
+            LossKind::HeadingLevel,
+            LossKind::Link,
+            LossKind::Metadata,
+            LossKind::NestedList,
+        ]
+    );
+
+    match import.blocks.get(2) {
+        Some(Block::Paragraph(entities)) => {
+            assert!(entities
+                .iter()
+                .any(|entity| matches!(entity, Entity::HyperLink(_))));
+            assert!(entities
+                .iter()
+                .any(|entity| matches!(entity, Entity::SlashLink(_))));
+        }
+        _ => panic!("Expected a paragraph with links"),
+    }
+}
+
+#[test]
+fn it_leaves_links_to_other_peers_as_text() {
+    assert_eq!(org_file_link(&LinkTarget::SlashLink("@cdata/foo")), None);
+    assert_eq!(
+        to_org(&blocks("Ask @cdata/foo or /bar"), &org_file_link),
+        "Ask @cdata/foo or [[file:bar.org]]\n"
+    );
+}
+
+#[test]
+fn it_round_trips_through_org() {
+    let input =
+        "# Evolution\nSee /natural-selection and [[Genes]]\n\n - Not a list item\n> Quoted\n";
+    let org = to_org(&blocks(input), &org_file_link);
+    let import = from_org(&org, &OrgOptions::default()).unwrap();
+
+    assert_eq!(import.to_subtext(), input);
+    assert!(import.losses.is_empty());
+}
+
+#[test]
+fn it_keeps_or_extracts_source_blocks_and_tables() {
+    let input = "#+BEGIN_SRC emacs-lisp\n(message \"hi\")\n,* not a headline\n#+END_SRC\n| a | b |\n|---+---|\n| 1 | 2 |";
+
+    let import = from_org(input, &OrgOptions::default()).unwrap();
+    assert_eq!(
+        import.to_subtext(),
+        "(message \"hi\")\n* not a headline\n- a | b\n- 1 | 2\n"
+    );
+    assert_eq!(
+        loss_kinds(&import.losses),
+        vec![LossKind::CodeBlock, LossKind::Table]
+    );
+
+    let import = from_org(
+        input,
+        &OrgOptions {
+            code_blocks: Embed::Extract,
+            tables: Embed::Extract,
+        },
+    )
+    .unwrap();
+    assert_eq!(import.to_subtext(), "/code\n/table\n");
+    assert_eq!(import.files[0].file_name(), "code.el");
+    assert_eq!(
+        import.files[0].content,
+        "(message \"hi\")\n* not a headline\n"
+    );
+    assert_eq!(import.files[1].content, "a,b\n1,2\n");
+}
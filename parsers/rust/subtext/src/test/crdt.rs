@@ -0,0 +1,114 @@
+use crate::{
+    block::Block,
+    crdt::{Operation, Replica},
+    primitive::Entity,
+};
+
+#[test]
+fn it_loads_and_materializes_a_document() {
+    let input = "# Hello\n\n- One\n- Two";
+    let replica = Replica::load(1, input.as_bytes()).unwrap();
+
+    assert_eq!(replica.len(), 4);
+    assert_eq!(replica.to_string(), input);
+
+    let blocks: Vec<Block<Entity>> = replica.to_blocks().unwrap();
+
+    match blocks.as_slice() {
+        [Block::Header(_), Block::Blank(_), Block::List(one), Block::List(two)] => {
+            assert_eq!(one.get(2).unwrap().to_string(), "One");
+            assert_eq!(two.get(2).unwrap().to_string(), "Two");
+        }
+        _ => panic!("Unexpected block(s): {:#?}", blocks),
+    }
+}
+
+#[test]
+fn it_converges_when_replicas_edit_concurrently() {
+    let origin = Replica::load(1, b"# Notes\nShared text").unwrap();
+    let mut alice = origin.fork(2);
+    let mut bob = origin.fork(3);
+    let mut carol = origin.fork(4);
+
+    alice.insert_block(1, "- From alice").unwrap();
+    bob.insert_block(1, "- From bob").unwrap();
+    bob.insert_text(2, 6, " more").unwrap();
+    carol.delete_text(0, 0, 2).unwrap();
+    carol.insert_text(0, 0, "> ").unwrap();
+
+    let mut alice_view = alice.clone();
+    alice_view.merge(&carol);
+    alice_view.merge(&bob);
+
+    let mut bob_view = bob.clone();
+    bob_view.merge(&alice);
+    bob_view.merge(&carol);
+
+    let mut carol_view = carol.clone();
+    carol_view.merge(&bob);
+    carol_view.merge(&alice);
+
+    assert_eq!(alice_view.to_string(), bob_view.to_string());
+    assert_eq!(bob_view.to_string(), carol_view.to_string());
+    assert_eq!(
+        carol_view.to_string(),
+        "> Notes\n- From bob\n- From alice\nShared more text"
+    );
+}
+
+#[test]
+fn it_applies_operations_idempotently() {
+    let mut alice = Replica::load(1, b"Hello").unwrap();
+    let mut bob = alice.fork(2);
+
+    alice.insert_text(0, 5, ", world").unwrap();
+    bob.merge(&alice);
+    bob.merge(&alice);
+
+    assert_eq!(bob.to_string(), "Hello, world");
+}
+
+#[test]
+fn it_holds_back_operations_until_their_dependencies_arrive() {
+    let mut alice = Replica::new(1);
+    let mut operations = alice.insert_block(0, "Hello").unwrap();
+    operations.append(&mut alice.delete_text(0, 0, 1).unwrap());
+    operations.reverse();
+
+    let mut bob = Replica::new(2);
+    for operation in operations {
+        bob.apply(operation);
+    }
+
+    assert_eq!(bob.to_string(), "ello");
+}
+
+#[test]
+fn it_round_trips_serialized_operations() {
+    let mut alice = Replica::new(1);
+    alice.insert_block(0, "- A list item ✨").unwrap();
+    alice.insert_block(1, "").unwrap();
+    alice.delete_block(1).unwrap();
+    alice.delete_text(0, 2, 2).unwrap();
+
+    let encoded = Operation::encode_all(alice.operations());
+    let decoded = Operation::decode_all(&encoded).unwrap();
+
+    assert_eq!(decoded, alice.operations());
+
+    let mut bob = Replica::new(2);
+    for operation in decoded {
+        bob.apply(operation);
+    }
+
+    assert_eq!(bob.to_string(), "- list item ✨");
+}
+
+#[test]
+fn it_rejects_text_that_would_split_a_block() {
+    let mut replica = Replica::load(1, b"Hello").unwrap();
+
+    assert!(replica.insert_text(0, 5, "\nWorld").is_err());
+    assert!(replica.insert_block(1, "Hello\r\nWorld").is_err());
+    assert!(replica.insert_block(3, "Out of bounds").is_err());
+}
@@ -0,0 +1,95 @@
+use std::str::FromStr;
+
+use crate::did::{Did, KeyType, PublicKey};
+
+#[test]
+fn it_parses_a_did_into_method_and_identifier() {
+    let did = Did::from_str("did:web:example.com:user:alice").unwrap();
+
+    assert_eq!(did.method, "web");
+    assert_eq!(did.id, "example.com:user:alice");
+    assert_eq!(did.to_string(), "did:web:example.com:user:alice");
+    assert_eq!(did.public_key(), None);
+
+    let did = Did::from_str("did:example:a::b%2Fc").unwrap();
+    assert_eq!(did.id, "a::b%2Fc");
+}
+
+#[test]
+fn it_rejects_dids_that_break_the_generic_syntax() {
+    for (input, message) in [
+        ("did:", "must have a method and an identifier"),
+        ("did:web", "must have a method and an identifier"),
+        ("key:z6Mk", "must start with did:"),
+        (":web:example.com", "must start with did:"),
+        ("did::example.com", "lowercase letters and digits"),
+        ("did:Web:example.com", "lowercase letters and digits"),
+        ("did:web:", "cannot be empty"),
+        ("did:web:example.com:", "cannot be empty"),
+        ("did:web:example com", "Invalid character ' '"),
+        ("did:web:example.com/foo", "Invalid character '/'"),
+        ("did:web:a%2", "Invalid percent-encoding %2"),
+        ("did:web:a%zz", "Invalid percent-encoding %zz"),
+    ] {
+        let error = Did::from_str(input).unwrap_err().to_string();
+        assert!(error.contains(message), "{}: {}", input, error);
+    }
+}
+
+#[test]
+fn it_decodes_the_public_key_of_a_did_key() {
+    for (input, key_type) in [
+        (
+            "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK",
+            KeyType::Ed25519,
+        ),
+        (
+            "did:key:z6LSeu9HkTHSfLLeUs2nnzUSNedgDUevfNQgQjQC23ZCit6F",
+            KeyType::X25519,
+        ),
+        (
+            "did:key:zQ3shokFTS3brHcDQrn82RUDfCZESWL1ZdCEJwekUDPQiYBme",
+            KeyType::Secp256k1,
+        ),
+        (
+            "did:key:zDnaerDaTF5BXEavCrfRZEk316dpbLsfPDZ3WJ5hRTPFU2169",
+            KeyType::P256,
+        ),
+    ] {
+        let did = Did::from_str(input).unwrap();
+        let key = did.public_key().unwrap();
+
+        assert_eq!(key.key_type, key_type, "{}", input);
+        assert_eq!(Some(key.bytes.len()), key_type.key_length());
+        assert_eq!(Did::from_public_key(&key), did);
+    }
+}
+
+#[test]
+fn it_rejects_a_did_key_that_is_not_a_valid_key() {
+    for (input, message) in [
+        ("did:key:z6MkAlice", "Invalid base58 character 'l'"),
+        ("did:key:z6Mk", "Unsupported key type 0x46"),
+        ("did:key:bafkreiabc", "must be base58btc"),
+        (
+            "did:key:z3D",
+            "Unexpected end of input while reading key type",
+        ),
+    ] {
+        let error = Did::from_str(input).unwrap_err().to_string();
+        assert!(error.contains(message), "{}: {}", input, error);
+    }
+
+    let key = PublicKey {
+        key_type: KeyType::Ed25519,
+        bytes: vec![1; 32],
+    };
+    assert_eq!(PublicKey::from_multibase(&key.to_multibase()).unwrap(), key);
+
+    let too_long = PublicKey {
+        bytes: vec![1; 33],
+        ..key
+    };
+    let error = PublicKey::from_multibase(&too_long.to_multibase()).unwrap_err();
+    assert_eq!(error.to_string(), "Ed25519 keys cannot be 33 bytes long");
+}
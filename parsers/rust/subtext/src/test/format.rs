@@ -0,0 +1,117 @@
+use crate::{
+    block::Block,
+    format::{format, FormatOptions},
+    parse,
+    primitive::Entity,
+};
+
+const MESSY: &str = "#Heading  \r\n\r\n\r\n\r\n-   One\r\n-\tTwo\n>quoted   \n  \n\nText with /link \t\n   Indented paragraph\n\n\n";
+
+/// The non-blank blocks of a document, reduced to their type and content
+fn semantic_blocks(input: &str) -> Vec<(String, String)> {
+    let blocks: Vec<Block<Entity>> = parse(input.as_bytes()).unwrap().collect();
+
+    blocks
+        .iter()
+        .filter_map(|block| {
+            let kind = match block {
+                Block::Header(_) => "header",
+                Block::Paragraph(_) => "paragraph",
+                Block::Quote(_) => "quote",
+                Block::List(_) => "list",
+                Block::Blank(_) => return None,
+            };
+            Some((kind.into(), block.to_text_content().trim().to_owned()))
+        })
+        .collect()
+}
+
+#[test]
+fn it_applies_every_normalization_by_default() {
+    let formatted = format(MESSY.as_bytes(), &FormatOptions::default()).unwrap();
+
+    assert_eq!(
+        formatted,
+        "# Heading\n\n- One\n- Two\n> quoted\n\nText with /link\n   Indented paragraph\n"
+    );
+}
+
+#[test]
+fn it_is_idempotent() {
+    let options = FormatOptions::default();
+    let once = format(MESSY.as_bytes(), &options).unwrap();
+    let twice = format(once.as_bytes(), &options).unwrap();
+
+    assert_eq!(once, twice);
+}
+
+#[test]
+fn it_is_idempotent_under_every_combination_of_options() {
+    for input in [MESSY, "a\n\n", "a\r\n\r\n", "\n\n# b  \n\n\n"] {
+        for rules in 0..16 {
+            for max_blank_lines in [None, Some(0), Some(1), Some(2)] {
+                let options = FormatOptions {
+                    normalize_newlines: rules & 1 != 0,
+                    sigil_spacing: rules & 2 != 0,
+                    trim_trailing_whitespace: rules & 4 != 0,
+                    max_blank_lines,
+                    final_newline: rules & 8 != 0,
+                };
+                let once = format(input.as_bytes(), &options).unwrap();
+                let twice = format(once.as_bytes(), &options).unwrap();
+
+                assert_eq!(once, twice, "{:?} with {:?}", input, options);
+            }
+        }
+    }
+}
+
+#[test]
+fn it_never_changes_the_sequence_of_semantic_blocks() {
+    let formatted = format(MESSY.as_bytes(), &FormatOptions::default()).unwrap();
+
+    assert_eq!(
+        semantic_blocks(&MESSY.replace("\r\n", "\n")),
+        semantic_blocks(&formatted)
+    );
+}
+
+#[test]
+fn it_can_disable_individual_rules() {
+    let options = FormatOptions {
+        normalize_newlines: true,
+        sigil_spacing: false,
+        trim_trailing_whitespace: false,
+        max_blank_lines: None,
+        final_newline: false,
+    };
+    let formatted = format(b"#Heading  \r\n\n\n\n-   One", &options).unwrap();
+
+    assert_eq!(formatted, "#Heading  \n\n\n\n-   One");
+}
+
+#[test]
+fn it_limits_runs_of_blank_lines() {
+    let options = FormatOptions {
+        max_blank_lines: Some(2),
+        ..FormatOptions::default()
+    };
+    let formatted = format(b"One\n\n\n\n\nTwo", &options).unwrap();
+
+    assert_eq!(formatted, "One\n\n\nTwo\n");
+
+    let options = FormatOptions {
+        max_blank_lines: Some(0),
+        ..FormatOptions::default()
+    };
+    let formatted = format(b"One\n\n\n\n\nTwo", &options).unwrap();
+
+    assert_eq!(formatted, "One\nTwo\n");
+}
+
+#[test]
+fn it_formats_an_empty_document_as_empty() {
+    let formatted = format(b"\n  \n\n", &FormatOptions::default()).unwrap();
+
+    assert_eq!(formatted, "");
+}
@@ -0,0 +1,81 @@
+use crate::{
+    block::Block,
+    json::{from_json, to_json},
+    parse,
+    primitive::Entity,
+};
+
+fn blocks(input: &str) -> Vec<Block<Entity>> {
+    parse(input.as_bytes()).unwrap().collect()
+}
+
+#[test]
+fn it_serializes_blocks_with_spans() {
+    let json = to_json(&blocks("# Hello, /world\n\nhttps://example.com"), true).unwrap();
+
+    assert_eq!(
+        json,
+        r##"{"version":1,"blocks":[{"type":"header","entities":[{"kind":"sigil","text":"#"},{"kind":"empty-space","text":" "},{"kind":"text","text":"Hello, "},{"kind":"slash-link","text":"/world"}],"span":{"start":0,"end":15}},{"type":"blank","entities":[{"kind":"empty-space","text":""}],"span":{"start":16,"end":16}},{"type":"paragraph","entities":[{"kind":"hyper-link","text":"https://example.com"}],"span":{"start":17,"end":36}}]}"##
+    );
+}
+
+#[test]
+fn it_round_trips_a_document() {
+    let input = include_str!("../../examples/example.subtext");
+    let original = blocks(input);
+
+    for spans in [true, false] {
+        let json = to_json(&original, spans).unwrap();
+        let blocks: Vec<Block<Entity>> = from_json(&json).unwrap();
+        assert_eq!(blocks, original);
+    }
+}
+
+#[test]
+fn it_rejects_unsupported_versions() {
+    let error = from_json::<Entity>(r##"{"version":2,"blocks":[]}"##).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Unsupported schema version 2 (expected 1)"
+    );
+}
+
+#[test]
+fn it_rejects_entities_that_could_not_form_a_block() {
+    let invalid = [
+        // A sigil after the start of a block
+        r##"{"type":"paragraph","entities":[{"kind":"text","text":"a "},{"kind":"sigil","text":"#"}]}"##,
+        // A header without its sigil
+        r##"{"type":"header","entities":[{"kind":"text","text":"Hello"}]}"##,
+        // A list with the wrong sigil
+        r##"{"type":"list","entities":[{"kind":"sigil","text":">"}]}"##,
+        // A paragraph that would be read as a quote
+        r##"{"type":"paragraph","entities":[{"kind":"text","text":"> Hello"}]}"##,
+        // A blank block with content
+        r##"{"type":"blank","entities":[{"kind":"text","text":"Hello"}]}"##,
+        // Links that aren't ones
+        r##"{"type":"paragraph","entities":[{"kind":"slash-link","text":"world"}]}"##,
+        r##"{"type":"paragraph","entities":[{"kind":"slash-link","text":"/hello world"}]}"##,
+        r##"{"type":"paragraph","entities":[{"kind":"slash-link","text":"@cdata foo"}]}"##,
+        // An entity spanning two lines
+        r##"{"type":"paragraph","entities":[{"kind":"text","text":"a\nb"}]}"##,
+        // An unknown kind of entity
+        r##"{"type":"paragraph","entities":[{"kind":"bold","text":"a"}]}"##,
+    ];
+
+    for block in invalid {
+        let json = format!(r#"{{"version":1,"blocks":[{}]}}"#, block);
+        assert!(
+            from_json::<Entity>(&json).is_err(),
+            "Expected {} to be rejected",
+            block
+        );
+    }
+
+    // Fields from a later revision of the schema are ignored
+    let valid = r##"{"version":1,"blocks":[{"type":"list","entities":[{"kind":"sigil","text":"-","style":"bold"},{"kind":"text","text":" Hello"}],"id":"b3f9c1"}]}"##;
+    match from_json::<Entity>(valid).unwrap().as_slice() {
+        [Block::List(entities)] => assert_eq!(entities.len(), 2),
+        _ => panic!("Expected a single list item"),
+    }
+}
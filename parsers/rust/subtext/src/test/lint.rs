@@ -0,0 +1,116 @@
+use crate::lint::{apply_fixes, lint, LintConfig, Location, Rule, Severity};
+
+fn rules(input: &str, config: &LintConfig) -> Vec<(Rule, Location)> {
+    lint(input.as_bytes(), config)
+        .unwrap()
+        .into_iter()
+        .map(|diagnostic| (diagnostic.rule, diagnostic.location))
+        .collect()
+}
+
+fn at(line: usize, column: usize) -> Location {
+    Location { line, column }
+}
+
+#[test]
+fn it_flags_problems_in_the_example_document() {
+    let input = include_str!("../../examples/example.subtext");
+
+    assert_eq!(
+        rules(input, &LintConfig::default()),
+        vec![(Rule::InvalidSlashlink, at(14, 25))]
+    );
+}
+
+#[test]
+fn it_flags_reserved_sigils() {
+    let input = "* Starred\n  Indented\n\tTabbed\n--- Rule\nλ Lambda\nPlain";
+
+    assert_eq!(
+        rules(input, &LintConfig::default()),
+        vec![
+            (Rule::ReservedSigil, at(1, 1)),
+            (Rule::ReservedSigil, at(2, 1)),
+            (Rule::ReservedSigil, at(3, 1)),
+            (Rule::ReservedSigil, at(4, 1)),
+            (Rule::ReservedSigil, at(5, 1)),
+        ]
+    );
+}
+
+#[test]
+fn it_does_not_flag_a_peer_link_at_the_start_of_a_line() {
+    assert!(rules(
+        "@cdata/foo is great\n@cdata said so",
+        &LintConfig::default()
+    )
+    .is_empty());
+    assert_eq!(
+        rules("@ is reserved", &LintConfig::default()),
+        vec![(Rule::ReservedSigil, at(1, 1))]
+    );
+}
+
+#[test]
+fn it_flags_problems_with_links() {
+    let input = "See [[unterminated\nSee https://example.com.\nSee /naïve";
+
+    assert_eq!(
+        rules(input, &LintConfig::default()),
+        vec![
+            (Rule::UnterminatedWikiLink, at(1, 5)),
+            (Rule::UrlTrailingPunctuation, at(2, 5)),
+            (Rule::InvalidSlashlink, at(3, 5)),
+        ]
+    );
+
+    // The peer of a peer-qualified link may contain dots and colons
+    assert!(rules(
+        "Ask @cdata.ben/evolution or @did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK/foo",
+        &LintConfig::default()
+    )
+    .is_empty());
+}
+
+#[test]
+fn it_flags_and_fixes_line_ending_and_whitespace_problems() {
+    let input = "One\r\nTwo\n\t \n  - Three";
+    let diagnostics = lint(input.as_bytes(), &LintConfig::default()).unwrap();
+
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.rule)
+            .collect::<Vec<Rule>>(),
+        vec![
+            Rule::CarriageReturn,
+            Rule::TabOnlyLine,
+            Rule::ReservedSigil,
+            Rule::LeadingWhitespaceBeforeSigil
+        ]
+    );
+    assert_eq!(apply_fixes(input, &diagnostics), "One\nTwo\n\n  - Three");
+    assert_eq!(diagnostics[3].fix, None);
+}
+
+#[test]
+fn it_allows_rules_to_be_configured() {
+    let input = "  - Not a list item\n* Starred";
+    let config = LintConfig::default()
+        .disable(Rule::ReservedSigil)
+        .severity(Rule::LeadingWhitespaceBeforeSigil, Severity::Error);
+    let diagnostics = lint(input.as_bytes(), &config).unwrap();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].rule, Rule::LeadingWhitespaceBeforeSigil);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+}
+
+#[test]
+fn it_accepts_a_sigil_escaped_with_a_single_space() {
+    assert!(rules(
+        " - Not a list item\n # Not a header\n > Not a quote",
+        &LintConfig::default()
+    )
+    .is_empty());
+}
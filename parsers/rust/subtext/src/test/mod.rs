@@ -1,6 +1,24 @@
+mod anchor;
 mod blank;
 mod block;
+mod builder;
+mod convert;
+mod crdt;
+mod did;
+mod format;
+#[cfg(feature = "serde")]
+mod json;
+mod lint;
+#[allow(clippy::redundant_slicing)]
 mod parse;
+mod petname;
+#[allow(clippy::get_first)]
 mod primitive;
+mod render;
+#[allow(clippy::bool_comparison)]
 mod sequence;
+mod sexpr;
+mod site;
+mod slug_path;
 mod text_content;
+mod transclude;
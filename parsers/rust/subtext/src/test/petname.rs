@@ -0,0 +1,138 @@
+use std::{fs, str::FromStr};
+
+use anyhow::Result;
+
+use crate::{
+    petname::{FileAddressBook, MemoryAddressBook, PetnameResolver},
+    Peer, Slashlink,
+};
+
+const LOCAL: &str = "did:key:z6MkLocal";
+
+fn book() -> MemoryAddressBook {
+    let mut book = MemoryAddressBook::default();
+    book.insert(None, "morgon", "did:key:z6MkMorgon");
+    book.insert(Some("did:key:z6MkMorgon"), "gordon", "did:key:z6MkGordon");
+    book.insert(Some("did:key:z6MkGordon"), "jordan", "did:key:z6MkJordan");
+    book.insert(Some("did:key:z6MkGordon"), "back", "did:key:z6MkMorgon");
+    book
+}
+
+fn link(input: &str) -> Slashlink {
+    Slashlink::from_str(input).unwrap()
+}
+
+#[test]
+fn it_resolves_slashlinks_through_chains_of_address_books() {
+    let resolver = PetnameResolver::new(book(), LOCAL);
+
+    assert_eq!(
+        resolver
+            .resolve(&link("@jordan.gordon.morgon/Foo-Bar"))
+            .unwrap(),
+        ("did:key:z6MkJordan".into(), Some("foo-bar".into()))
+    );
+    assert_eq!(
+        resolver.resolve(&link("@Morgon")).unwrap(),
+        ("did:key:z6MkMorgon".into(), None)
+    );
+    assert_eq!(
+        resolver.resolve(&link("/notes")).unwrap(),
+        (LOCAL.into(), Some("notes".into()))
+    );
+    assert_eq!(
+        resolver
+            .resolve(&link(
+                "@did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK/notes"
+            ))
+            .unwrap(),
+        (
+            "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK".into(),
+            Some("notes".into())
+        )
+    );
+
+    let error = resolver.resolve(&link("@nobody.morgon/foo")).unwrap_err();
+    assert!(
+        format!("{:#}", error)
+            .contains("No peer named nobody in the address book of did:key:z6MkMorgon"),
+        "{:#}",
+        error
+    );
+}
+
+#[test]
+fn it_limits_cycles_and_chain_depth() {
+    let resolver = PetnameResolver::new(book(), LOCAL);
+    assert!(resolver
+        .resolve_peer(&Peer::Name(vec![
+            "back".into(),
+            "gordon".into(),
+            "morgon".into()
+        ]))
+        .is_err());
+
+    let resolver = PetnameResolver::new(book(), LOCAL).max_depth(2);
+    assert!(resolver.resolve(&link("@gordon.morgon")).is_ok());
+    assert!(resolver.resolve(&link("@jordan.gordon.morgon")).is_err());
+}
+
+#[test]
+fn it_accepts_closures_as_address_books() {
+    let book = |peer: Option<&str>, petname: &str| -> Result<Option<String>> {
+        Ok(match (peer, petname) {
+            (None, "ben") => Some("did:key:z6MkBen".into()),
+            (Some("did:key:z6MkBen"), "alice") => Some("did:key:z6MkAlice".into()),
+            _ => None,
+        })
+    };
+
+    assert_eq!(
+        PetnameResolver::new(book, LOCAL)
+            .resolve_peer(&Peer::Name(vec!["alice".into(), "ben".into()]))
+            .unwrap(),
+        "did:key:z6MkAlice"
+    );
+}
+
+#[test]
+fn it_reads_and_writes_address_book_files() {
+    let path = std::env::temp_dir().join(format!("subtext-petnames-{}", std::process::id()));
+    fs::write(
+        &path,
+        "# My peers\nmorgon did:key:z6MkMorgon\n\n[did:key:z6MkMorgon]\n  gordon   did:key:z6MkGordon\n",
+    )
+    .unwrap();
+
+    let mut book = FileAddressBook::open(&path).unwrap();
+    assert_eq!(
+        PetnameResolver::new(book.clone(), LOCAL)
+            .resolve(&link("@gordon.morgon/x"))
+            .unwrap()
+            .0,
+        "did:key:z6MkGordon"
+    );
+
+    assert!(book.insert(None, "not valid", "did:key:z6MkBen").is_err());
+    assert!(book.insert(None, "ben", "z6MkBen").is_err());
+    book.insert(None, "ben", "did:key:z6MkBen").unwrap();
+    book.remove(None, "morgon");
+    book.save().unwrap();
+
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "ben did:key:z6MkBen\n\n[did:key:z6MkMorgon]\ngordon did:key:z6MkGordon\n"
+    );
+    assert_eq!(FileAddressBook::open(&path).unwrap(), book);
+
+    fs::write(&path, "morgon\n").unwrap();
+    assert!(FileAddressBook::open(&path).is_err());
+    fs::remove_file(&path).unwrap();
+
+    // A missing file is an empty address book
+    assert!(
+        PetnameResolver::new(FileAddressBook::open(&path).unwrap(), LOCAL)
+            .resolve(&link("@morgon"))
+            .is_err()
+    );
+}
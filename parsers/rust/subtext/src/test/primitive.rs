@@ -1,8 +1,13 @@
 use crate::{
-    primitive::{parse_slash_link, parse_text, parse_wiki_link, Entity},
+    primitive::{parse_slash_link, parse_text, parse_wiki_link, peer_link_length, Entity},
     str::SharedString,
 };
 
+fn entities(input: &str) -> Vec<Entity> {
+    let input = SharedString::try_from_byte_slice(input.as_bytes()).unwrap();
+    parse_text::<Entity>(input).unwrap().0
+}
+
 #[test]
 fn it_parses_a_slash_link_blap() {
     let input = SharedString::try_from_byte_slice(b"/foo").unwrap();
@@ -75,3 +80,52 @@ fn it_parses_a_slash_link_following_a_text_span() {
     assert_eq!(entities.get(0).unwrap().to_string(), "foo bar ");
     assert_eq!(entities.get(1).unwrap().to_string(), "/baz");
 }
+
+#[test]
+fn it_parses_peer_links_and_mentions_in_a_text_span() {
+    assert_eq!(
+        entities("Ask @cdata/foo-bar or @ben.gordon, and @did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK/notes#intro."),
+        vec![
+            Entity::TextSpan("Ask ".into()),
+            Entity::SlashLink("@cdata/foo-bar".into()),
+            Entity::TextSpan(" or ".into()),
+            Entity::SlashLink("@ben.gordon".into()),
+            Entity::TextSpan(", and ".into()),
+            Entity::SlashLink("@did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK/notes#intro".into()),
+            Entity::TextSpan(".".into()),
+        ]
+    );
+    assert_eq!(
+        entities("@cdata at the start"),
+        vec![
+            Entity::SlashLink("@cdata".into()),
+            Entity::TextSpan(" at the start".into()),
+        ]
+    );
+}
+
+#[test]
+fn it_does_not_mistake_email_addresses_for_mentions() {
+    for input in [
+        "Write to ben@example.com today",
+        "Follow @ben@example.social",
+        "Meet @ 5pm",
+        "Ask @.ben or @did:key or @did:Key:abc",
+    ] {
+        assert_eq!(
+            entities(input),
+            vec![Entity::TextSpan(input.into())],
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn it_measures_peer_links() {
+    assert_eq!(peer_link_length("@cdata"), Some(6));
+    assert_eq!(peer_link_length("@cdata/"), Some(6));
+    assert_eq!(peer_link_length("@cdata/foo/bar/."), Some(14));
+    assert_eq!(peer_link_length("@a..b"), None);
+    assert_eq!(peer_link_length("cdata"), None);
+}
@@ -0,0 +1,90 @@
+use crate::{
+    block::Block,
+    parse,
+    primitive::Entity,
+    render::{render_html, render_html_with, HtmlOptions, LinkTarget},
+};
+
+fn blocks(input: &str) -> Vec<Block<Entity>> {
+    parse(input.as_bytes()).unwrap().collect()
+}
+
+fn resolve(target: &LinkTarget) -> Option<String> {
+    match target {
+        LinkTarget::SlashLink(slashlink) => Some(format!("/notes{}.html", slashlink)),
+        LinkTarget::WikiLink(_) => None,
+    }
+}
+
+#[test]
+fn it_renders_semantic_html() {
+    let input = r#"# Evolution
+
+Evolution is a behavior that emerges in any system with:
+
+- Mutation
+- Heredity
+
+- Selection
+> There is no such thing as advantageous
+> in a general sense."#;
+
+    assert_eq!(
+        render_html(&blocks(input), &resolve),
+        r#"<h1>Evolution</h1>
+<p>Evolution is a behavior that emerges in any system with:</p>
+<ul>
+<li>Mutation</li>
+<li>Heredity</li>
+</ul>
+<ul>
+<li>Selection</li>
+</ul>
+<blockquote>
+<p>There is no such thing as advantageous</p>
+<p>in a general sense.</p>
+</blockquote>"#
+    );
+}
+
+#[test]
+fn it_resolves_links() {
+    let input = "See /punctuated-equilibrium and [[Evolution]] at https://example.com/?a=1&b=2";
+
+    assert_eq!(
+        render_html(&blocks(input), &resolve),
+        r#"<p>See <a href="/notes/punctuated-equilibrium.html">/punctuated-equilibrium</a> and Evolution at <a href="https://example.com/?a=1&amp;b=2">https://example.com/?a=1&amp;b=2</a></p>"#
+    );
+}
+
+#[test]
+fn it_escapes_text() {
+    let input = "<script>alert(\"hi\")</script> & 'friends'";
+
+    assert_eq!(
+        render_html(&blocks(input), &|_: &LinkTarget| None),
+        "<p>&lt;script&gt;alert(&quot;hi&quot;)&lt;/script&gt; &amp; &#39;friends&#39;</p>"
+    );
+}
+
+#[test]
+fn it_can_give_headers_unique_ids() {
+    let input = "# Questions\n# Questions\n# Why?";
+    let options = HtmlOptions { header_ids: true };
+
+    assert_eq!(
+        render_html_with(&blocks(input), &resolve, &options),
+        "<h1 id=\"questions\">Questions</h1>\n<h1 id=\"questions-2\">Questions</h1>\n<h1 id=\"why\">Why?</h1>"
+    );
+}
+
+#[test]
+fn it_does_not_reuse_a_suffixed_id() {
+    let input = "# Foo\n# Foo\n# Foo 2\n# Foo 2\n# Foo";
+    let options = HtmlOptions { header_ids: true };
+
+    assert_eq!(
+        render_html_with(&blocks(input), &resolve, &options),
+        "<h1 id=\"foo\">Foo</h1>\n<h1 id=\"foo-2\">Foo</h1>\n<h1 id=\"foo-2-2\">Foo 2</h1>\n<h1 id=\"foo-2-3\">Foo 2</h1>\n<h1 id=\"foo-3\">Foo</h1>"
+    );
+}
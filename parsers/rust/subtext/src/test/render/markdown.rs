@@ -0,0 +1,69 @@
+use crate::{
+    block::Block,
+    parse,
+    primitive::Entity,
+    render::{render_markdown, LinkTarget},
+};
+
+fn blocks(input: &str) -> Vec<Block<Entity>> {
+    parse(input.as_bytes()).unwrap().collect()
+}
+
+fn resolve(target: &LinkTarget) -> Option<String> {
+    match target {
+        LinkTarget::SlashLink(slashlink) => Some(format!("{}.md", &slashlink[1..])),
+        LinkTarget::WikiLink(label) => Some(format!("{}.md", label.replace(' ', "-"))),
+    }
+}
+
+#[test]
+fn it_renders_blocks_as_commonmark() {
+    let input = r#"# Evolution
+Evolution is a behavior.
+Nature selects for good enough.
+
+- Mutation
+- Heredity
+> There is no such thing as advantageous
+> in a general sense."#;
+
+    assert_eq!(
+        render_markdown(&blocks(input), &resolve),
+        r#"# Evolution
+
+Evolution is a behavior.
+
+Nature selects for good enough.
+
+- Mutation
+- Heredity
+
+> There is no such thing as advantageous
+>
+> in a general sense."#
+    );
+}
+
+#[test]
+fn it_renders_links() {
+    let input = "See /punctuated-equilibrium then [[Some Note]] and https://example.com";
+
+    assert_eq!(
+        render_markdown(&blocks(input), &resolve),
+        "See [/punctuated-equilibrium](punctuated-equilibrium.md) then [Some Note](Some-Note.md) and <https://example.com>"
+    );
+
+    let unresolved = render_markdown(&blocks("See /foo_bar"), &|_: &LinkTarget| None);
+
+    assert_eq!(unresolved, "See /foo\\_bar");
+}
+
+#[test]
+fn it_escapes_markdown_syntax() {
+    let input = "Use *stars*, _underscores_, `ticks` and [brackets] \\ literally\n  1. Not a list\n- + Not nested";
+
+    assert_eq!(
+        render_markdown(&blocks(input), &resolve),
+        "Use \\*stars\\*, \\_underscores\\_, \\`ticks\\` and \\[brackets\\] \\\\ literally\n\n1\\. Not a list\n\n- \\+ Not nested"
+    );
+}
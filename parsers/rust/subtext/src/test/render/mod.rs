@@ -0,0 +1,5 @@
+mod html;
+mod markdown;
+mod plain;
+mod template;
+mod terminal;
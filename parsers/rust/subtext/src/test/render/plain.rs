@@ -0,0 +1,105 @@
+use crate::{
+    block::Block,
+    parse,
+    primitive::Entity,
+    render::{
+        plain::{render_inline, LinkText},
+        render_plain, strip, PlainOptions,
+    },
+};
+
+fn blocks(input: &str) -> Vec<Block<Entity>> {
+    parse(input.as_bytes()).unwrap().collect()
+}
+
+const INPUT: &str = r#"
+# Evolution
+Evolution is a behavior, see /natural-selection
+
+
+- Mutation https://example.com/mutation and [[Genetic drift]]
+> There is no such thing as advantageous in a general sense
+"#;
+
+#[test]
+fn it_strips_sigils() {
+    assert_eq!(
+        strip(&blocks(INPUT)),
+        r#"
+Evolution
+Evolution is a behavior, see /natural-selection
+
+
+Mutation https://example.com/mutation and [[Genetic drift]]
+There is no such thing as advantageous in a general sense"#
+    );
+}
+
+#[test]
+fn it_renders_links_as_configured() {
+    let blocks = blocks(
+        "See /natural-selection/fitness_landscape, https://example.com or [[Genetic drift]] now",
+    );
+    let render = |links| {
+        render_inline(
+            &blocks[0],
+            &PlainOptions {
+                links,
+                ..Default::default()
+            },
+        )
+    };
+
+    assert_eq!(
+        render(LinkText::Raw),
+        "See /natural-selection/fitness_landscape, https://example.com or [[Genetic drift]] now"
+    );
+    assert_eq!(
+        render(LinkText::Words),
+        "See natural selection fitness landscape, https://example.com or Genetic drift now"
+    );
+    assert_eq!(
+        render(LinkText::DropUrls),
+        "See natural selection fitness landscape, or Genetic drift now"
+    );
+}
+
+#[test]
+fn it_collapses_blank_lines() {
+    let options = PlainOptions {
+        collapse_blank_lines: true,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        render_plain(&blocks(INPUT), &options),
+        r#"Evolution
+Evolution is a behavior, see /natural-selection
+
+Mutation https://example.com/mutation and [[Genetic drift]]
+There is no such thing as advantageous in a general sense"#
+    );
+}
+
+#[test]
+fn it_wraps_with_hanging_indents_for_lists_and_quotes() {
+    let options = PlainOptions {
+        links: LinkText::Words,
+        collapse_blank_lines: true,
+        width: Some(24),
+    };
+
+    assert_eq!(
+        render_plain(&blocks(INPUT), &options),
+        r#"Evolution
+Evolution is a behavior,
+see natural selection
+
+Mutation
+  https://example.com/mutation
+  and Genetic drift
+There is no such thing
+  as advantageous in a
+  general sense"#
+    );
+}
@@ -0,0 +1,84 @@
+use crate::{
+    block::Block,
+    parse,
+    primitive::Entity,
+    render::{render_html, render_markdown, render_template, LinkTarget, Template, Templates},
+};
+
+fn blocks(input: &str) -> Vec<Block<Entity>> {
+    parse(input.as_bytes()).unwrap().collect()
+}
+
+fn resolve(target: &LinkTarget) -> Option<String> {
+    match target {
+        LinkTarget::SlashLink(slashlink) => Some(format!("/notes{}.html", slashlink)),
+        LinkTarget::WikiLink(_) => None,
+    }
+}
+
+const DOCUMENT: &str = r#"# Evolution & /selection
+
+Evolution is a *behavior*, see /natural-selection and [[Genetic Drift]]
+
+- Mutation
+- Heredity at https://example.com/heredity
+
+- Selection
+> There is no such thing as advantageous
+> in a general sense.
+#1 rule"#;
+
+#[test]
+fn it_matches_the_html_and_markdown_renderers_by_default() {
+    let blocks = blocks(DOCUMENT);
+
+    assert_eq!(
+        render_template(&blocks, &Templates::html(), &resolve),
+        render_html(&blocks, &resolve)
+    );
+    assert_eq!(
+        render_template(&blocks, &Templates::markdown(), &resolve),
+        render_markdown(&blocks, &resolve)
+    );
+}
+
+#[test]
+fn it_renders_blocks_and_entities_through_custom_templates() {
+    let templates = Templates {
+        header: "== {text} ({id}) ==".parse().unwrap(),
+        paragraph: "{content}{^last} / {/last}".parse().unwrap(),
+        list: "{#first}Items:{/first} {index}. {content}{#last}.{/last}"
+            .parse()
+            .unwrap(),
+        quote: "".parse().unwrap(),
+        slashlink: "{#url}<{url}|{label}>{/url}".parse().unwrap(),
+        wikilink: "{{{label}}}".parse().unwrap(),
+        hyperlink: "{kind}".parse().unwrap(),
+        group_separator: "\n".into(),
+        escape: |text| text.to_uppercase(),
+        ..Templates::html()
+    };
+    let resolver = |target: &LinkTarget| match target {
+        LinkTarget::SlashLink("/b") => Some("https://b".to_owned()),
+        _ => None,
+    };
+
+    assert_eq!(
+        render_template(
+            &blocks("# Digest\n\nSee /a\nthen /b\n- [[One]]\n- https://two\n> Dropped"),
+            &templates,
+            &resolver
+        ),
+        "== DIGEST (DIGEST) ==\nSEE  / THEN <https://b|/B>\nItems: 1. {ONE} 2. hyperlink."
+    );
+}
+
+#[test]
+fn it_rejects_malformed_templates() {
+    assert!("{#first}<ul>".parse::<Template>().is_err());
+    assert!("<ul>{/first}".parse::<Template>().is_err());
+    assert!("{#first}{/last}".parse::<Template>().is_err());
+    assert!("{content".parse::<Template>().is_err());
+    assert!("{two words}".parse::<Template>().is_err());
+    assert!("{{content} }".parse::<Template>().is_ok());
+}
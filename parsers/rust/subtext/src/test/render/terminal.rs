@@ -0,0 +1,122 @@
+use crate::{
+    block::Block,
+    parse,
+    primitive::Entity,
+    render::{render_terminal, ColorMode, LinkTarget, TerminalOptions},
+};
+
+fn blocks(input: &str) -> Vec<Block<Entity>> {
+    parse(input.as_bytes()).unwrap().collect()
+}
+
+fn resolve(target: &LinkTarget) -> Option<String> {
+    match target {
+        LinkTarget::SlashLink(slashlink) => Some(format!("/notes{}.subtext", slashlink)),
+        LinkTarget::WikiLink(_) => None,
+    }
+}
+
+#[test]
+fn it_renders_without_color() {
+    let input = r#"# Evolution
+Evolution is a behavior, see /natural-selection
+
+- Mutation introduces variation into a population
+> There is no such thing as advantageous in a general sense"#;
+
+    let options = TerminalOptions {
+        color: ColorMode::Never,
+        width: Some(28),
+    };
+
+    assert_eq!(
+        render_terminal(&blocks(input), &resolve, &options),
+        r#"Evolution
+Evolution is a behavior, see
+/natural-selection
+
+• Mutation introduces
+  variation into a
+  population
+│ There is no such thing as
+│ advantageous in a general
+│ sense"#
+    );
+}
+
+#[test]
+fn it_renders_colors_and_hyperlinks() {
+    let input =
+        "# Evolution\nSee /natural-selection and https://example.com, or [[Drift]]\n> Quote";
+    let options = TerminalOptions {
+        color: ColorMode::Always,
+        width: Some(80),
+    };
+
+    assert_eq!(
+        render_terminal(&blocks(input), &resolve, &options),
+        "\x1b[1;35mEvolution\x1b[0m\n\
+        See \x1b]8;;file:///notes/natural-selection.subtext\x1b\\\x1b[4;36m/natural-selection\x1b[24;39m\x1b]8;;\x1b\\ \
+        and \x1b]8;;https://example.com,\x1b\\\x1b[4;36mhttps://example.com,\x1b[24;39m\x1b]8;;\x1b\\ \
+        or \x1b[4;36m[[Drift]]\x1b[24;39m\n\
+        \x1b[2m│\x1b[0m Quote"
+    );
+}
+
+#[test]
+fn it_keeps_the_header_color_after_a_link() {
+    let options = TerminalOptions {
+        color: ColorMode::Always,
+        width: Some(80),
+    };
+
+    assert_eq!(
+        render_terminal(&blocks("# See [[Drift]] again"), &resolve, &options),
+        "\x1b[1;35mSee \x1b[4;36m[[Drift]]\x1b[24;39m\x1b[1;35m again\x1b[0m"
+    );
+}
+
+#[test]
+fn it_wraps_by_visible_width() {
+    let input = "See https://example.com/a https://example.com/b";
+    let render = |color| {
+        render_terminal(
+            &blocks(input),
+            &resolve,
+            &TerminalOptions {
+                color,
+                width: Some(30),
+            },
+        )
+    };
+
+    assert_eq!(render(ColorMode::Always).lines().count(), 2);
+    assert_eq!(
+        render(ColorMode::Never),
+        "See https://example.com/a\nhttps://example.com/b"
+    );
+}
+
+#[test]
+fn it_strips_control_characters() {
+    let input = "Title \x1b]0;pwned\x07 and \x1b[2J\ttab\nhttps://example.com/\x1b[2J";
+    let render = |color| {
+        render_terminal(
+            &blocks(input),
+            &resolve,
+            &TerminalOptions {
+                color,
+                width: Some(80),
+            },
+        )
+    };
+
+    assert_eq!(
+        render(ColorMode::Never),
+        "Title ]0;pwned and [2J tab\nhttps://example.com/[2J"
+    );
+    assert_eq!(
+        render(ColorMode::Always),
+        "Title ]0;pwned and [2J tab\n\x1b[4;36mhttps://example.com/[2J\x1b[24;39m"
+    );
+}
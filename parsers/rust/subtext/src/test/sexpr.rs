@@ -0,0 +1,123 @@
+use crate::{
+    block::Block,
+    builder::Document,
+    parse,
+    primitive::Entity,
+    sexpr::{block_from_sexpr, blocks_from_sexpr, blocks_to_sexpr, to_sexpr},
+};
+
+fn parse_blocks(input: &str) -> Vec<Block<Entity>> {
+    parse(input.as_bytes()).unwrap().collect()
+}
+
+#[test]
+fn it_parses_the_blank_line_example_from_the_specification() {
+    let input = "I have eaten
+the plums
+that were in
+the icebox
+
+and which
+you were probably
+saving
+for breakfast";
+
+    let expected = r#"((text "I have eaten")
+ (text "the plums")
+ (text "that were in")
+ (text "the icebox")
+ (blank)
+ (text "and which")
+ (text "you were probably")
+ (text "saving")
+ (text "for breakfast"))"#;
+
+    let blocks = parse_blocks(input);
+
+    assert_eq!(blocks_to_sexpr(&blocks), expected);
+    assert_eq!(blocks_from_sexpr::<Entity>(expected).unwrap(), blocks);
+}
+
+#[test]
+fn it_prints_block_types_and_inline_entities() {
+    let blocks = parse_blocks(
+        "# Evolution\nSee /natural-selection and [[Mutation]]\n- https://example.com\n> Quoted",
+    );
+
+    assert_eq!(
+        blocks_to_sexpr(&blocks),
+        r#"((header "Evolution")
+ (text "See " (slashlink "/natural-selection") " and " (wikilink "[[Mutation]]"))
+ (list (hyperlink "https://example.com"))
+ (quote "Quoted"))"#
+    );
+}
+
+#[test]
+fn it_round_trips_escapes_and_significant_whitespace() {
+    let blocks = parse_blocks(" # Not a header\n\"Quoted\" \\ and\ttabbed\n#");
+
+    let sexpr = blocks_to_sexpr(&blocks);
+    assert_eq!(
+        sexpr,
+        r#"((text " # Not a header")
+ (text "\"Quoted\" \\ and\ttabbed")
+ (header))"#
+    );
+    assert_eq!(blocks_from_sexpr::<Entity>(&sexpr).unwrap(), blocks);
+}
+
+#[test]
+fn it_reads_a_single_block() {
+    let block = block_from_sexpr::<Entity>(r#"(list "Go to " (slashlink "/shops"))"#).unwrap();
+
+    assert_eq!(block, parse_blocks("- Go to /shops")[0]);
+    assert_eq!(to_sexpr(&block), r#"(list "Go to " (slashlink "/shops"))"#);
+}
+
+#[test]
+fn it_rejects_blocks_that_would_be_parsed_differently() {
+    let cases = [
+        // Not a link
+        r#"((text (slashlink "no slash")))"#,
+        // A link the s-expression doesn't mention
+        r#"((text "See /natural-selection"))"#,
+        // A paragraph that is read as a header
+        r##"((text "# Not a paragraph"))"##,
+        r#"((blank "content"))"#,
+        r#"((text "one\ntwo"))"#,
+        r#"((heading "Evolution"))"#,
+        r#"((text "Unterminated))"#,
+        r#"((text "Hello") (blank)"#,
+        r#"((text "Hello")) (blank)"#,
+        r#"(text "Hello")"#,
+    ];
+
+    for case in cases {
+        assert!(
+            blocks_from_sexpr::<Entity>(case).is_err(),
+            "{} should be rejected",
+            case
+        );
+    }
+}
+
+#[test]
+fn it_prints_and_reads_a_document() {
+    let document = Document::builder()
+        .header("Evolution")
+        .blank()
+        .paragraph(|p| p.text("See ").slashlink("punctuated-equilibrium"))
+        .build()
+        .unwrap();
+
+    let sexpr = document.to_sexpr();
+    assert_eq!(
+        sexpr,
+        r#"((header "Evolution")
+ (blank)
+ (text "See " (slashlink "/punctuated-equilibrium")))"#
+    );
+    assert_eq!(Document::from_sexpr(&sexpr).unwrap(), document);
+    assert_eq!(Document::from_sexpr("()").unwrap().to_string(), "");
+}
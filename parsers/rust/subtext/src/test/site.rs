@@ -0,0 +1,228 @@
+use std::{fs, path::PathBuf};
+
+use crate::site::{build, BrokenLink, SiteOptions};
+
+/// A scratch directory of notes that is removed when dropped
+struct TempSite {
+    root: PathBuf,
+}
+
+impl TempSite {
+    fn new(name: &str) -> Self {
+        let root =
+            std::env::temp_dir().join(format!("subtext-site-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("notes")).unwrap();
+        TempSite { root }
+    }
+
+    fn write(&self, path: &str, content: &str) {
+        let path = self.root.join("notes").join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn remove(&self, path: &str) {
+        fs::remove_file(self.root.join("notes").join(path)).unwrap();
+    }
+
+    fn read(&self, path: &str) -> String {
+        fs::read_to_string(self.root.join("site").join(path)).unwrap()
+    }
+
+    fn options(&self) -> SiteOptions {
+        SiteOptions {
+            source: self.root.join("notes"),
+            output: self.root.join("site"),
+            title: "Garden".into(),
+        }
+    }
+}
+
+impl Drop for TempSite {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+fn sorted(mut slugs: Vec<String>) -> Vec<String> {
+    slugs.sort();
+    slugs
+}
+
+#[test]
+fn it_renders_pages_with_titles_links_and_backlinks() {
+    let site = TempSite::new("pages");
+    site.write(
+        "evolution.subtext",
+        "# Evolution\nSee /natural-selection and [[Genetic Drift]]\n- /topics/biology",
+    );
+    site.write(
+        "natural-selection.subtext",
+        "# Natural selection\nPart of /evolution",
+    );
+    site.write("genetic-drift.subtext", "Random changes");
+    site.write("topics/biology.subtext", "# Biology\nBack to /evolution");
+
+    let result = build(&site.options()).unwrap();
+    assert_eq!(
+        sorted(result.written),
+        vec![
+            "evolution",
+            "genetic-drift",
+            "index",
+            "natural-selection",
+            "topics/biology"
+        ]
+    );
+    assert!(result.broken_links.is_empty());
+
+    let evolution = site.read("evolution.html");
+    assert!(evolution.contains("<title>Evolution | Garden</title>"));
+    assert!(evolution.contains(
+        "<p>See <a href=\"natural-selection.html\">/natural-selection</a> and <a href=\"genetic-drift.html\">Genetic Drift</a></p>"
+    ));
+    assert!(evolution.contains("<li><a href=\"topics/biology.html\">/topics/biology</a></li>"));
+    assert!(evolution.contains(
+        "<nav class=\"backlinks\">\n<h2>Backlinks</h2>\n<ul>\n<li><a href=\"natural-selection.html\">Natural selection</a></li>\n<li><a href=\"topics/biology.html\">Biology</a></li>\n</ul>\n</nav>"
+    ));
+
+    let biology = site.read("topics/biology.html");
+    assert!(biology.contains("<a href=\"../evolution.html\">/evolution</a>"));
+    assert!(biology.contains("<li><a href=\"../evolution.html\">Evolution</a></li>"));
+
+    // Without a header, the slug is the title
+    assert!(site
+        .read("genetic-drift.html")
+        .contains("<title>genetic-drift | Garden</title>"));
+    // Wikilinks count as backlinks too
+    assert!(site
+        .read("genetic-drift.html")
+        .contains("<li><a href=\"evolution.html\">Evolution</a></li>"));
+}
+
+#[test]
+fn it_writes_an_index_of_every_page() {
+    let site = TempSite::new("index");
+    site.write("b.subtext", "# Beta");
+    site.write("a.subtext", "# Alpha");
+
+    build(&site.options()).unwrap();
+    assert_eq!(
+        site.read("index.html"),
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Garden</title>
+</head>
+<body>
+<h1>Garden</h1>
+<nav class="pages">
+<ul>
+<li><a href="a.html">Alpha</a></li>
+<li><a href="b.html">Beta</a></li>
+</ul>
+</nav>
+</body>
+</html>
+"#
+    );
+
+    // A note named index is shown above the list
+    site.write("index.subtext", "Welcome, start at /a");
+    build(&site.options()).unwrap();
+    assert!(site.read("index.html").contains(
+        "<main>\n<p>Welcome, start at <a href=\"a.html\">/a</a></p>\n</main>\n<nav class=\"pages\">"
+    ));
+}
+
+#[test]
+fn it_reports_broken_links() {
+    let site = TempSite::new("broken");
+    site.write(
+        "a.subtext",
+        "See /missing and [[Nowhere]] and /b, not @cdata/elsewhere",
+    );
+    site.write("b.subtext", "Fine");
+
+    let result = build(&site.options()).unwrap();
+    assert_eq!(
+        result.broken_links,
+        vec![
+            BrokenLink {
+                page: "a".into(),
+                link: "/missing".into()
+            },
+            BrokenLink {
+                page: "a".into(),
+                link: "[[Nowhere]]".into()
+            },
+        ]
+    );
+    assert!(site.read("a.html").contains(
+        "<p>See /missing and Nowhere and <a href=\"b.html\">/b,</a> not @cdata/elsewhere</p>"
+    ));
+    assert!(!site.read("a.html").contains("Backlinks"));
+}
+
+#[test]
+fn it_links_to_sections_of_a_page() {
+    let site = TempSite::new("fragments");
+    site.write("a.subtext", "See /b#questions");
+    site.write("b.subtext", "# Questions\nWhy?");
+
+    let result = build(&site.options()).unwrap();
+    assert!(result.broken_links.is_empty());
+    assert!(site
+        .read("a.html")
+        .contains("<p>See <a href=\"b.html#questions\">/b#questions</a></p>"));
+    assert!(site
+        .read("b.html")
+        .contains("<h1 id=\"questions\">Questions</h1>"));
+    assert!(site
+        .read("b.html")
+        .contains("<li><a href=\"a.html\">a</a></li>"));
+}
+
+#[test]
+fn it_only_rebuilds_pages_that_changed() {
+    let site = TempSite::new("incremental");
+    site.write("a.subtext", "# A\nSee /b");
+    site.write("b.subtext", "# B");
+    site.write("c.subtext", "# C");
+
+    build(&site.options()).unwrap();
+
+    let result = build(&site.options()).unwrap();
+    assert!(result.written.is_empty());
+    assert_eq!(result.unchanged.len(), 4);
+
+    // Changing the title of A changes the backlinks of B and the index
+    site.write("a.subtext", "# Alpha\nSee /b");
+    let result = build(&site.options()).unwrap();
+    assert_eq!(sorted(result.written), vec!["a", "b", "index"]);
+    assert_eq!(result.unchanged, vec!["c"]);
+
+    // Removing B removes its page, and breaks the link from A
+    site.remove("b.subtext");
+    let result = build(&site.options()).unwrap();
+    assert_eq!(sorted(result.written), vec!["a", "index"]);
+    assert_eq!(result.removed, vec!["b"]);
+    assert!(!site.root.join("site/b.html").exists());
+    assert_eq!(result.broken_links.len(), 1);
+
+    // A page that was deleted from the output is written again
+    fs::remove_file(site.root.join("site/c.html")).unwrap();
+    let result = build(&site.options()).unwrap();
+    assert_eq!(result.written, vec!["c"]);
+}
+
+#[test]
+fn it_rejects_notes_with_the_same_slug() {
+    let site = TempSite::new("duplicate");
+    site.write("Note.subtext", "One");
+    site.write("note.subtext", "Two");
+
+    assert!(build(&site.options()).is_err());
+}
@@ -0,0 +1,98 @@
+use std::{collections::BTreeSet, str::FromStr};
+
+use crate::{Slashlink, SlugPath};
+
+fn path(input: &str) -> SlugPath {
+    SlugPath::from_str(input).unwrap()
+}
+
+#[test]
+fn it_parses_and_validates_segments() {
+    let journal = path("/journal/2021-10-09");
+
+    assert_eq!(journal.segments(), &["journal", "2021-10-09"]);
+    assert_eq!(journal.name(), "2021-10-09");
+    assert_eq!(journal.to_string(), "journal/2021-10-09");
+    assert_eq!(path("climate/carbon_sinks"), path("/climate/carbon_sinks"));
+
+    for invalid in ["", "/", "a//b", "a/", "café", "a b", "a/./b", "a.b"] {
+        assert!(SlugPath::from_str(invalid).is_err(), "{:?}", invalid);
+    }
+    assert!(SlugPath::new(["journal", "a/b"]).is_err());
+    assert!(SlugPath::new(Vec::<String>::new()).is_err());
+}
+
+#[test]
+fn it_navigates_the_hierarchy() {
+    let sinks = path("climate/carbon-sinks");
+
+    assert_eq!(sinks.parent(), Some(path("climate")));
+    assert_eq!(path("climate").parent(), None);
+    assert_eq!(
+        path("climate").join("oceans/kelp").unwrap(),
+        path("climate/oceans/kelp")
+    );
+    assert!(path("climate").join("../oceans").is_err());
+
+    assert!(sinks.starts_with(&path("climate")));
+    assert!(sinks.starts_with(&sinks));
+    assert!(!path("climate-archive/old").starts_with(&path("climate")));
+}
+
+#[test]
+fn it_resolves_relative_paths() {
+    let sinks = path("climate/carbon-sinks/oceans");
+
+    assert_eq!(
+        sinks.resolve("forests").unwrap(),
+        path("climate/carbon-sinks/forests")
+    );
+    assert_eq!(
+        sinks.resolve("./forests/boreal").unwrap(),
+        path("climate/carbon-sinks/forests/boreal")
+    );
+    assert_eq!(sinks.resolve("../../energy").unwrap(), path("energy"));
+    assert_eq!(sinks.resolve("/journal").unwrap(), path("journal"));
+    assert!(sinks.resolve("../../../energy").is_err());
+    assert!(sinks.resolve("bad name").is_err());
+}
+
+#[test]
+fn it_lists_everything_under_a_prefix() {
+    let paths = [
+        "journal",
+        "journal/2021-10-09",
+        "journal/2021-10-10/evening",
+        "journal-archive",
+        "index",
+        "journal/2021-10-10",
+    ]
+    .iter()
+    .map(|input| path(input))
+    .collect::<BTreeSet<SlugPath>>();
+
+    assert_eq!(
+        path("journal")
+            .descendants(&paths)
+            .map(|path| path.to_string())
+            .collect::<Vec<String>>(),
+        vec![
+            "journal/2021-10-09",
+            "journal/2021-10-10",
+            "journal/2021-10-10/evening"
+        ]
+    );
+    assert_eq!(path("index").descendants(&paths).count(), 0);
+}
+
+#[test]
+fn it_converts_to_and_from_slashlinks() {
+    let slashlink = Slashlink::from_str("/Journal//2021-10-09/").unwrap();
+
+    assert_eq!(slashlink.slug_path(), Some(path("journal/2021-10-09")));
+    assert_eq!(
+        path("journal/2021-10-09").to_slashlink().to_string(),
+        "/journal/2021-10-09"
+    );
+    assert_eq!(Slashlink::from_str("@cdata").unwrap().slug_path(), None);
+}
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    block::Block,
+    parse,
+    primitive::Entity,
+    render::LinkTarget,
+    transclude::{
+        Loader, Part, Resource, SkipReason, Skipped, TranscludeOptions, Transcluder, Transclusion,
+    },
+    Slashlink,
+};
+
+/// Serves documents from a map of `{slug}.{kind}` to content
+fn loader(files: &[(&str, &str)]) -> impl Loader {
+    let files = files
+        .iter()
+        .map(|(name, content)| (name.to_string(), content.to_string()))
+        .collect::<HashMap<String, String>>();
+
+    move |slashlink: &Slashlink| -> Result<Option<Resource>> {
+        let slug = slashlink.slug.clone().unwrap_or_default();
+        Ok(files.iter().find_map(|(name, content)| {
+            let (name, kind) = name.rsplit_once('.')?;
+            (name == slug).then(|| Resource {
+                kind: kind.to_owned(),
+                content: content.clone().into_bytes(),
+            })
+        }))
+    }
+}
+
+fn blocks(input: &str) -> Vec<Block<Entity>> {
+    parse(input.as_bytes()).unwrap().collect()
+}
+
+fn to_subtext(transclusion: &Transclusion) -> String {
+    transclusion
+        .to_blocks()
+        .unwrap()
+        .iter()
+        .map(|block| format!("{}\n", block.to_string()))
+        .collect()
+}
+
+#[test]
+fn it_splices_linked_subtext_in_place_of_standalone_slashlinks() {
+    let transcluder = Transcluder::new(loader(&[(
+        "finches.subtext",
+        "Finches have varied beaks.\n- Ground finch\n- Tree finch",
+    )]));
+
+    let transclusion = transcluder.transclude(&blocks("# Birds\n/finches\nSee /finches too"));
+    assert!(transclusion.skipped.is_empty());
+    assert_eq!(
+        to_subtext(&transclusion),
+        "# Birds\nFinches have varied beaks.\n- Ground finch\n- Tree finch\nSee /finches too\n"
+    );
+}
+
+#[test]
+fn it_transcludes_sections_blocks_and_excerpts() {
+    let files = [(
+        "notes.subtext",
+        "# Overview\nEvolution emerges.\n\n# Questions\n- Which systems evolve?\n\n# Sources\nDarwin",
+    )];
+
+    let transclusion = Transcluder::new(loader(&files)).transclude(&blocks("/notes#questions"));
+    assert_eq!(
+        to_subtext(&transclusion),
+        "# Questions\n- Which systems evolve?\n"
+    );
+
+    let transclusion = Transcluder::new(loader(&files))
+        .options(TranscludeOptions {
+            excerpt: Some(2),
+            ..Default::default()
+        })
+        .transclude(&blocks("/notes"));
+    assert_eq!(
+        to_subtext(&transclusion),
+        "# Overview\nEvolution emerges.\n"
+    );
+
+    let transclusion = Transcluder::new(loader(&files)).transclude(&blocks("/notes#nowhere"));
+    assert_eq!(to_subtext(&transclusion), "/notes#nowhere\n");
+    assert_eq!(
+        transclusion.skipped,
+        vec![Skipped {
+            link: "/notes#nowhere".into(),
+            reason: SkipReason::MissingAnchor
+        }]
+    );
+}
+
+#[test]
+fn it_transcludes_recursively_up_to_a_depth_limit() {
+    let files = [
+        ("a.subtext", "A\n/b"),
+        ("b.subtext", "B\n/c"),
+        ("c.subtext", "C"),
+    ];
+
+    let transclusion = Transcluder::new(loader(&files)).transclude(&blocks("/a"));
+    assert_eq!(to_subtext(&transclusion), "A\nB\nC\n");
+
+    let transclusion = Transcluder::new(loader(&files))
+        .options(TranscludeOptions {
+            max_depth: 2,
+            ..Default::default()
+        })
+        .transclude(&blocks("/a"));
+    assert_eq!(to_subtext(&transclusion), "A\nB\n/c\n");
+    assert_eq!(transclusion.skipped[0].reason, SkipReason::TooDeep);
+}
+
+#[test]
+fn it_leaves_cycles_and_missing_documents_as_links() {
+    let files = [
+        ("a.subtext", "A\n/b#part"),
+        ("b.subtext", "B\n/a\n/missing"),
+    ];
+
+    let transclusion = Transcluder::new(loader(&files)).transclude_link(&"/a".parse().unwrap());
+    assert_eq!(to_subtext(&transclusion), "A\n/b#part\n");
+    assert_eq!(transclusion.skipped[0].reason, SkipReason::MissingAnchor);
+
+    let files = [("a.subtext", "A\n/b"), ("b.subtext", "B\n/a\n/missing")];
+    let transclusion = Transcluder::new(loader(&files)).transclude_link(&"/a".parse().unwrap());
+    assert_eq!(to_subtext(&transclusion), "A\nB\n/a\n/missing\n");
+    assert_eq!(
+        transclusion.skipped,
+        vec![
+            Skipped {
+                link: "/a".into(),
+                reason: SkipReason::Cycle
+            },
+            Skipped {
+                link: "/missing".into(),
+                reason: SkipReason::NotFound
+            },
+        ]
+    );
+}
+
+#[test]
+fn it_renders_csv_as_a_table() {
+    let files = [(
+        "finches.csv",
+        "name,beak\r\n\"Ground finch\",\"short, \"\"thick\"\"\"\r\nTree finch\r\n",
+    )];
+
+    let transclusion = Transcluder::new(loader(&files)).transclude(&blocks("/finches"));
+    assert_eq!(
+        transclusion.parts,
+        vec![Part::Table(vec![
+            vec!["name".into(), "beak".into()],
+            vec!["Ground finch".into(), "short, \"thick\"".into()],
+            vec!["Tree finch".into(), "".into()],
+        ])]
+    );
+    assert_eq!(
+        to_subtext(&transclusion),
+        "- name | beak\n- Ground finch | short, \"thick\"\n- Tree finch |\n"
+    );
+
+    let resolver = |_: &LinkTarget| None;
+    assert_eq!(
+        Transcluder::new(loader(&files))
+            .transclude(&blocks("Finches:\n/finches"))
+            .to_html(&resolver),
+        "<p>Finches:</p>\n<table>\n<thead><tr><th>name</th><th>beak</th></tr></thead>\n<tbody>\n<tr><td>Ground finch</td><td>short, &quot;thick&quot;</td></tr>\n<tr><td>Tree finch</td><td></td></tr>\n</tbody>\n</table>"
+    );
+}
+
+#[test]
+fn it_renders_yaml_as_a_list() {
+    let files = [(
+        "finch.yaml",
+        r#"---
+# A Darwin's finch
+name: "Ground finch"
+beak:
+  shape: short  # and thick
+  uses: [seeds, 'insects']
+islands:
+- name: Santa Cruz
+  visited: 1835
+- Isabela
+notes: |
+  Observed on the
+  Beagle voyage # in 1835
+usage: >-
+  Use # for headers
+"#,
+    )];
+
+    let transclusion = Transcluder::new(loader(&files)).transclude(&blocks("/finch"));
+    assert_eq!(
+        to_subtext(&transclusion),
+        "- name: Ground finch
+- beak / shape: short
+- beak / uses: seeds
+- beak / uses: insects
+- islands / name: Santa Cruz
+- islands / visited: 1835
+- islands: Isabela
+- notes: Observed on the Beagle voyage # in 1835
+- usage: Use # for headers
+"
+    );
+
+    let files = [(
+        "items.yaml",
+        "items:\n  - |\n    line one\n    line two\n  - plain\ntags: [a, \"b, c\", 'd, e', f]\n",
+    )];
+    let transclusion = Transcluder::new(loader(&files)).transclude(&blocks("/items"));
+    assert_eq!(
+        to_subtext(&transclusion),
+        "- items: line one line two
+- items: plain
+- tags: a
+- tags: b, c
+- tags: d, e
+- tags: f
+"
+    );
+
+    let files = [("broken.yaml", "a: 1\n   b: 2")];
+    let transclusion = Transcluder::new(loader(&files)).transclude(&blocks("/broken"));
+    assert!(matches!(
+        transclusion.skipped[0].reason,
+        SkipReason::Failed(_)
+    ));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn it_renders_json_as_a_list() {
+    let files = [(
+        "finch.json",
+        r#"{"name": "Ground finch", "beak": {"zeta": 1, "alpha": 12.5}, "islands": ["Isabela"], "extinct": false}"#,
+    )];
+
+    let transclusion = Transcluder::new(loader(&files)).transclude(&blocks("/finch"));
+    assert_eq!(
+        to_subtext(&transclusion),
+        "- name: Ground finch\n- beak / zeta: 1\n- beak / alpha: 12.5\n- islands: Isabela\n- extinct: false\n"
+    );
+}
+
+#[test]
+fn it_uses_registered_adapters_and_reports_unsupported_kinds() {
+    let files = [("poem.txt", "Roses are red"), ("data.bin", "...")];
+    let upper = |content: &str| -> Result<Vec<Part>> {
+        Ok(blocks(&content.to_uppercase())
+            .into_iter()
+            .map(Part::Block)
+            .collect())
+    };
+
+    let transclusion = Transcluder::new(loader(&files))
+        .adapter("txt", upper)
+        .transclude(&blocks("/poem\n/data"));
+    assert_eq!(to_subtext(&transclusion), "ROSES ARE RED\n/data\n");
+    assert_eq!(
+        transclusion.skipped[0].reason,
+        SkipReason::Unsupported("bin".into())
+    );
+
+    let failing = |_: &Slashlink| -> Result<Option<Resource>> { Err(anyhow!("Offline")) };
+    let transclusion = Transcluder::new(failing).transclude(&blocks("/poem"));
+    assert_eq!(
+        transclusion.skipped[0].reason,
+        SkipReason::Failed("Offline".into())
+    );
+}
@@ -0,0 +1,473 @@
+//! Adapters that turn linked documents of other kinds into parts of a
+//! Subtext document.
+//!
+//! Tree-shaped data (YAML, JSON) becomes a list with one item per value,
+//! labelled with the path of keys that leads to it, such as
+//! `finch / beak: short`. Items of a sequence share the label of the
+//! sequence.
+use anyhow::{anyhow, Result};
+
+use crate::convert::{
+    opml::{from_opml, OpmlOptions},
+    Writer,
+};
+
+use super::Part;
+
+/// Turns the content of a linked document into parts. Any
+/// `Fn(&str) -> Result<Vec<Part>>` closure is an adapter.
+pub trait Adapter {
+    fn adapt(&self, content: &str) -> Result<Vec<Part>>;
+}
+
+impl<F> Adapter for F
+where
+    F: Fn(&str) -> Result<Vec<Part>>,
+{
+    fn adapt(&self, content: &str) -> Result<Vec<Part>> {
+        self(content)
+    }
+}
+
+/// Renders CSV (RFC 4180) as a table whose first row is the header
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CsvAdapter;
+
+impl Adapter for CsvAdapter {
+    fn adapt(&self, content: &str) -> Result<Vec<Part>> {
+        let mut rows = parse_csv(content)?;
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        rows.iter_mut()
+            .for_each(|row| row.resize(width, String::new()));
+
+        Ok(match rows.is_empty() {
+            true => Vec::new(),
+            false => vec![Part::Table(rows)],
+        })
+    }
+}
+
+fn parse_csv(content: &str) -> Result<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        match char {
+            '"' if quoted => match chars.peek() {
+                Some('"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                _ => quoted = false,
+            },
+            _ if quoted => field.push(char),
+            '"' if field.is_empty() => quoted = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => (),
+            '\n' => {
+                if !row.is_empty() || !field.is_empty() {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                }
+            }
+            _ => field.push(char),
+        }
+    }
+
+    if quoted {
+        return Err(anyhow!("Unterminated quoted field in CSV"));
+    }
+    if !row.is_empty() || !field.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+/// Renders an OPML outline the way [from_opml] imports it
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OpmlAdapter;
+
+impl Adapter for OpmlAdapter {
+    fn adapt(&self, content: &str) -> Result<Vec<Part>> {
+        let import = from_opml(content, &OpmlOptions::default())?;
+        Ok(import.blocks.into_iter().map(Part::Block).collect())
+    }
+}
+
+/// Renders YAML as a list of its values. Only the common block subset of
+/// YAML is understood: mappings, sequences, plain and quoted scalars,
+/// literal (`|`) and folded (`>`) scalars, and flow sequences of scalars.
+/// Anchors, tags and flow mappings are read as plain text.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct YamlAdapter;
+
+impl Adapter for YamlAdapter {
+    fn adapt(&self, content: &str) -> Result<Vec<Part>> {
+        to_list(&parse_yaml(content)?)
+    }
+}
+
+/// Renders JSON as a list of its values, in the order they are written
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonAdapter;
+
+#[cfg(feature = "serde")]
+impl Adapter for JsonAdapter {
+    fn adapt(&self, content: &str) -> Result<Vec<Part>> {
+        fn convert(value: serde_json::Value) -> Value {
+            match value {
+                serde_json::Value::String(text) => Value::Scalar(text),
+                serde_json::Value::Array(items) => {
+                    Value::List(items.into_iter().map(convert).collect())
+                }
+                serde_json::Value::Object(entries) => Value::Map(
+                    entries
+                        .into_iter()
+                        .map(|(key, value)| (key, convert(value)))
+                        .collect(),
+                ),
+                scalar => Value::Scalar(scalar.to_string()),
+            }
+        }
+
+        to_list(&convert(serde_json::from_str(content)?))
+    }
+}
+
+/// Tree-shaped data, as read from YAML or JSON
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Scalar(String),
+    List(Vec<Value>),
+    Map(Vec<(String, Value)>),
+}
+
+/// One list item per scalar, labelled with the keys that lead to it
+fn to_list(value: &Value) -> Result<Vec<Part>> {
+    fn flatten(value: &Value, path: &mut Vec<String>, items: &mut Vec<String>) {
+        let label = path.join(" / ");
+
+        match value {
+            Value::Scalar(text) => items.push(match (label.is_empty(), text.is_empty()) {
+                (true, _) => text.clone(),
+                (false, true) => label,
+                (false, false) => format!("{}: {}", label, text),
+            }),
+            Value::List(values) if values.is_empty() => items.push(label),
+            Value::Map(entries) if entries.is_empty() => items.push(label),
+            Value::List(values) => values.iter().for_each(|value| flatten(value, path, items)),
+            Value::Map(entries) => {
+                for (key, value) in entries {
+                    path.push(key.clone());
+                    flatten(value, path, items);
+                    path.pop();
+                }
+            }
+        }
+    }
+
+    let mut items = Vec::new();
+    flatten(value, &mut Vec::new(), &mut items);
+
+    let mut writer = Writer::default();
+    items
+        .iter()
+        .filter(|item| !item.trim().is_empty())
+        .for_each(|item| writer.list(item));
+
+    Ok(writer.into_blocks()?.into_iter().map(Part::Block).collect())
+}
+
+/// A line of YAML with its comment removed
+#[derive(Debug)]
+struct Line {
+    number: usize,
+    indent: usize,
+    text: String,
+}
+
+/// Remove a `#` comment that is not inside quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+
+    for (index, char) in line.char_indices() {
+        match (quote, char) {
+            (None, '"' | '\'') if previous.is_whitespace() || "[,".contains(previous) => {
+                quote = Some(char)
+            }
+            (Some(open), _) if char == open => quote = None,
+            (None, '#') if previous.is_whitespace() => return &line[..index],
+            _ => (),
+        }
+        previous = char;
+    }
+
+    line
+}
+
+/// Whether a line ends in a `|` or `>` block indicator, so that the lines
+/// indented under it are the text of a block scalar
+fn opens_block_scalar(text: &str) -> bool {
+    match split_key(text) {
+        Some((_, value)) => is_block_indicator(value),
+        None => is_block_indicator(text.strip_prefix("- ").unwrap_or_default().trim()),
+    }
+}
+
+/// Whether a value is a `|` or `>` block indicator, with any chomping or
+/// indentation modifiers
+fn is_block_indicator(value: &str) -> bool {
+    value.starts_with(['|', '>'])
+        && value[1..]
+            .chars()
+            .all(|c| c == '-' || c == '+' || c.is_ascii_digit())
+}
+
+fn parse_yaml(content: &str) -> Result<Value> {
+    let mut lines = Vec::new();
+    // The indentation of the line that opened the current block scalar
+    let mut block_scalar: Option<usize> = None;
+
+    for (index, line) in content.lines().enumerate() {
+        let line_indent = line.len() - line.trim_start_matches(' ').len();
+        let in_block_scalar =
+            block_scalar.is_some_and(|indent| line_indent > indent || line.trim().is_empty());
+
+        // A `#` in a block scalar is text, not a comment
+        let text = match in_block_scalar {
+            true => line.trim_end(),
+            false => strip_comment(line).trim_end(),
+        };
+        let indent = text.len() - text.trim_start_matches(' ').len();
+
+        if !in_block_scalar {
+            block_scalar = opens_block_scalar(text.trim_start()).then_some(indent);
+        }
+
+        if text.trim().is_empty() || text == "---" || text == "..." {
+            continue;
+        }
+        if text[indent..].starts_with('\t') {
+            return Err(anyhow!("Tab indentation in YAML at line {}", index + 1));
+        }
+
+        lines.push(Line {
+            number: index + 1,
+            indent,
+            text: text[indent..].to_owned(),
+        });
+    }
+
+    let Some(indent) = lines.first().map(|line| line.indent) else {
+        return Ok(Value::Map(Vec::new()));
+    };
+
+    let mut index = 0;
+    let value = parse_node(&mut lines, &mut index, indent)?;
+
+    match lines.get(index) {
+        Some(line) => Err(anyhow!(
+            "Unexpected indentation in YAML at line {}",
+            line.number
+        )),
+        None => Ok(value),
+    }
+}
+
+fn is_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Split `key: value` into its key and value
+fn split_key(text: &str) -> Option<(String, &str)> {
+    let end = match text.chars().next()? {
+        quote @ ('"' | '\'') => text[1..].find(quote)? + 2,
+        '[' | '{' => return None,
+        _ => text
+            .find(": ")
+            .or_else(|| text.strip_suffix(':').map(str::len))?,
+    };
+
+    let rest = text[end..].trim_start();
+    let value = rest.strip_prefix(':')?;
+    if !value.is_empty() && !value.starts_with(' ') {
+        return None;
+    }
+
+    Some((unquote(text[..end].trim()), value.trim()))
+}
+
+fn unquote(text: &str) -> String {
+    if text.len() >= 2 && text.starts_with('"') && text.ends_with('"') {
+        let mut unquoted = String::new();
+        let mut chars = text[1..text.len() - 1].chars();
+
+        while let Some(char) = chars.next() {
+            match (char, char == '\\') {
+                (_, true) => match chars.next() {
+                    Some('n') => unquoted.push('\n'),
+                    Some('t') => unquoted.push('\t'),
+                    Some(escaped) => unquoted.push(escaped),
+                    None => unquoted.push('\\'),
+                },
+                _ => unquoted.push(char),
+            }
+        }
+
+        return unquoted;
+    }
+
+    if text.len() >= 2 && text.starts_with('\'') && text.ends_with('\'') {
+        return text[1..text.len() - 1].replace("''", "'");
+    }
+
+    text.to_owned()
+}
+
+fn scalar(text: &str) -> Value {
+    match text
+        .strip_prefix('[')
+        .and_then(|text| text.strip_suffix(']'))
+    {
+        Some(items) if items.trim().is_empty() => Value::List(Vec::new()),
+        Some(items) => Value::List(
+            split_flow_items(items)
+                .into_iter()
+                .map(|item| Value::Scalar(unquote(item.trim())))
+                .collect(),
+        ),
+        None => Value::Scalar(unquote(text)),
+    }
+}
+
+/// Split the items of a flow sequence on the commas that are not inside
+/// quotes
+fn split_flow_items(items: &str) -> Vec<&str> {
+    let mut quote = None;
+    let mut previous = ',';
+    let mut start = 0;
+    let mut parts = Vec::new();
+
+    for (index, char) in items.char_indices() {
+        match (quote, char) {
+            (None, '"' | '\'') if previous.is_whitespace() || previous == ',' => quote = Some(char),
+            (Some(open), _) if char == open => quote = None,
+            (None, ',') => {
+                parts.push(&items[start..index]);
+                start = index + 1;
+            }
+            _ => (),
+        }
+        previous = char;
+    }
+
+    parts.push(&items[start..]);
+    parts
+}
+
+/// The text of a block scalar, from the lines indented under the line that
+/// opened it
+fn block_scalar(lines: &[Line], index: &mut usize, indent: usize, indicator: &str) -> Value {
+    let mut parts = Vec::new();
+    while let Some(line) = lines.get(*index).filter(|line| line.indent > indent) {
+        parts.push(line.text.clone());
+        *index += 1;
+    }
+    let separator = if indicator.starts_with('|') {
+        "\n"
+    } else {
+        " "
+    };
+    Value::Scalar(parts.join(separator))
+}
+
+fn parse_node(lines: &mut [Line], index: &mut usize, indent: usize) -> Result<Value> {
+    let text = &lines[*index].text;
+
+    if is_item(text) {
+        parse_list(lines, index, indent)
+    } else if split_key(text).is_some() {
+        parse_map(lines, index, indent)
+    } else {
+        *index += 1;
+        Ok(scalar(&lines[*index - 1].text))
+    }
+}
+
+/// The value nested under a line that ends in `:` or `-`
+fn parse_child(lines: &mut [Line], index: &mut usize, indent: usize) -> Result<Value> {
+    match lines.get(*index) {
+        Some(line) if line.indent > indent => {
+            let indent = line.indent;
+            parse_node(lines, index, indent)
+        }
+        _ => Ok(Value::Scalar(String::new())),
+    }
+}
+
+fn parse_list(lines: &mut [Line], index: &mut usize, indent: usize) -> Result<Value> {
+    let mut items = Vec::new();
+
+    while let Some(line) = lines.get_mut(*index) {
+        if line.indent != indent || !is_item(&line.text) {
+            break;
+        }
+
+        let rest = line.text[1..].trim_start().to_owned();
+        if rest.is_empty() {
+            *index += 1;
+            items.push(parse_child(lines, index, indent)?);
+        } else if is_block_indicator(&rest) {
+            *index += 1;
+            items.push(block_scalar(lines, index, indent, &rest));
+        } else {
+            // Read whatever follows the dash as though it started its own
+            // line, so that `- key: value` begins a mapping
+            let offset = line.text.len() - rest.len();
+            line.indent += offset;
+            line.text = rest;
+            items.push(parse_node(lines, index, indent + offset)?);
+        }
+    }
+
+    Ok(Value::List(items))
+}
+
+fn parse_map(lines: &mut [Line], index: &mut usize, indent: usize) -> Result<Value> {
+    let mut entries = Vec::new();
+
+    while let Some(line) = lines.get(*index) {
+        if line.indent != indent {
+            break;
+        }
+
+        let number = line.number;
+        let (key, value) = split_key(&line.text)
+            .map(|(key, value)| (key, value.to_owned()))
+            .ok_or_else(|| anyhow!("Expected a key in YAML at line {}", number))?;
+        *index += 1;
+
+        let value = match value.as_str() {
+            "" => match lines.get(*index) {
+                // A sequence may sit at the same indentation as its key
+                Some(next) if next.indent == indent && is_item(&next.text) => {
+                    parse_list(lines, index, indent)?
+                }
+                _ => parse_child(lines, index, indent)?,
+            },
+            block if block.starts_with(['|', '>']) => block_scalar(lines, index, indent, block),
+            value => scalar(value),
+        };
+
+        entries.push((key, value));
+    }
+
+    Ok(Value::Map(entries))
+}
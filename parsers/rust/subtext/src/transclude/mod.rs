@@ -0,0 +1,435 @@
+//! Transclusion: rendering linked documents in place of the links to them.
+//!
+//! A paragraph that holds nothing but a slashlink is replaced by the
+//! document it links to, loaded through a [Loader]. Linked Subtext is
+//! spliced in block for block, either in full, as an excerpt of its first
+//! few blocks, or as the block or section named by the slashlink's fragment
+//! (see [crate::anchor]); its own standalone slashlinks are transcluded in
+//! turn. Other kinds of document go through an [Adapter]: CSV becomes a
+//! table, and YAML, JSON and OPML become lists.
+//!
+//! A link is left as it is when its target can't be transcluded: when it
+//! is missing, has no adapter, is nested too deeply, or would transclude a
+//! document into itself. Each such link is reported as [Skipped].
+pub mod adapter;
+
+pub use adapter::{Adapter, CsvAdapter, OpmlAdapter, YamlAdapter};
+
+#[cfg(feature = "serde")]
+pub use adapter::JsonAdapter;
+
+use std::{collections::HashMap, fmt::Display, path::PathBuf, str::FromStr};
+
+use anyhow::Result;
+
+use crate::{
+    anchor::{resolve, Anchor},
+    block::Block,
+    primitive::Entity,
+    render::{
+        html::{escape_html, render_html as render_blocks_html},
+        LinkResolver,
+    },
+    Peer, Slashlink,
+};
+
+/// A linked document as it was loaded
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resource {
+    /// The kind of document, as a file extension such as `subtext` or `csv`
+    pub kind: String,
+    pub content: Vec<u8>,
+}
+
+/// Loads the documents that slashlinks refer to. Any
+/// `Fn(&Slashlink) -> Result<Option<Resource>>` closure is a loader.
+pub trait Loader {
+    /// Load the document a slashlink refers to, or `None` if there is no
+    /// such document
+    fn load(&self, slashlink: &Slashlink) -> Result<Option<Resource>>;
+}
+
+impl<F> Loader for F
+where
+    F: Fn(&Slashlink) -> Result<Option<Resource>>,
+{
+    fn load(&self, slashlink: &Slashlink) -> Result<Option<Resource>> {
+        self(slashlink)
+    }
+}
+
+/// The kinds of document a [DirectoryLoader] looks for, in order
+const KINDS: &[&str] = &["subtext", "csv", "json", "yaml", "yml", "opml"];
+
+/// Loads `{slug}.{kind}` from a directory, for slashlinks without a peer
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectoryLoader {
+    pub root: PathBuf,
+}
+
+impl DirectoryLoader {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        DirectoryLoader { root: root.into() }
+    }
+}
+
+impl Loader for DirectoryLoader {
+    fn load(&self, slashlink: &Slashlink) -> Result<Option<Resource>> {
+        let slug = match (&slashlink.peer, &slashlink.slug) {
+            (Peer::None, Some(slug)) => slug,
+            _ => return Ok(None),
+        };
+
+        for kind in KINDS {
+            let path = self.root.join(format!("{}.{}", slug, kind));
+            if path.is_file() {
+                return Ok(Some(Resource {
+                    kind: kind.to_string(),
+                    content: std::fs::read(path)?,
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// A piece of a document after transclusion
+#[derive(Debug, Clone, PartialEq)]
+pub enum Part {
+    Block(Block<Entity>),
+    /// Rows of cells, the first of which is the header row
+    Table(Vec<Vec<String>>),
+}
+
+/// Why a link was not transcluded
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipReason {
+    /// The loader found no document
+    NotFound,
+    /// The document is already being transcluded further up
+    Cycle,
+    /// Transclusions are nested deeper than [TranscludeOptions::max_depth]
+    TooDeep,
+    /// The fragment matches no block or section of the document
+    MissingAnchor,
+    /// There is no adapter for this kind of document
+    Unsupported(String),
+    /// The document could not be loaded or read
+    Failed(String),
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::NotFound => write!(f, "not found"),
+            SkipReason::Cycle => write!(f, "transcludes itself"),
+            SkipReason::TooDeep => write!(f, "nested too deeply"),
+            SkipReason::MissingAnchor => write!(f, "no such block or section"),
+            SkipReason::Unsupported(kind) => write!(f, "unsupported kind {:?}", kind),
+            SkipReason::Failed(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+/// A link that was left in place rather than transcluded
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skipped {
+    /// The slashlink as it was written
+    pub link: String,
+    pub reason: SkipReason,
+}
+
+/// The result of transcluding a document
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transclusion {
+    pub parts: Vec<Part>,
+    pub skipped: Vec<Skipped>,
+}
+
+impl Transclusion {
+    /// The parts as blocks, with each row of a table written as a list item
+    /// of cells separated by `|`
+    pub fn to_blocks(&self) -> Result<Vec<Block<Entity>>> {
+        let mut blocks = Vec::new();
+
+        for part in &self.parts {
+            match part {
+                Part::Block(block) => blocks.push(block.clone()),
+                Part::Table(rows) => {
+                    let mut writer = crate::convert::Writer::default();
+                    rows.iter().for_each(|row| writer.list(&row.join(" | ")));
+                    blocks.append(&mut writer.into_blocks()?);
+                }
+            }
+        }
+
+        Ok(blocks)
+    }
+
+    /// Render the parts as HTML, with tables as `<table>` elements
+    pub fn to_html(&self, resolver: &impl LinkResolver) -> String {
+        let mut elements = Vec::<String>::new();
+        let mut blocks = Vec::<Block<Entity>>::new();
+
+        for part in self.parts.iter().chain([&Part::Table(Vec::new())]) {
+            match part {
+                Part::Block(block) => blocks.push(block.clone()),
+                Part::Table(rows) => {
+                    if !blocks.is_empty() {
+                        elements.push(render_blocks_html(&blocks, resolver));
+                        blocks.clear();
+                    }
+                    if !rows.is_empty() {
+                        elements.push(render_table(rows));
+                    }
+                }
+            }
+        }
+
+        elements.retain(|element| !element.is_empty());
+        elements.join("\n")
+    }
+}
+
+fn render_table(rows: &[Vec<String>]) -> String {
+    let row = |cells: &Vec<String>, tag: &str| {
+        let cells = cells
+            .iter()
+            .map(|cell| format!("<{}>{}</{}>", tag, escape_html(cell), tag))
+            .collect::<String>();
+        format!("<tr>{}</tr>", cells)
+    };
+
+    let mut lines = vec!["<table>".to_owned()];
+    if let Some((header, body)) = rows.split_first() {
+        lines.push(format!("<thead>{}</thead>", row(header, "th")));
+        if !body.is_empty() {
+            lines.push("<tbody>".into());
+            lines.extend(body.iter().map(|cells| row(cells, "td")));
+            lines.push("</tbody>".into());
+        }
+    }
+    lines.push("</table>".into());
+
+    lines.join("\n")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscludeOptions {
+    /// How many transclusions deep to go; `0` transcludes nothing
+    pub max_depth: usize,
+    /// Transclude only this many blocks (not counting blank lines) of
+    /// linked Subtext that has no fragment
+    pub excerpt: Option<usize>,
+}
+
+impl Default for TranscludeOptions {
+    fn default() -> Self {
+        TranscludeOptions {
+            max_depth: 3,
+            excerpt: None,
+        }
+    }
+}
+
+/// The slashlink of a paragraph that holds nothing else
+fn standalone_slashlink(block: &Block<Entity>) -> Option<&str> {
+    let Block::Paragraph(_) = block else {
+        return None;
+    };
+
+    let mut entities = block
+        .to_content_entities()
+        .into_iter()
+        .filter(|entity| !matches!(entity, Entity::EmptySpace(_)));
+
+    match (entities.next(), entities.next()) {
+        (Some(Entity::SlashLink(text)), None) => Some(text.as_ref()),
+        _ => None,
+    }
+}
+
+/// Identifies a document regardless of which part of it a link refers to
+fn document_key(slashlink: &Slashlink) -> String {
+    Slashlink {
+        fragment: None,
+        ..slashlink.normalize()
+    }
+    .to_string()
+}
+
+/// The blocks of a document that a link asks for, without trailing blank
+/// lines
+fn select(
+    blocks: Vec<Block<Entity>>,
+    fragment: Option<&str>,
+    excerpt: Option<usize>,
+) -> Option<Vec<Block<Entity>>> {
+    let mut blocks = match (fragment, excerpt) {
+        (Some(fragment), _) => match resolve(&blocks, fragment)? {
+            Anchor::Block(index) => vec![blocks[index].clone()],
+            Anchor::Section(range) => blocks[range].to_vec(),
+        },
+        (None, Some(excerpt)) => {
+            let mut count = 0;
+            blocks
+                .into_iter()
+                .take_while(|block| {
+                    if !matches!(block, Block::Blank(_)) {
+                        count += 1;
+                    }
+                    count <= excerpt
+                })
+                .collect()
+        }
+        (None, None) => blocks,
+    };
+
+    while matches!(blocks.last(), Some(Block::Blank(_))) {
+        blocks.pop();
+    }
+
+    Some(blocks)
+}
+
+/// Transcludes linked documents into a document
+pub struct Transcluder<L: Loader> {
+    loader: L,
+    adapters: HashMap<String, Box<dyn Adapter>>,
+    options: TranscludeOptions,
+}
+
+impl<L: Loader> Transcluder<L> {
+    /// A transcluder with the default options and the built-in adapters
+    pub fn new(loader: L) -> Self {
+        let transcluder = Transcluder {
+            loader,
+            adapters: HashMap::new(),
+            options: TranscludeOptions::default(),
+        }
+        .adapter("csv", CsvAdapter)
+        .adapter("yaml", YamlAdapter)
+        .adapter("yml", YamlAdapter)
+        .adapter("opml", OpmlAdapter);
+
+        #[cfg(feature = "serde")]
+        let transcluder = transcluder.adapter("json", JsonAdapter);
+
+        transcluder
+    }
+
+    pub fn options(mut self, options: TranscludeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Use an adapter for documents of the given kind, replacing any adapter
+    /// that was already registered for it
+    pub fn adapter(mut self, kind: &str, adapter: impl Adapter + 'static) -> Self {
+        self.adapters.insert(kind.to_owned(), Box::new(adapter));
+        self
+    }
+
+    /// Transclude the standalone slashlinks of a document
+    pub fn transclude(&self, blocks: &[Block<Entity>]) -> Transclusion {
+        let mut transclusion = Transclusion::default();
+        self.expand(blocks, &mut Vec::new(), &mut transclusion);
+        transclusion
+    }
+
+    /// Load a document and transclude its standalone slashlinks. Links back
+    /// to the document itself are treated as cycles.
+    pub fn transclude_link(&self, slashlink: &Slashlink) -> Transclusion {
+        let mut transclusion = Transclusion::default();
+
+        match self.include(slashlink, &mut Vec::new(), &mut transclusion) {
+            Ok(mut parts) => transclusion.parts.append(&mut parts),
+            Err(reason) => transclusion.skipped.push(Skipped {
+                link: slashlink.to_string(),
+                reason,
+            }),
+        }
+
+        transclusion
+    }
+
+    fn expand(
+        &self,
+        blocks: &[Block<Entity>],
+        stack: &mut Vec<String>,
+        transclusion: &mut Transclusion,
+    ) {
+        for block in blocks {
+            let Some(link) = standalone_slashlink(block) else {
+                transclusion.parts.push(Part::Block(block.clone()));
+                continue;
+            };
+
+            let included = match Slashlink::from_str(link) {
+                Ok(slashlink) => self.include(&slashlink, stack, transclusion),
+                Err(error) => Err(SkipReason::Failed(error.to_string())),
+            };
+
+            match included {
+                Ok(mut parts) => transclusion.parts.append(&mut parts),
+                Err(reason) => {
+                    transclusion.skipped.push(Skipped {
+                        link: link.to_owned(),
+                        reason,
+                    });
+                    transclusion.parts.push(Part::Block(block.clone()));
+                }
+            }
+        }
+    }
+
+    /// Load a linked document and turn it into parts, recording any links
+    /// inside it that are skipped
+    fn include(
+        &self,
+        slashlink: &Slashlink,
+        stack: &mut Vec<String>,
+        transclusion: &mut Transclusion,
+    ) -> Result<Vec<Part>, SkipReason> {
+        let key = document_key(slashlink);
+        if stack.contains(&key) {
+            return Err(SkipReason::Cycle);
+        }
+        if stack.len() >= self.options.max_depth {
+            return Err(SkipReason::TooDeep);
+        }
+
+        let resource = self
+            .loader
+            .load(slashlink)
+            .map_err(|error| SkipReason::Failed(error.to_string()))?
+            .ok_or(SkipReason::NotFound)?;
+
+        if resource.kind != "subtext" {
+            let adapter = self
+                .adapters
+                .get(&resource.kind)
+                .ok_or_else(|| SkipReason::Unsupported(resource.kind.clone()))?;
+            let content = String::from_utf8(resource.content)
+                .map_err(|error| SkipReason::Failed(error.to_string()))?;
+            return adapter
+                .adapt(&content)
+                .map_err(|error| SkipReason::Failed(error.to_string()));
+        }
+
+        let blocks = crate::parse(&resource.content)
+            .map_err(|error| SkipReason::Failed(error.to_string()))?
+            .collect();
+        let blocks = select(blocks, slashlink.fragment.as_deref(), self.options.excerpt)
+            .ok_or(SkipReason::MissingAnchor)?;
+
+        let mut nested = Transclusion::default();
+        stack.push(key);
+        self.expand(&blocks, stack, &mut nested);
+        stack.pop();
+
+        transclusion.skipped.append(&mut nested.skipped);
+        Ok(nested.parts)
+    }
+}
@@ -111,37 +111,38 @@ def blocks_to_plain(blocks):
         yield f"{block.value}"
 
 
-_SLASHLINK = re.compile(
-    "(^|\s)(/[a-zA-Z0-9/\-\_]+)"
-)
-
-_BARELINK = re.compile(
-    "(^|\s)(https?://[^\s>]+)[\.,;]?"
-)
-
-_BRACKETLINK = re.compile(
-    "<([^>\s]+)>"
+_INLINE = re.compile(
+    r"(?<!\S)(?P<bracketlink><(?P<bracketurl>[^<>\s]+)>)(?=\s|$)"
+    r"|(?<!\S)(?P<barelink>https?://[^\s>]+?)(?=[\.,;]?(\s|$))"
+    r"|(?<!\S)(?P<slashlink>/[a-zA-Z0-9/\-\_]+)"
 )
 
 
-def _repl_barelink(matchobj):
-    url = matchobj.group(2)
-    text = html.escape(url)
-    return f'<a href="{url}">{text}</a>'
-
-
-def _repl_bracketlink(matchobj):
-    url = matchobj.group(1)
-    text = html.escape(url)
-    return f'<a href="{url}">{text}</a>'
+def _render_link(url):
+    href = html.escape(url)
+    return f'<a href="{href}">{href}</a>'
 
 
 def render_inline_html(line, slashlink):
-    def _repl_slashlink(matchobj):
-        return slashlink(matchobj.group(2))
-    line = _SLASHLINK_RE.sub(_repl_slashlink, line)
-    line = _BARELINK.sub(_repl_barelink, link)
-    line = _BARELINK.sub(_repl_bracketlink, link)
+    """
+    Render a line of Subtext text as inline HTML.
+    Text is escaped. Bare and bracketed URLs become anchors, and slashlinks
+    are rendered by calling `slashlink` with the slashlink text, which should
+    return an HTML string.
+    """
+    parts = []
+    cursor = 0
+    for match in _INLINE.finditer(line):
+        parts.append(html.escape(line[cursor:match.start()]))
+        if match.group("bracketlink"):
+            parts.append(_render_link(match.group("bracketurl")))
+        elif match.group("barelink"):
+            parts.append(_render_link(match.group("barelink")))
+        else:
+            parts.append(slashlink(match.group("slashlink")))
+        cursor = match.end()
+    parts.append(html.escape(line[cursor:]))
+    return "".join(parts)
 
 
 BlockGroup = namedtuple("BlockGroup", ("type", "value"))